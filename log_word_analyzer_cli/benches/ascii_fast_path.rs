@@ -0,0 +1,106 @@
+//! Compares the ASCII tokenizing fast path against the plain Unicode path.
+//!
+//! Run with `cargo bench --bench ascii_fast_path`. The baseline below is
+//! the tokenization the crate used before the ASCII fast path existed.
+//! Counting end to end is reported as well; there, hashing takes most of
+//! the time, so the gain is smaller than for tokenizing alone.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use log_word_analyzer_cli::{count_words, for_each_word};
+
+/// Tokenizes with Unicode lowercasing and char-based splitting.
+fn for_each_word_unicode(line: &str, mut f: impl FnMut(&str)) {
+    for word in line
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+    {
+        if !word.is_empty() {
+            f(word);
+        }
+    }
+}
+
+/// Counts words exactly like `count_words`, but with the Unicode path.
+fn count_words_unicode(logs: &[String]) -> HashMap<String, usize> {
+    let mut frequency_map: HashMap<String, usize> = HashMap::new();
+    for line in logs {
+        for_each_word_unicode(line, |word| {
+            if let Some(count) = frequency_map.get_mut(word) {
+                *count += 1;
+            } else {
+                frequency_map.insert(word.to_string(), 1);
+            }
+        });
+    }
+    frequency_map
+}
+
+/// A tokenizing function under test, called with a per-word callback.
+type TokenizeFn = fn(&str, &mut dyn FnMut(&str));
+
+/// Returns the fastest of several timed runs.
+fn best_of(runs: usize, mut f: impl FnMut()) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Prints the timings of both paths and the resulting speedup.
+fn report(name: &str, unicode: Duration, fast: Duration) {
+    println!(
+        "{:<10} unicode {:>10.2?}  ascii {:>10.2?}  speedup {:.2}x",
+        name,
+        unicode,
+        fast,
+        unicode.as_secs_f64() / fast.as_secs_f64()
+    );
+}
+
+fn main() {
+    let logs: Vec<String> = (0..200_000)
+        .map(|i| {
+            format!(
+                "2024-05-01T10:{:02}:{:02}Z INFO [worker-{}] GET /api/v1/items?id={} status=200 took {}ms",
+                i / 60 % 60,
+                i % 60,
+                i % 16,
+                i % 1000,
+                i % 250
+            )
+        })
+        .collect();
+    assert_eq!(count_words(&logs), count_words_unicode(&logs));
+
+    let tokenize = |tokenizer: TokenizeFn| {
+        best_of(10, || {
+            let mut bytes = 0;
+            for line in &logs {
+                tokenizer(black_box(line), &mut |word| bytes += word.len());
+            }
+            black_box(bytes);
+        })
+    };
+    report(
+        "tokenize",
+        tokenize(|line, f| for_each_word_unicode(line, f)),
+        tokenize(|line, f| for_each_word(line, f)),
+    );
+
+    report(
+        "count",
+        best_of(10, || {
+            black_box(count_words_unicode(black_box(&logs)));
+        }),
+        best_of(10, || {
+            black_box(count_words(black_box(&logs)));
+        }),
+    );
+}
@@ -0,0 +1,119 @@
+//! Deviation of a log's word distribution from a baseline, to surface
+//! what is unusual about it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::count_words;
+use crate::severity::select_top_k_scored;
+
+/// Pseudo-count added to every word of the baseline, so words it never
+/// saw still have a small expected frequency.
+pub const SMOOTHING: f64 = 0.5;
+
+/// Scores every word by how far its observed count deviates from what the
+/// baseline's relative frequencies predict.
+///
+/// The expected count of a word is the observed token total times its
+/// smoothed baseline share, `(baseline + 0.5) / (baseline total + 0.5 *
+/// vocabulary)` over the words of both maps, and the score is its
+/// chi-square contribution `(observed - expected)² / expected`. Spikes
+/// and words that went missing both score high; compare the counts to
+/// tell them apart.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use log_word_analyzer_cli::baseline::deviation_scores;
+///
+/// let baseline = HashMap::from([("info".to_string(), 90), ("error".to_string(), 10)]);
+/// let observed = HashMap::from([("info".to_string(), 50), ("error".to_string(), 50)]);
+/// let scores = deviation_scores(&observed, &baseline);
+/// assert!(scores["error"] > scores["info"]);
+/// ```
+pub fn deviation_scores(
+    observed: &HashMap<String, usize>,
+    baseline: &HashMap<String, usize>,
+) -> HashMap<String, f64> {
+    let vocabulary: HashSet<&String> = observed.keys().chain(baseline.keys()).collect();
+    let observed_total: usize = observed.values().sum();
+    let baseline_total: usize = baseline.values().sum();
+    let smoothed_total = baseline_total as f64 + SMOOTHING * vocabulary.len() as f64;
+
+    vocabulary
+        .into_iter()
+        .map(|word| {
+            let share =
+                (baseline.get(word).copied().unwrap_or(0) as f64 + SMOOTHING) / smoothed_total;
+            let expected = observed_total as f64 * share;
+            let actual = observed.get(word).copied().unwrap_or(0) as f64;
+            let score = if expected > 0.0 {
+                (actual - expected).powi(2) / expected
+            } else {
+                0.0
+            };
+            (word.clone(), score)
+        })
+        .collect()
+}
+
+/// Finds the K words of `logs` deviating most from the word distribution
+/// of `baseline`, by [`deviation_scores`].
+///
+/// # Returns
+///
+/// `(word, score)` tuples sorted by score (descending) and alphabetically
+/// for ties.
+pub fn top_k_deviations(logs: &[String], baseline: &[String], k: usize) -> Vec<(String, f64)> {
+    select_top_k_scored(
+        deviation_scores(&count_words(logs), &count_words(baseline)),
+        k,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a word spiking relative to the baseline ranks first
+    #[test]
+    fn test_spiking_word_ranks_first() {
+        let baseline: Vec<String> = (0..100)
+            .map(|i| {
+                if i % 20 == 0 {
+                    "request timeout".to_string()
+                } else {
+                    "request ok".to_string()
+                }
+            })
+            .collect();
+        let current: Vec<String> = (0..100)
+            .map(|i| {
+                if i % 2 == 0 {
+                    "request timeout".to_string()
+                } else {
+                    "request ok".to_string()
+                }
+            })
+            .collect();
+
+        let result = top_k_deviations(&current, &baseline, 3);
+
+        assert_eq!(result[0].0, "timeout");
+        // "request" occurs on every line of both, as expected
+        assert_eq!(result[2].0, "request");
+        assert!(result[2].1 < 1.0);
+    }
+
+    /// Test that a word missing from the baseline gets a finite score
+    #[test]
+    fn test_unseen_word_is_smoothed() {
+        let baseline = HashMap::from([("ok".to_string(), 10)]);
+        let observed = HashMap::from([("ok".to_string(), 10), ("panic".to_string(), 3)]);
+
+        let scores = deviation_scores(&observed, &baseline);
+
+        assert!(scores["panic"].is_finite());
+        assert!(scores["panic"] > scores["ok"]);
+    }
+}
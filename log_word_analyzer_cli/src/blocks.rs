@@ -0,0 +1,67 @@
+//! Vocabulary statistics over consecutive blocks of lines.
+
+use crate::count_words;
+
+/// Counts the distinct words in each consecutive block of `block_lines`
+/// lines.
+///
+/// This shows how vocabulary richness changes with position in the log;
+/// a burst of new words stands out as a block with a high count. The last
+/// block may hold fewer lines.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `block_lines` - Number of lines per block; must be greater than zero
+///
+/// # Returns
+///
+/// `(block_index, distinct_words)` pairs, with blocks numbered from 0.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::blocks::distinct_per_block;
+///
+/// let logs: Vec<String> = ["a b", "a a", "c d e"].map(String::from).to_vec();
+/// assert_eq!(distinct_per_block(&logs, 2), vec![(0, 2), (1, 3)]);
+/// ```
+pub fn distinct_per_block(logs: &[String], block_lines: usize) -> Vec<(usize, usize)> {
+    assert!(block_lines > 0, "block_lines must be greater than zero");
+    logs.chunks(block_lines)
+        .map(|block| count_words(block).len())
+        .enumerate()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test distinct counts of full blocks of 5 lines and a final partial block
+    #[test]
+    fn test_distinct_per_block_of_five() {
+        let logs: Vec<String> = [
+            // Block 0: error, disk, full, retry
+            "error disk full",
+            "error disk full",
+            "retry",
+            "ERROR",
+            "",
+            // Block 1: a burst of seven different words
+            "kernel panic",
+            "oom killer invoked",
+            "panic",
+            "reboot scheduled",
+            "kernel",
+            // Block 2: partial block with two lines
+            "ok",
+            "ok ok",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(distinct_per_block(&logs, 5), vec![(0, 4), (1, 7), (2, 1)]);
+        assert_eq!(distinct_per_block(&[], 5), vec![]);
+    }
+}
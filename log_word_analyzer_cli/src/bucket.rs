@@ -0,0 +1,119 @@
+//! Bucketing of numeric tokens into value ranges.
+
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// How all-digit tokens are grouped into labeled buckets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericBuckets {
+    /// One bucket per order of magnitude: `0-9`, `10-99`, `100-999`, ...
+    Magnitude,
+    /// Explicit inclusive ranges such as `200-299`, checked in order
+    Ranges(Vec<(u64, u64)>),
+}
+
+impl FromStr for NumericBuckets {
+    type Err = String;
+
+    /// Parses `magnitude` or a comma-separated list like `0-99,100-199`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        if spec == "magnitude" {
+            return Ok(NumericBuckets::Magnitude);
+        }
+        let mut ranges = Vec::new();
+        for range in spec.split(',') {
+            let bounds = range.split_once('-').and_then(|(low, high)| {
+                Some((
+                    low.trim().parse::<u64>().ok()?,
+                    high.trim().parse::<u64>().ok()?,
+                ))
+            });
+            match bounds {
+                Some((low, high)) if low <= high => ranges.push((low, high)),
+                _ => {
+                    return Err(format!(
+                        "invalid bucket range '{}' (expected LOW-HIGH)",
+                        range
+                    ));
+                }
+            }
+        }
+        Ok(NumericBuckets::Ranges(ranges))
+    }
+}
+
+impl NumericBuckets {
+    /// Returns the bucket label for a number, if any bucket contains it.
+    pub fn label(&self, value: u64) -> Option<String> {
+        match self {
+            NumericBuckets::Magnitude => {
+                // Smallest power of ten above the value bounds its bucket
+                let digits = value.checked_ilog10().unwrap_or(0);
+                let low = if digits == 0 { 0 } else { 10u64.pow(digits) };
+                let high = 10u64
+                    .checked_pow(digits + 1)
+                    .map_or(u64::MAX, |limit| limit - 1);
+                Some(format!("{}-{}", low, high))
+            }
+            NumericBuckets::Ranges(ranges) => ranges
+                .iter()
+                .find(|(low, high)| (*low..=*high).contains(&value))
+                .map(|(low, high)| format!("{}-{}", low, high)),
+        }
+    }
+
+    /// Maps a token to the key it is counted under in bucket mode.
+    ///
+    /// All-digit tokens become their bucket label (or are dropped when no
+    /// range contains them). Other tokens are kept as-is when
+    /// `keep_words` is set and dropped otherwise.
+    pub fn bucket_token(&self, token: &str, keep_words: bool) -> Option<String> {
+        if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) {
+            // Digit strings too long for u64 fall into the top magnitude
+            let value = token.parse().unwrap_or(u64::MAX);
+            return self.label(value);
+        }
+        keep_words.then(|| token.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_words, regroup, select_top_k};
+
+    /// Test that status codes 200, 201 and 204 share the 200-299 bucket
+    #[test]
+    fn test_status_codes_share_bucket() {
+        let buckets: NumericBuckets = "100-199,200-299,400-599".parse().unwrap();
+        let logs = vec![
+            "GET / 200".to_string(),
+            "POST /login 201".to_string(),
+            "DELETE /item 204".to_string(),
+            "GET /missing 404".to_string(),
+            "GET /teapot 999".to_string(),
+        ];
+
+        let counts = regroup(count_words(&logs), |word| buckets.bucket_token(word, false));
+
+        assert_eq!(
+            select_top_k(counts, 5),
+            vec![("200-299".to_string(), 3), ("400-599".to_string(), 1)]
+        );
+    }
+
+    /// Test order-of-magnitude labels and keeping non-numeric tokens
+    #[test]
+    fn test_magnitude_buckets() {
+        let buckets = NumericBuckets::Magnitude;
+
+        assert_eq!(buckets.label(0), Some("0-9".to_string()));
+        assert_eq!(buckets.label(42), Some("10-99".to_string()));
+        assert_eq!(buckets.label(1500), Some("1000-9999".to_string()));
+        assert_eq!(buckets.bucket_token("12ms", false), None);
+        assert_eq!(buckets.bucket_token("12ms", true), Some("12ms".to_string()));
+        assert!("5-1".parse::<NumericBuckets>().is_err());
+    }
+}
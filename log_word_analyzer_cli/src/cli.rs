@@ -0,0 +1,4540 @@
+//! Command-line front end: argument parsing and the `run` entry point.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::{IntErrorKind, ParseIntError};
+use std::path::Path;
+use std::time::Duration;
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::baseline::deviation_scores;
+use crate::bucket::NumericBuckets;
+use crate::compare::compare_rankings;
+use crate::convergence::convergence_by_key;
+use crate::counts::precounted_counts;
+use crate::extract::{CaptureGroup, JsonField};
+use crate::filter::{DropReason, FilterStats, MarkerRange, MarkerState, TokenFilter};
+use crate::fuzzy::fuzzy_merge;
+use crate::histogram::{LengthUnit, length_histogram, write_chart};
+use crate::hll::HyperLogLog;
+use crate::input::{
+    ReadOptions, STDIN, is_url, list_dir, newest_files, read_lines, read_manifest, stream_lines,
+    walk_dir,
+};
+use crate::lemma::Lemmatizer;
+use crate::line_length::LineLengthStats;
+use crate::logfmt::{edge_counts, logfmt_value_counts};
+use crate::named_groups::lines_by_named_group;
+#[cfg(feature = "parquet")]
+use crate::output::write_parquet;
+use crate::output::{
+    CountWithLines, CountWithSpan, JsonEnvelope, OutputFormat, ResultValue, RunLength,
+    SortDirection, SortKey, SortOrder, WeightRange, group_by_initial, group_by_tier,
+    write_comparison, write_nested_sections, write_rows, write_sections,
+};
+use crate::parallel::{count_words_parallel_filtered, positions_by_key_parallel};
+use crate::path_tree::path_tree;
+use crate::positions::positions_by_key;
+use crate::preprocess::{DEFAULT_HEX_MIN_LEN, Preprocessor};
+use crate::repl::Repl;
+use crate::runs::top_k_runs;
+use crate::severity::{SeverityWeights, select_top_k_scored, weighted_scores};
+use crate::shape::shape_counts;
+use crate::state::accumulate;
+use crate::summary::{FreqStats, entropy};
+use crate::tfidf::tfidf_scores;
+use crate::timestamp::{TimeRange, spans_by_key};
+use crate::tokenize::{
+    ApostropheTokenizer, FieldTokenizer, Tokenizer, UnitTokenizer, WhitespaceTokenizer,
+};
+use crate::validate::nonconforming_lines;
+use crate::weight::{WeightField, field_weighted_counts};
+use crate::{
+    TieBreak, WordCounter, coverage_percent, discovery_order, for_each_word, for_each_word_capped,
+    regroup, select_in_order, select_top_and_bottom_k, select_top_k, select_top_k_by,
+};
+
+/// Resolved command-line configuration.
+///
+/// Serializes, as printed by `--print-config`, to an object keyed by the
+/// field names: regexes as their pattern, timestamps in RFC3339 and the
+/// loaded reference vocabulary and lemma dictionary by the files they
+/// came from.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Config {
+    /// Log files to analyze, in the order given
+    pub files: Vec<String>,
+    /// Manifest listing further log files, read by [`Config::resolve_files`]
+    pub files_from: Option<String>,
+    /// Replace directory inputs by the files below them
+    pub recursive: bool,
+    /// With `recursive`, only take files whose name matches this glob
+    pub glob: Option<String>,
+    /// Read only this many of the input files, the most recently modified
+    pub newest: Option<usize>,
+    /// Number of top words to report
+    pub k: usize,
+    /// Format used to print the results
+    pub format: OutputFormat,
+    /// File the report is written to instead of standard output
+    pub output: Option<String>,
+    /// Wrap JSON output in a `{"schema_version", "results"}` object
+    pub json_envelope: bool,
+    /// File the report is also written to as JSON
+    pub also_json: Option<String>,
+    /// Order in which the K reported words are chosen and printed
+    pub sort: SortOrder,
+    /// Order of words with equal counts in the ranked report
+    pub tie_break: TieBreak,
+    /// Key the selected top-K rows are finally printed in order of
+    pub sort_by: Option<SortKey>,
+    /// Direction of `sort_by`; its default direction when not given
+    pub sort_dir: Option<SortDirection>,
+    /// Report a separate top-K for each input file instead of merging them
+    pub per_file: bool,
+    /// Rank the two input files separately and show them side by side
+    pub compare: bool,
+    /// Report the K least frequent words after the top K
+    pub top_and_bottom: bool,
+    /// Print only the number of distinct counted words
+    pub count_distinct: bool,
+    /// Print a HyperLogLog estimate of the number of distinct words
+    pub approx_distinct: bool,
+    /// Maximum number of rows to print; defaults to `k`
+    pub display: Option<usize>,
+    /// With JSON output, list up to this many line numbers for each word
+    pub include_positions: Option<usize>,
+    /// Report the first and last timestamps each top-K word occurs at
+    pub time_span: bool,
+    /// Report how much of the token stream the full top-K covers
+    pub coverage: bool,
+    /// Report summary statistics of the per-word counts
+    pub summary: bool,
+    /// Report the Shannon entropy of the word distribution
+    pub entropy: bool,
+    /// Report how many input lines are empty or whitespace-only
+    pub count_empty_lines: bool,
+    /// Report the distribution of input line lengths
+    pub line_length_stats: bool,
+    /// Start/stop markers limiting which lines of each file are counted
+    pub markers: MarkerRange,
+    /// Time bounds limiting which timestamped lines are counted
+    pub time_range: TimeRange,
+    /// Count lines on all cores instead of a single thread
+    pub parallel: bool,
+    /// Read, filter and count input files on this many worker threads
+    pub jobs: Option<usize>,
+    /// Weight word occurrences by the severity level of their line
+    pub severity_weights: Option<SeverityWeights>,
+    /// Weight word occurrences by a numeric field of their line
+    pub weight_field: Option<WeightField>,
+    /// With `weight_field`, give lines without a valid weight a weight of 1
+    pub weight_default_one: bool,
+    /// How input files are decoded into lines
+    pub read_options: ReadOptions,
+    /// Count only the text of this field of JSON log lines
+    pub json_field: Option<JsonField>,
+    /// Count only the text of this capture group of a line regex
+    pub capture: Option<CaptureGroup>,
+    /// Count the whole values of this key of logfmt lines instead of words
+    pub logfmt_key: Option<String>,
+    /// Count `from -> to` transitions from the value of this logfmt key
+    /// to that of `edge_to`, instead of words
+    pub edge_from: Option<String>,
+    /// Logfmt key holding the target of each transition
+    pub edge_to: Option<String>,
+    /// Split words on whitespace only, trimming their outer punctuation
+    pub whitespace_tokens: bool,
+    /// Keep apostrophes between letters inside words, as in `don't`
+    pub keep_apostrophes: bool,
+    /// Keep numbers with units such as `12ms` or `3.5GB` as single words
+    pub keep_units: bool,
+    /// Split lines on exactly this character, counting each field as one
+    /// word
+    pub field_separator: Option<char>,
+    /// Trim the whitespace around `field_separator` fields
+    pub trim_fields: bool,
+    /// Read each line as a `word count` pair instead of tokenizing it
+    pub input_counts: bool,
+    /// Count word shapes such as `aaaa000` instead of the words themselves
+    pub shape: bool,
+    /// In shape mode, map uppercase letters to `A` instead of `a`
+    pub shape_case: bool,
+    /// Transforms applied to every line before tokenizing
+    pub preprocessor: Preprocessor,
+    /// Filters deciding which tokens are counted
+    pub token_filter: TokenFilter,
+    /// Treat stop words containing `*` or `?` as glob patterns
+    pub stopword_glob: bool,
+    /// Report what each filter removed on stderr
+    pub explain: bool,
+    /// Rank the distinctive words of each file by TF-IDF
+    pub tfidf: bool,
+    /// Reference file of known words, read by [`Config::load_reference`]
+    pub new_against: Option<String>,
+    /// Rank words by how far their frequency deviates from the word
+    /// distribution of this reference log
+    pub baseline: Option<String>,
+    /// Tab-separated lemma dictionary, read by [`Config::load_lemmas`]
+    pub lemma_file: Option<String>,
+    /// Maps counted words to their lemma
+    #[serde(skip)]
+    pub lemmatizer: Option<Lemmatizer>,
+    /// Count numeric tokens by value bucket instead of individually
+    pub numeric_buckets: Option<NumericBuckets>,
+    /// In bucket mode, also count non-numeric tokens as themselves
+    pub bucket_keep_words: bool,
+    /// Count words within this edit distance under the most frequent spelling
+    pub fuzzy_merge: Option<usize>,
+    /// Print the top-K grouped into sections by initial letter
+    pub group_by_initial: bool,
+    /// Print the top-K of the lines of every value of every named capture
+    /// group of this regex, keyed by group name then value
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub group_by_named: Option<Regex>,
+    /// Print the top-K in tiers of words counted at least this often,
+    /// highest first
+    pub tiers: Option<Vec<usize>>,
+    /// Count once, then answer queries from stdin
+    pub interactive: bool,
+    /// File holding running totals that this run's counts are added to
+    pub state: Option<String>,
+    /// Report the distinct words of each block of this many lines instead of the top-K
+    pub block_distinct: Option<usize>,
+    /// Report the lines repeated most often in a row instead of the top-K
+    pub runs: bool,
+    /// Report how many words have each length instead of the top-K
+    pub length_histogram: bool,
+    /// Report the path-like tokens as a prefix tree with aggregate counts
+    /// instead of the top-K
+    pub path_tree: bool,
+    /// Report the line after which the top-K stopped changing instead of
+    /// the top-K
+    pub convergence: bool,
+    /// What the length histogram counts for each word
+    pub histogram_unit: LengthUnit,
+    /// Print the processing plan instead of counting
+    pub dry_run: bool,
+    /// Print the resolved configuration as JSON on stderr before running
+    pub print_config: bool,
+    /// Exit with [`EXIT_REQUIREMENT_FAILED`] unless this word is counted
+    pub require_word: Option<String>,
+    /// Minimum number of occurrences of `require_word`; defaults to 1
+    pub require_count: Option<usize>,
+    /// Print the counted tokens one per line instead of counting
+    pub tokenize_only: bool,
+    /// Check that every line matches this format instead of counting
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub validate: Option<Regex>,
+    /// Nonconforming lines tolerated by `validate` before the run fails
+    pub max_failures: usize,
+}
+
+/// Line numbers listed per word by `--include-positions` without a limit.
+pub const DEFAULT_POSITIONS_LIMIT: usize = 10;
+
+/// Exit code of a run that succeeded but missed its `--require-word` check
+/// or found too many lines failing `--validate`.
+pub const EXIT_REQUIREMENT_FAILED: i32 = 2;
+
+impl Config {
+    /// Parses the full argument vector (including the program name).
+    ///
+    /// Positional arguments are one or more file names followed by `k`; the
+    /// file name `-` reads standard input.
+    /// Options may appear anywhere on the command line.
+    ///
+    /// # Returns
+    ///
+    /// The parsed configuration, or an error message suitable for
+    /// printing to the user.
+    pub fn from_args(args: &[String]) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut positionals: Vec<String> = Vec::new();
+
+        // A number right after --include-positions is its limit, unless k
+        // turns out to be missing
+        let mut positions_limit: Option<&String> = None;
+        // Likewise a number right after --fuzzy-merge is its distance
+        let mut fuzzy_distance: Option<&String> = None;
+        let mut max_failures: Option<usize> = None;
+        let mut normalize_hex = false;
+        let mut hex_min_len: Option<usize> = None;
+        let mut cloud_range: Option<WeightRange> = None;
+        let mut capture: Option<Regex> = None;
+        let mut capture_group: Option<usize> = None;
+
+        let mut iter = args.iter().skip(1).peekable();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--files-from" => {
+                    config.files_from = Some(option_value(&mut iter, arg)?.to_string())
+                }
+                "--recursive" => config.recursive = true,
+                "--glob" => config.glob = Some(option_value(&mut iter, arg)?.to_string()),
+                "--newest" => {
+                    let count = number_value(&mut iter, arg)?;
+                    if count == 0 {
+                        return Err("--newest must be greater than zero".to_string());
+                    }
+                    config.newest = Some(count);
+                }
+                "--per-file" => config.per_file = true,
+                "--compare" => config.compare = true,
+                "--top-and-bottom" => config.top_and_bottom = true,
+                "--count-distinct" => config.count_distinct = true,
+                "--approx-distinct" => config.approx_distinct = true,
+                "--format" => config.format = option_value(&mut iter, arg)?.parse()?,
+                "--output" => config.output = Some(option_value(&mut iter, arg)?.to_string()),
+                "--json-envelope" => config.json_envelope = true,
+                "--also-json" => config.also_json = Some(option_value(&mut iter, arg)?.to_string()),
+                "--cloud-range" => cloud_range = Some(option_value(&mut iter, arg)?.parse()?),
+                "--sort" => config.sort = option_value(&mut iter, arg)?.parse()?,
+                "--tie-break" => config.tie_break = option_value(&mut iter, arg)?.parse()?,
+                "--sort-by" => config.sort_by = Some(option_value(&mut iter, arg)?.parse()?),
+                "--sort-dir" => config.sort_dir = Some(option_value(&mut iter, arg)?.parse()?),
+                "--display" => config.display = Some(number_value(&mut iter, arg)?),
+                "--include-positions" => {
+                    positions_limit = iter.next_if(|value| value.parse::<usize>().is_ok());
+                    config.include_positions = Some(DEFAULT_POSITIONS_LIMIT);
+                }
+                "--time-span" => config.time_span = true,
+                "--coverage" => config.coverage = true,
+                "--summary" => config.summary = true,
+                "--entropy" => config.entropy = true,
+                "--count-empty-lines" => config.count_empty_lines = true,
+                "--line-length-stats" => config.line_length_stats = true,
+                "--start-after" => {
+                    config.markers.start_after = Some(regex_value(&mut iter, arg)?);
+                }
+                "--stop-at" => config.markers.stop_at = Some(regex_value(&mut iter, arg)?),
+                "--since" => {
+                    config.time_range.since = Some(option_value(&mut iter, arg)?.parse()?);
+                }
+                "--until" => {
+                    config.time_range.until = Some(option_value(&mut iter, arg)?.parse()?);
+                }
+                "--parallel" => config.parallel = true,
+                "--jobs" => {
+                    let jobs = number_value(&mut iter, arg)?;
+                    if jobs == 0 {
+                        return Err("--jobs must be greater than zero".to_string());
+                    }
+                    config.jobs = Some(jobs);
+                }
+                "--severity-weights" => {
+                    config.severity_weights = Some(option_value(&mut iter, arg)?.parse()?);
+                }
+                "--weight-field" => {
+                    let index = number_value(&mut iter, arg)?;
+                    if index == 0 {
+                        return Err("--weight-field counts fields from 1".to_string());
+                    }
+                    config.weight_field = Some(WeightField::new(index));
+                }
+                "--weight-default-one" => config.weight_default_one = true,
+                "--encoding" => {
+                    config.read_options.encoding = option_value(&mut iter, arg)?.parse()?;
+                }
+                "--record-separator" => {
+                    config.read_options.separator = option_value(&mut iter, arg)?.parse()?;
+                }
+                "--compression" => {
+                    config.read_options.compression = option_value(&mut iter, arg)?.parse()?;
+                }
+                "--read-retries" => {
+                    config.read_options.read_retries = number_value(&mut iter, arg)?
+                }
+                "--idle-timeout" => {
+                    let value = option_value(&mut iter, arg)?;
+                    let seconds = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                        .ok_or_else(|| {
+                            format!(
+                                "--idle-timeout must be a positive number of seconds, got '{}'",
+                                value
+                            )
+                        })?;
+                    config.read_options.idle_timeout = Some(Duration::from_secs_f64(seconds));
+                }
+                "--byte-start" => {
+                    config.read_options.byte_start = number_value(&mut iter, arg)? as u64
+                }
+                "--byte-end" => {
+                    config.read_options.byte_end = Some(number_value(&mut iter, arg)? as u64)
+                }
+                "--max-lines-per-sec" => {
+                    let rate = number_value(&mut iter, arg)?;
+                    if rate == 0 {
+                        return Err("--max-lines-per-sec must be greater than zero".to_string());
+                    }
+                    config.read_options.max_lines_per_sec = Some(rate as f64);
+                }
+                "--json-field" => {
+                    config.json_field = Some(option_value(&mut iter, arg)?.parse()?);
+                }
+                "--capture" => capture = Some(regex_value(&mut iter, arg)?),
+                "--capture-group" => capture_group = Some(number_value(&mut iter, arg)?),
+                "--logfmt-key" => {
+                    config.logfmt_key = Some(option_value(&mut iter, arg)?.to_string());
+                }
+                "--edge-from" => config.edge_from = Some(option_value(&mut iter, arg)?.to_string()),
+                "--edge-to" => config.edge_to = Some(option_value(&mut iter, arg)?.to_string()),
+                "--whitespace-tokens" => config.whitespace_tokens = true,
+                "--keep-apostrophes" => config.keep_apostrophes = true,
+                "--keep-units" => config.keep_units = true,
+                "--field-separator" => {
+                    config.field_separator = Some(separator_value(&mut iter, arg)?);
+                }
+                "--trim-fields" => config.trim_fields = true,
+                "--input-counts" => config.input_counts = true,
+                "--shape" => config.shape = true,
+                "--shape-case" => config.shape_case = true,
+                "--trim" => config.preprocessor.trim = true,
+                "--strip-prefix" => {
+                    config.preprocessor.strip_prefix = Some(regex_value(&mut iter, arg)?);
+                }
+                "--redact" => config
+                    .preprocessor
+                    .redact
+                    .push(regex_value(&mut iter, arg)?),
+                "--normalize-hex" => normalize_hex = true,
+                "--hex-min-len" => {
+                    let length = number_value(&mut iter, arg)?;
+                    if length == 0 {
+                        return Err("--hex-min-len must be greater than zero".to_string());
+                    }
+                    hex_min_len = Some(length);
+                }
+                "--stop-words" => {
+                    let words = option_value(&mut iter, arg)?;
+                    config.token_filter.add_stop_words(words.split(','));
+                }
+                "--stopword-glob" => config.stopword_glob = true,
+                "--min-len" => config.token_filter.min_len = number_value(&mut iter, arg)?,
+                "--exclude-numeric" => config.token_filter.exclude_numeric = true,
+                "--contains" => config
+                    .token_filter
+                    .set_contains(option_value(&mut iter, arg)?),
+                "--new-against" => {
+                    config.new_against = Some(option_value(&mut iter, arg)?.to_string());
+                }
+                "--baseline" => {
+                    config.baseline = Some(option_value(&mut iter, arg)?.to_string());
+                }
+                "--max-unique" => {
+                    config.token_filter.max_unique = Some(number_value(&mut iter, arg)?);
+                }
+                "--max-tokens-per-line" => {
+                    config.token_filter.max_tokens_per_line = Some(number_value(&mut iter, arg)?);
+                }
+                "--record-frequency" => config.token_filter.once_per_record = true,
+                "--prune-threshold" => {
+                    let threshold = number_value(&mut iter, arg)?;
+                    if threshold < 2 {
+                        return Err("--prune-threshold must be at least 2".to_string());
+                    }
+                    config.token_filter.prune_threshold = Some(threshold);
+                }
+                "--explain" => config.explain = true,
+                "--tfidf" => config.tfidf = true,
+                "--lemma-file" => {
+                    config.lemma_file = Some(option_value(&mut iter, arg)?.to_string())
+                }
+                "--numeric-bucket" => {
+                    config.numeric_buckets = Some(option_value(&mut iter, arg)?.parse()?);
+                }
+                "--bucket-keep-words" => config.bucket_keep_words = true,
+                "--fuzzy-merge" => {
+                    fuzzy_distance = iter.next_if(|value| value.parse::<usize>().is_ok());
+                    config.fuzzy_merge = Some(1);
+                }
+                "--group-by-initial" => config.group_by_initial = true,
+                "--group-by-named" => {
+                    let pattern = regex_value(&mut iter, arg)?;
+                    if pattern.capture_names().flatten().next().is_none() {
+                        return Err(
+                            "--group-by-named needs a regex with named groups like (?P<service>\\w+)"
+                                .to_string(),
+                        );
+                    }
+                    config.group_by_named = Some(pattern);
+                }
+                "--tiers" => {
+                    let list = option_value(&mut iter, arg)?;
+                    let mut tiers = list
+                        .split(',')
+                        .map(|threshold| parse_count(threshold.trim(), arg))
+                        .collect::<Result<Vec<usize>, String>>()?;
+                    tiers.sort_unstable_by(|a, b| b.cmp(a));
+                    tiers.dedup();
+                    config.tiers = Some(tiers);
+                }
+                "--interactive" => config.interactive = true,
+                "--block-distinct" => {
+                    let lines = number_value(&mut iter, arg)?;
+                    if lines == 0 {
+                        return Err("--block-distinct must be greater than zero".to_string());
+                    }
+                    config.block_distinct = Some(lines);
+                }
+                "--runs" => config.runs = true,
+                "--length-histogram" => config.length_histogram = true,
+                "--path-tree" => config.path_tree = true,
+                "--convergence" => config.convergence = true,
+                "--histogram-tokens" => config.histogram_unit = LengthUnit::Tokens,
+                "--dry-run" => config.dry_run = true,
+                "--print-config" => config.print_config = true,
+                "--state" => config.state = Some(option_value(&mut iter, arg)?.to_string()),
+                "--require-word" => {
+                    config.require_word = Some(word_value(&mut iter, arg)?);
+                }
+                "--require-count" => {
+                    config.require_count = Some(number_value(&mut iter, arg)?);
+                }
+                "--tokenize-only" => config.tokenize_only = true,
+                "--validate" => config.validate = Some(regex_value(&mut iter, arg)?),
+                "--max-failures" => max_failures = Some(number_value(&mut iter, arg)?),
+                option if option.starts_with("--") => {
+                    return Err(format!("unknown option '{}'", option));
+                }
+                _ => positionals.push(arg.clone()),
+            }
+        }
+
+        match (&config.edge_from, &config.edge_to) {
+            (Some(_), None) | (None, Some(_)) => {
+                return Err("--edge-from and --edge-to must be given together".to_string());
+            }
+            _ => {}
+        }
+        check_conflicts(&config)?;
+        if config.trim_fields && config.field_separator.is_none() {
+            return Err("--trim-fields needs --field-separator".to_string());
+        }
+        if config.shape_case && !config.shape {
+            return Err("--shape-case needs --shape".to_string());
+        }
+        match config.weight_field.as_mut() {
+            Some(field) => field.default_one = config.weight_default_one,
+            None if config.weight_default_one => {
+                return Err("--weight-default-one needs --weight-field".to_string());
+            }
+            None => {}
+        }
+        if config.stopword_glob {
+            if config.token_filter.stop_words.is_empty() {
+                return Err("--stopword-glob needs --stop-words".to_string());
+            }
+            config.token_filter.use_stop_globs();
+        }
+        match capture {
+            Some(regex) => config.capture = Some(CaptureGroup::new(regex, capture_group)?),
+            None if capture_group.is_some() => {
+                return Err("--capture-group needs --capture".to_string());
+            }
+            None => {}
+        }
+        if config.sort_dir.is_some() && config.sort_by.is_none() {
+            return Err("--sort-dir needs --sort-by".to_string());
+        }
+        if let Some(range) = cloud_range {
+            match &mut config.format {
+                OutputFormat::WordCloud(weights) => *weights = range,
+                _ => return Err("--cloud-range needs --format wordcloud".to_string()),
+            }
+        }
+        if config.glob.is_some() && !config.recursive && config.newest.is_none() {
+            return Err("--glob needs --recursive or --newest".to_string());
+        }
+        if let Some(end) = config.read_options.byte_end
+            && end <= config.read_options.byte_start
+        {
+            return Err("--byte-end must be greater than --byte-start".to_string());
+        }
+        if config.require_count.is_some() && config.require_word.is_none() {
+            return Err("--require-count needs --require-word".to_string());
+        }
+        if normalize_hex {
+            let min_len = hex_min_len.unwrap_or(DEFAULT_HEX_MIN_LEN);
+            config.preprocessor.hex = Some(Preprocessor::hex_pattern(min_len));
+        } else if hex_min_len.is_some() {
+            return Err("--hex-min-len needs --normalize-hex".to_string());
+        }
+        if let Some(limit) = max_failures {
+            if config.validate.is_none() {
+                return Err("--max-failures needs --validate".to_string());
+            }
+            config.max_failures = limit;
+        }
+        if config.path_tree && !matches!(config.format, OutputFormat::Debug | OutputFormat::Json) {
+            return Err("--path-tree prints the debug or json format only".to_string());
+        }
+        if config.histogram_unit == LengthUnit::Tokens && !config.length_histogram {
+            return Err("--histogram-tokens needs --length-histogram".to_string());
+        }
+        if config.include_positions.is_some() && config.format != OutputFormat::Json {
+            return Err("--include-positions needs --format json".to_string());
+        }
+        if config.json_envelope && config.format != OutputFormat::Json {
+            return Err("--json-envelope needs --format json".to_string());
+        }
+        if config.format == OutputFormat::Parquet {
+            if !cfg!(feature = "parquet") {
+                return Err("--format parquet needs a build with the `parquet` feature".to_string());
+            }
+            if config.output.is_none() {
+                return Err("--format parquet needs --output".to_string());
+            }
+        }
+        if config.time_span && !matches!(config.format, OutputFormat::Debug | OutputFormat::Json) {
+            return Err("--time-span needs --format debug or json".to_string());
+        }
+
+        // The last positional argument is k, everything before it is a file;
+        // with a manifest, the files may all come from there instead.
+        // Validation and tokenizing take no k, so every positional argument
+        // is a file
+        // With --newest, no file means the current directory
+        let min_positionals = if config.files_from.is_some() || config.newest.is_some() {
+            1
+        } else {
+            2
+        };
+        if config.validate.is_some() || config.tokenize_only {
+            if positionals.len() < min_positionals - 1 {
+                return Err("expected at least one file name".to_string());
+            }
+            config.files = positionals;
+            return Ok(config);
+        }
+        if let Some(limit) = positions_limit {
+            if positionals.len() < min_positionals {
+                positionals.push(limit.clone());
+            } else {
+                config.include_positions = limit.parse().ok();
+            }
+        }
+        if let Some(distance) = fuzzy_distance {
+            if positionals.len() < min_positionals {
+                positionals.push(distance.clone());
+            } else {
+                config.fuzzy_merge = distance.parse().ok();
+            }
+        }
+        if positionals.len() < min_positionals {
+            return Err("expected at least one file name and k".to_string());
+        }
+        let k_arg = positionals.pop().unwrap_or_default();
+        config.k = parse_k(&k_arg)?;
+        config.files = positionals;
+        if config.interactive && config.files.iter().any(|file| file == STDIN) {
+            return Err(
+                "--interactive reads commands from stdin, so no input may be '-'".to_string(),
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// Appends the files listed by `--files-from` to the input files and,
+    /// with `--recursive`, replaces directories by the files below them.
+    ///
+    /// Manifest entries follow the files given on the command line, in
+    /// manifest order. The files of a directory take its place, sorted by
+    /// path. With `--newest` but not `--recursive`, a directory is replaced
+    /// by the files directly in it, and no input at all reads the current
+    /// directory. `--glob` keeps only the matching files of a directory.
+    ///
+    /// # Returns
+    ///
+    /// Warnings about directory entries that were skipped.
+    pub fn resolve_files(&mut self) -> Result<Vec<String>, String> {
+        if let Some(manifest) = &self.files_from {
+            let listed = read_manifest(manifest)?;
+            if listed.is_empty() && self.files.is_empty() {
+                return Err(format!("manifest '{}' lists no files", manifest));
+            }
+            self.files.extend(listed);
+        }
+
+        if self.files.is_empty() && self.newest.is_some() {
+            self.files.push(".".to_string());
+        }
+
+        let mut warnings = Vec::new();
+        let mut files = Vec::with_capacity(self.files.len());
+        for file in std::mem::take(&mut self.files) {
+            if !Path::new(&file).is_dir() {
+                files.push(file);
+            } else if self.recursive || self.newest.is_some() {
+                let (found, skipped) = if self.recursive {
+                    walk_dir(&file, self.glob.as_deref())
+                } else {
+                    list_dir(&file, self.glob.as_deref())
+                };
+                if found.is_empty() {
+                    warnings.push(format!("no matching files in directory '{}'", file));
+                }
+                files.extend(found);
+                warnings.extend(skipped);
+            } else {
+                return Err(format!(
+                    "'{}' is a directory; pass --recursive to read the files in it",
+                    file
+                ));
+            }
+        }
+        if files.is_empty() {
+            return Err("no input files to read".to_string());
+        }
+        if let Some(count) = self.newest {
+            if files.iter().any(|file| file == STDIN || is_url(file)) {
+                return Err(
+                    "--newest picks by modification time, so it cannot read standard input or URLs"
+                        .to_string(),
+                );
+            }
+            files = newest_files(&files, count)?;
+        }
+        if self.compare && files.len() != 2 {
+            return Err(format!(
+                "--compare needs exactly two input files, got {}",
+                files.len()
+            ));
+        }
+        self.files = files;
+        Ok(warnings)
+    }
+
+    /// Loads the reference vocabulary named by `--new-against`.
+    ///
+    /// Every word of the file is known, so it may be a plain word list or
+    /// an older log.
+    pub fn load_reference(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.new_against {
+            for line in read_lines(path, &ReadOptions::default())? {
+                for_each_word(&line, |word| {
+                    self.token_filter.known_words.insert(word.to_string());
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the dictionary named by `--lemma-file`.
+    pub fn load_lemmas(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.lemma_file {
+            self.lemmatizer = Some(Lemmatizer::load(path)?);
+        }
+        Ok(())
+    }
+}
+
+/// Takes the value following an option, failing when it is missing.
+fn option_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<&'a str, String> {
+    iter.next()
+        .map(|value| value.as_str())
+        .ok_or_else(|| format!("option '{}' requires a value", option))
+}
+
+/// Takes the value following an option and parses it as a count.
+fn number_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<usize, String> {
+    parse_count(option_value(iter, option)?, option)
+}
+
+/// Parses a count, telling values too large for this platform apart
+/// from malformed ones.
+fn parse_count(value: &str, name: &str) -> Result<usize, String> {
+    value.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow => format!(
+            "{} is too large, got '{}' (at most {})",
+            name,
+            value,
+            usize::MAX
+        ),
+        _ => format!("{} must be a positive number, got '{}'", name, value),
+    })
+}
+
+/// Parses the positional k leniently: a leading `+` and a whole number
+/// written as a decimal, such as `5.0`, are accepted.
+fn parse_k(value: &str) -> Result<usize, String> {
+    let digits = value.strip_prefix('+').unwrap_or(value);
+    let digits = match digits.split_once('.') {
+        Some((whole, fraction)) if fraction.bytes().all(|b| b == b'0') => whole,
+        _ => digits,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("k must be a non-negative integer, got '{}'", value));
+    }
+    parse_count(digits, "k")
+        .map_err(|_| format!("k is too large, got '{}' (at most {})", value, usize::MAX))
+}
+
+/// Takes the value following an option and compiles it as a regex.
+fn regex_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<Regex, String> {
+    let pattern = option_value(iter, option)?;
+    Regex::new(pattern).map_err(|e| format!("invalid regex for '{}': {}", option, e))
+}
+
+/// Takes the value following an option as a single separator character,
+/// where `\t` stands for a tab.
+fn separator_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<char, String> {
+    let value = option_value(iter, option)?;
+    if value == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(separator), None) => Ok(separator),
+        _ => Err(format!(
+            "{} must be a single character, got '{}'",
+            option, value
+        )),
+    }
+}
+
+/// Takes the value following an option and checks it is a single word.
+///
+/// The word is lowercased, matching how log lines are tokenized.
+fn word_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> Result<String, String> {
+    let value = option_value(iter, option)?;
+    let mut words = Vec::new();
+    for_each_word(value, |word| words.push(word.to_string()));
+    match words.as_slice() {
+        [word] if word.len() == value.len() => Ok(word.clone()),
+        _ => Err(format!("{} must be a single word, got '{}'", option, value)),
+    }
+}
+
+/// An option taking part in the conflict checks of [`check_conflicts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opt {
+    AlsoJson,
+    ApproxDistinct,
+    Baseline,
+    BlockDistinct,
+    Compare,
+    Convergence,
+    CountDistinct,
+    Edge,
+    FieldSeparator,
+    FuzzyMerge,
+    GroupByInitial,
+    GroupByNamed,
+    IncludePositions,
+    InputCounts,
+    Interactive,
+    JsonEnvelope,
+    JsonField,
+    KeepApostrophes,
+    KeepUnits,
+    LemmaFile,
+    LengthHistogram,
+    LogfmtKey,
+    MaxTokensPerLine,
+    NumericBucket,
+    Parquet,
+    PathTree,
+    PerFile,
+    PruneThreshold,
+    RecordFrequency,
+    RequireWord,
+    Runs,
+    SeverityWeights,
+    Shape,
+    SortBy,
+    SortFirstSeen,
+    SortNone,
+    State,
+    TieBreak,
+    Tfidf,
+    Tiers,
+    TimeSpan,
+    TokenizeOnly,
+    TopAndBottom,
+    Validate,
+    WeightField,
+    WhitespaceTokens,
+}
+
+impl Opt {
+    /// The option as written on the command line.
+    fn flag(self) -> &'static str {
+        match self {
+            Opt::AlsoJson => "--also-json",
+            Opt::ApproxDistinct => "--approx-distinct",
+            Opt::Baseline => "--baseline",
+            Opt::BlockDistinct => "--block-distinct",
+            Opt::Compare => "--compare",
+            Opt::Convergence => "--convergence",
+            Opt::CountDistinct => "--count-distinct",
+            Opt::Edge => "--edge-from/--edge-to",
+            Opt::FieldSeparator => "--field-separator",
+            Opt::FuzzyMerge => "--fuzzy-merge",
+            Opt::GroupByInitial => "--group-by-initial",
+            Opt::GroupByNamed => "--group-by-named",
+            Opt::IncludePositions => "--include-positions",
+            Opt::InputCounts => "--input-counts",
+            Opt::Interactive => "--interactive",
+            Opt::JsonEnvelope => "--json-envelope",
+            Opt::JsonField => "--json-field",
+            Opt::KeepApostrophes => "--keep-apostrophes",
+            Opt::KeepUnits => "--keep-units",
+            Opt::LemmaFile => "--lemma-file",
+            Opt::LengthHistogram => "--length-histogram",
+            Opt::LogfmtKey => "--logfmt-key",
+            Opt::MaxTokensPerLine => "--max-tokens-per-line",
+            Opt::NumericBucket => "--numeric-bucket",
+            Opt::Parquet => "--format parquet",
+            Opt::PathTree => "--path-tree",
+            Opt::PerFile => "--per-file",
+            Opt::PruneThreshold => "--prune-threshold",
+            Opt::RecordFrequency => "--record-frequency",
+            Opt::RequireWord => "--require-word",
+            Opt::Runs => "--runs",
+            Opt::SeverityWeights => "--severity-weights",
+            Opt::Shape => "--shape",
+            Opt::SortBy => "--sort-by",
+            Opt::SortFirstSeen => "--sort-by firstseen",
+            Opt::SortNone => "--sort none",
+            Opt::State => "--state",
+            Opt::TieBreak => "--tie-break",
+            Opt::Tfidf => "--tfidf",
+            Opt::Tiers => "--tiers",
+            Opt::TimeSpan => "--time-span",
+            Opt::TokenizeOnly => "--tokenize-only",
+            Opt::TopAndBottom => "--top-and-bottom",
+            Opt::Validate => "--validate",
+            Opt::WeightField => "--weight-field",
+            Opt::WhitespaceTokens => "--whitespace-tokens",
+        }
+    }
+
+    /// Whether the option is given in `config`.
+    fn is_set(self, config: &Config) -> bool {
+        match self {
+            Opt::AlsoJson => config.also_json.is_some(),
+            Opt::ApproxDistinct => config.approx_distinct,
+            Opt::Baseline => config.baseline.is_some(),
+            Opt::BlockDistinct => config.block_distinct.is_some(),
+            Opt::Compare => config.compare,
+            Opt::Convergence => config.convergence,
+            Opt::CountDistinct => config.count_distinct,
+            Opt::Edge => config.edge_from.is_some(),
+            Opt::FieldSeparator => config.field_separator.is_some(),
+            Opt::FuzzyMerge => config.fuzzy_merge.is_some(),
+            Opt::GroupByInitial => config.group_by_initial,
+            Opt::GroupByNamed => config.group_by_named.is_some(),
+            Opt::IncludePositions => config.include_positions.is_some(),
+            Opt::InputCounts => config.input_counts,
+            Opt::Interactive => config.interactive,
+            Opt::JsonEnvelope => config.json_envelope,
+            Opt::JsonField => config.json_field.is_some(),
+            Opt::KeepApostrophes => config.keep_apostrophes,
+            Opt::KeepUnits => config.keep_units,
+            Opt::LemmaFile => config.lemma_file.is_some(),
+            Opt::LengthHistogram => config.length_histogram,
+            Opt::LogfmtKey => config.logfmt_key.is_some(),
+            Opt::MaxTokensPerLine => config.token_filter.max_tokens_per_line.is_some(),
+            Opt::NumericBucket => config.numeric_buckets.is_some(),
+            Opt::Parquet => config.format == OutputFormat::Parquet,
+            Opt::PathTree => config.path_tree,
+            Opt::PerFile => config.per_file,
+            Opt::PruneThreshold => config.token_filter.prune_threshold.is_some(),
+            Opt::RecordFrequency => config.token_filter.once_per_record,
+            Opt::RequireWord => config.require_word.is_some(),
+            Opt::Runs => config.runs,
+            Opt::SeverityWeights => config.severity_weights.is_some(),
+            Opt::Shape => config.shape,
+            Opt::SortBy => config.sort_by.is_some(),
+            Opt::SortFirstSeen => config.sort_by == Some(SortKey::FirstSeen),
+            Opt::SortNone => config.sort == SortOrder::None,
+            Opt::State => config.state.is_some(),
+            Opt::TieBreak => config.tie_break != TieBreak::Alpha,
+            Opt::Tfidf => config.tfidf,
+            Opt::Tiers => config.tiers.is_some(),
+            Opt::TimeSpan => config.time_span,
+            Opt::TokenizeOnly => config.tokenize_only,
+            Opt::TopAndBottom => config.top_and_bottom,
+            Opt::Validate => config.validate.is_some(),
+            Opt::WeightField => config.weight_field.is_some(),
+            Opt::WhitespaceTokens => config.whitespace_tokens,
+        }
+    }
+}
+
+/// The reports, each replacing the plain top-K, so at most one is given.
+const REPORT_MODES: &[Opt] = &[
+    Opt::Validate,
+    Opt::TokenizeOnly,
+    Opt::Interactive,
+    Opt::BlockDistinct,
+    Opt::LengthHistogram,
+    Opt::PathTree,
+    Opt::Convergence,
+    Opt::GroupByNamed,
+    Opt::Tfidf,
+    Opt::Compare,
+    Opt::TopAndBottom,
+    Opt::CountDistinct,
+    Opt::ApproxDistinct,
+    Opt::Edge,
+    Opt::Runs,
+    Opt::Baseline,
+    Opt::SeverityWeights,
+    Opt::IncludePositions,
+    Opt::Parquet,
+    Opt::TimeSpan,
+];
+
+/// The ways of turning a line into counted words, so at most one is given.
+const WORD_SOURCES: &[Opt] = &[
+    Opt::WeightField,
+    Opt::LogfmtKey,
+    Opt::Shape,
+    Opt::WhitespaceTokens,
+    Opt::KeepApostrophes,
+    Opt::KeepUnits,
+    Opt::FieldSeparator,
+    Opt::InputCounts,
+];
+
+/// Groups of options of which at most one may be given.
+const EXCLUSIVE_GROUPS: &[&[Opt]] = &[REPORT_MODES, WORD_SOURCES];
+
+/// Options that cannot be combined: no option of a row's first list goes
+/// with any option of its second.
+const CONFLICTS: &[(&[Opt], &[Opt])] = &[
+    // These follow the words as tokenized, one occurrence at a time
+    (
+        &[
+            Opt::ApproxDistinct,
+            Opt::Convergence,
+            Opt::IncludePositions,
+            Opt::SeverityWeights,
+            Opt::Tfidf,
+            Opt::TimeSpan,
+        ],
+        WORD_SOURCES,
+    ),
+    (
+        &[
+            Opt::ApproxDistinct,
+            Opt::Convergence,
+            Opt::IncludePositions,
+            Opt::SeverityWeights,
+            Opt::Tfidf,
+            Opt::TimeSpan,
+            Opt::TokenizeOnly,
+            Opt::Shape,
+        ],
+        &[Opt::FuzzyMerge],
+    ),
+    (
+        &[Opt::TokenizeOnly],
+        &[
+            Opt::InputCounts,
+            Opt::LogfmtKey,
+            Opt::Shape,
+            Opt::WeightField,
+        ],
+    ),
+    (&[Opt::IncludePositions], &[Opt::MaxTokensPerLine]),
+    (&[Opt::LogfmtKey], &[Opt::JsonField]),
+    (&[Opt::Shape], &[Opt::LemmaFile, Opt::NumericBucket]),
+    (
+        &[
+            Opt::PruneThreshold,
+            Opt::RecordFrequency,
+            Opt::SortFirstSeen,
+        ],
+        &[Opt::InputCounts, Opt::LogfmtKey, Opt::Shape],
+    ),
+    (
+        &[Opt::PerFile],
+        &[
+            Opt::ApproxDistinct,
+            Opt::BlockDistinct,
+            Opt::Compare,
+            Opt::Convergence,
+            Opt::CountDistinct,
+            Opt::GroupByInitial,
+            Opt::GroupByNamed,
+            Opt::Interactive,
+            Opt::LengthHistogram,
+            Opt::Parquet,
+            Opt::PathTree,
+            Opt::State,
+            Opt::Tiers,
+            Opt::TopAndBottom,
+        ],
+    ),
+    (
+        &[Opt::GroupByInitial, Opt::Tiers],
+        &[
+            Opt::ApproxDistinct,
+            Opt::Compare,
+            Opt::Convergence,
+            Opt::CountDistinct,
+            Opt::GroupByNamed,
+            Opt::Parquet,
+            Opt::PathTree,
+            Opt::TopAndBottom,
+        ],
+    ),
+    (&[Opt::GroupByInitial], &[Opt::Tiers]),
+    (
+        &[Opt::Tiers],
+        &[
+            Opt::Baseline,
+            Opt::BlockDistinct,
+            Opt::Interactive,
+            Opt::LengthHistogram,
+            Opt::SeverityWeights,
+            Opt::Tfidf,
+        ],
+    ),
+    (
+        &[Opt::State],
+        &[
+            Opt::ApproxDistinct,
+            Opt::Baseline,
+            Opt::BlockDistinct,
+            Opt::Compare,
+            Opt::Convergence,
+            Opt::CountDistinct,
+            Opt::Edge,
+            Opt::GroupByNamed,
+            Opt::Interactive,
+            Opt::LengthHistogram,
+            Opt::PathTree,
+            Opt::Runs,
+            Opt::SeverityWeights,
+            Opt::Tfidf,
+            Opt::TokenizeOnly,
+            Opt::TopAndBottom,
+            Opt::Validate,
+        ],
+    ),
+    (
+        &[Opt::RequireWord],
+        &[
+            Opt::Convergence,
+            Opt::GroupByNamed,
+            Opt::PathTree,
+            Opt::SeverityWeights,
+            Opt::Tfidf,
+            Opt::TokenizeOnly,
+            Opt::Validate,
+        ],
+    ),
+    (
+        &[Opt::AlsoJson],
+        &[
+            Opt::ApproxDistinct,
+            Opt::BlockDistinct,
+            Opt::Compare,
+            Opt::Convergence,
+            Opt::CountDistinct,
+            Opt::Interactive,
+            Opt::LengthHistogram,
+            Opt::Parquet,
+            Opt::PathTree,
+            Opt::Tfidf,
+            Opt::TokenizeOnly,
+            Opt::TopAndBottom,
+            Opt::Validate,
+        ],
+    ),
+    (
+        &[Opt::JsonEnvelope],
+        &[
+            Opt::ApproxDistinct,
+            Opt::Interactive,
+            Opt::TokenizeOnly,
+            Opt::Validate,
+        ],
+    ),
+    // These rank by something other than the count
+    (
+        &[Opt::SortBy],
+        &[
+            Opt::Baseline,
+            Opt::Compare,
+            Opt::Edge,
+            Opt::GroupByNamed,
+            Opt::Runs,
+            Opt::SeverityWeights,
+            Opt::SortNone,
+            Opt::Tfidf,
+            Opt::TopAndBottom,
+        ],
+    ),
+    (
+        &[Opt::SortNone],
+        &[
+            Opt::Baseline,
+            Opt::Convergence,
+            Opt::Edge,
+            Opt::FieldSeparator,
+            Opt::GroupByNamed,
+            Opt::InputCounts,
+            Opt::KeepApostrophes,
+            Opt::KeepUnits,
+            Opt::LogfmtKey,
+            Opt::PathTree,
+            Opt::Runs,
+            Opt::SeverityWeights,
+            Opt::Shape,
+            Opt::Tfidf,
+            Opt::TieBreak,
+            Opt::TopAndBottom,
+            Opt::WhitespaceTokens,
+        ],
+    ),
+];
+
+/// Rejects options given together that [`EXCLUSIVE_GROUPS`] or
+/// [`CONFLICTS`] keep apart, naming the first such pair.
+fn check_conflicts(config: &Config) -> Result<(), String> {
+    let conflict = |first: Opt, second: Opt| {
+        Err(format!(
+            "{} cannot be combined with {}",
+            first.flag(),
+            second.flag()
+        ))
+    };
+    for group in EXCLUSIVE_GROUPS {
+        let mut given = group.iter().filter(|option| option.is_set(config));
+        if let (Some(&first), Some(&second)) = (given.next(), given.next()) {
+            return conflict(first, second);
+        }
+    }
+    for (options, excluded) in CONFLICTS {
+        for &first in options.iter().filter(|option| option.is_set(config)) {
+            if let Some(&second) = excluded.iter().find(|option| option.is_set(config)) {
+                return conflict(first, second);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Option summary printed below the usage line.
+const OPTIONS_HELP: &str = "\
+Options:
+  --files-from <file>   Also read the paths listed in file, one per line (- for stdin)
+  --format <name>       Output format: debug, json, csv, tsv, markdown, wordcloud,
+                        lines, repeated, prometheus or parquet (default: debug)
+  --output <file>       Write the report to <file> instead of standard output
+  --json-envelope       With --format json, wrap the output as
+                        {\"schema_version\":1,\"results\":...}
+  --also-json <file>    Also write the top-K as JSON to file, e.g. next to a markdown table
+  --cloud-range <min-max>
+                        Weights of the rarest and top word in wordcloud output (default: 10-100)
+  --sort <order>        Result order: count or none (first appearance) (default: count)
+  --tie-break <order>   Order of equal counts: alpha or length (longest first) (default: alpha)
+  --sort-by <key>       Print the top-K (still chosen by count) ordered by count, alpha,
+                        length or firstseen
+  --sort-dir <dir>      Direction of --sort-by: asc or desc (default: desc for count and
+                        length, asc for alpha and firstseen)
+  --recursive           Read all files below directory inputs
+  --glob <pattern>      With --recursive or --newest, only read the files of directory
+                        inputs named like pattern, e.g. '*.log'
+  --newest <n>          Read only the n most recently modified input files, newest first;
+                        a directory input, or the current directory when no file is
+                        given, stands for the files directly in it
+  --per-file            Report a separate top-K for each file
+  --count-distinct      Print only the number of distinct counted words (k is ignored)
+  --approx-distinct     Like --count-distinct, but estimated in constant memory (HyperLogLog)
+  --top-and-bottom      Also report the k least frequent words, in a second section
+  --compare             Show the top-K of exactly two files side by side with ranks and counts
+  --display <n>         Print at most n of the top-K rows
+  --include-positions [n]
+                        With --format json, list the first n lines of each word (default: 10)
+  --time-span           With --format debug or json, add each word's first and last timestamp
+  --coverage            Report the share of tokens the top-K covers
+  --summary             Report min, max, mean, median, p90 and p99 of word counts
+  --entropy             Report the Shannon entropy of the word distribution in bits
+  --count-empty-lines   Report how many lines are empty or whitespace-only
+  --line-length-stats   Report the mean, median, p90, p99 and max line length
+  --start-after <re>    Count only lines after the first match of re
+  --stop-at <re>        Stop counting at the next line matching re
+  --since <time>        Count only lines stamped at or after an RFC3339 time
+  --until <time>        Count only lines stamped at or before an RFC3339 time
+  --parallel            Count lines on all available cores
+  --jobs <n>            Read, filter and count the input files on n threads
+  --severity-weights <spec>
+                        Weight words by line level, e.g. ERROR=5,WARN=2,INFO=1
+  --weight-field <n>    Add the number in whitespace field n of each line per word, not 1
+  --weight-default-one  With --weight-field, weigh lines without a number as 1 (default: skip)
+  --encoding <name>     Input encoding: utf8, latin1 or windows-1252 (default: utf8)
+  --record-separator <sep>
+                        Split records on \\0, \\n, a hex byte like 0x1e, a character, or any
+                        (\\n, \\r\\n and bare \\r line endings)
+  --compression <name>  Input compression: auto, none, xz or lz4 (default: auto)
+  --max-lines-per-sec <n>
+                        Consume at most n lines per second (for live stdin sources)
+  --read-retries <n>    Retry opening and reading a file up to n times after transient
+                        I/O errors, with doubling backoff from 100 ms
+  --idle-timeout <secs> Treat an input that sends nothing for secs seconds as ended, so a
+                        quiet FIFO or live stdin source still gets its top-K
+  --byte-start <n>      Seek to byte n of each file and count only lines beginning there
+                        or later; a partial line at n is skipped
+  --byte-end <n>        Stop at the first line beginning at or after byte n
+  --json-field <path>   Count only this field of JSON lines, e.g. error.message
+  --logfmt-key <key>    Count the values of key in logfmt lines (key=value) instead of words
+  --edge-from <key>     With --edge-to, count 'from -> to' transitions between two logfmt keys
+  --edge-to <key>       Key holding the target state of a transition
+  --whitespace-tokens   Split words on whitespace only, trimming outer punctuation (keeps /api/v1)
+  --keep-apostrophes    Keep apostrophes between letters in words, so don't is one word
+  --keep-units          Keep numbers with units as one word, such as 12ms or 3.5GB
+  --field-separator <c> Split lines on exactly the character c (\\t for tab) and count
+                        every field as one word
+  --trim-fields         Trim whitespace around --field-separator fields
+  --input-counts        Read each line as a precounted `word count` pair and add up the counts
+  --shape               Count word shapes (letters as a, digits as 0) instead of words
+  --shape-case          With --shape, map uppercase letters to A
+  --capture <re>        Count only the text of a capture group of re, skipping lines without a match
+  --capture-group <n>   Group counted by --capture (default: 1, or 0 without groups)
+  --trim                Trim whitespace from lines before counting
+  --strip-prefix <re>   Remove a match of re at the start of each line
+  --redact <re>         Replace matches of re with <redacted> before counting (repeatable)
+  --normalize-hex       Replace 0x numbers and long hex-digit words (addresses, hashes)
+                        with <hex> before counting
+  --hex-min-len <n>     Shortest bare hex word --normalize-hex replaces (default: 8)
+  --stop-words <list>   Comma-separated words that are never counted
+  --stopword-glob       Treat stop words with * or ? as globs, e.g. debug*
+  --min-len <n>         Ignore words shorter than n characters
+  --exclude-numeric     Ignore words made only of digits
+  --contains <substr>   Count only words containing substr (case-insensitive)
+  --new-against <file>  Count only words that do not occur in the reference file
+  --baseline <file>     Rank words by how far their frequency deviates from that of the
+                        reference log (chi-square contribution)
+  --max-unique <n>      Stop tracking new words after n distinct words
+  --max-tokens-per-line <n>
+                        Tokenize only the first n words of each line (default: unlimited)
+  --record-frequency    Count each word at most once per record (line), i.e. in how
+                        many records it occurs
+  --prune-threshold <n> Drop words seen fewer than n times whenever the counts
+                        double, bounding memory; counts may become slightly low
+  --explain             Report on stderr what each filter removed
+  --tfidf               Report the most distinctive words of each file by TF-IDF
+  --lemma-file <file>   Count words under their lemma from a surface<TAB>lemma file
+  --numeric-bucket <spec>
+                        Count numbers by bucket: magnitude or ranges like 0-99,100-199
+  --bucket-keep-words   With --numeric-bucket, also count non-numeric words
+  --fuzzy-merge [n]     Count words within n edits (default 1) under the most
+                        frequent spelling
+  --group-by-initial    Print the top-K in sections by initial letter (# for others)
+  --group-by-named <re> Print the top-K of the lines of each value of each named group
+                        of re, e.g. '(?P<level>[A-Z]+) (?P<service>\\w+):'
+  --tiers <n,n,...>     Print the top-K in tiers of words counted at least n times, e.g.
+                        100,10,1; each word goes into the highest tier it reaches
+  --interactive         Count once, then run commands like 'top 5' read from stdin
+  --block-distinct <n>  Report the number of distinct words in each block of n lines
+  --state <file>        Add the counts to running totals kept in file and report those
+  --runs                Report the top-K lines by longest run of consecutive repeats
+  --length-histogram    Report how many distinct words have each length, as a chart
+  --histogram-tokens    With --length-histogram, count token occurrences instead
+  --path-tree           Report path-like tokens as an indented tree of counts per subtree
+  --convergence         Report the line after which the top-K (set and order) never changed
+  --dry-run             Print the inputs and settings that would be used, then exit
+  --print-config        Print the resolved configuration as JSON on stderr before running
+  --require-word <w>    Exit with code 2 unless w occurs in the counted input
+  --require-count <n>   With --require-word, require at least n occurrences
+  --tokenize-only       Print each counted token on its own line instead of counting (no k)
+  --validate <re>       List the lines not matching re instead of counting (no k);
+                        exit with code 2 if any do
+  --max-failures <n>    With --validate, exit with code 2 only above n failing lines
+";
+
+/// Prints the usage message for the given program name.
+fn print_usage<E: Write>(program: &str, err: &mut E) {
+    let _ = writeln!(err, "Usage: {} <filename>... <k> [options]", program);
+    let _ = writeln!(err, "Example: {} logs.txt 5", program);
+    let _ = writeln!(err);
+    let _ = write!(err, "{}", OPTIONS_HELP);
+}
+
+/// Runs the analyzer with the given arguments.
+///
+/// Results are written to `out` and diagnostics to `err`, which keeps the
+/// whole command testable without spawning a process.
+///
+/// # Returns
+///
+/// The process exit code: `0` on success, `1` on any error and
+/// [`EXIT_REQUIREMENT_FAILED`] when a `--require-word` or `--validate`
+/// check fails.
+pub fn run<W: Write, E: Write>(args: &[String], out: &mut W, err: &mut E) -> i32 {
+    let program = args
+        .first()
+        .map(String::as_str)
+        .unwrap_or("log_word_analyzer_cli");
+
+    // Parse the command line, printing usage on malformed input
+    let mut config = match Config::from_args(args) {
+        Ok(config) => config,
+        Err(message) => {
+            let _ = writeln!(err, "error: {}", message);
+            print_usage(program, err);
+            return 1;
+        }
+    };
+
+    let loaded = config.resolve_files().and_then(|warnings| {
+        config.load_reference()?;
+        config.load_lemmas()?;
+        Ok(warnings)
+    });
+    match loaded {
+        Ok(warnings) => {
+            for warning in warnings {
+                let _ = writeln!(err, "warning: {}", warning);
+            }
+        }
+        Err(message) => {
+            let _ = writeln!(err, "error: {}", message);
+            return 1;
+        }
+    }
+
+    if config.print_config {
+        let text = serde_json::to_string_pretty(&config).unwrap_or_default();
+        let _ = writeln!(err, "{}", text);
+    }
+    if config.dry_run {
+        return if write_plan(&config, err) { 0 } else { 1 };
+    }
+
+    let mut session = Session::new(err);
+    // An empty report is easily mistaken for filters that matched nothing
+    if config.k == 0 && ranks_top_k(&config) {
+        let mut filters = line_filters(&config);
+        filters.extend(
+            config
+                .token_filter
+                .active()
+                .into_iter()
+                .map(DropReason::option),
+        );
+        if !filters.is_empty() {
+            let _ = writeln!(
+                session.err,
+                "note: k is 0, so no words are reported whatever {} keep; pass a larger k to see them",
+                filters.join(", ")
+            );
+        }
+    }
+    if let Some(jobs) = config.jobs {
+        match ThreadPoolBuilder::new().num_threads(jobs).build() {
+            Ok(pool) => session.pool = Some(pool),
+            Err(e) => {
+                let _ = writeln!(
+                    session.err,
+                    "error: Unable to start {} threads: {}",
+                    jobs, e
+                );
+                return 1;
+            }
+        }
+    }
+    let executed = match &config.output {
+        Some(path) => File::create(path)
+            .map_err(|e| format!("Unable to create output file '{}': {}", path, e))
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                emit(&config, &mut writer, &mut session)?;
+                writer
+                    .flush()
+                    .map_err(|e| format!("Unable to write output: {}", e))
+            }),
+        None => emit(&config, out, &mut session),
+    };
+    if let Err(message) = executed {
+        let _ = writeln!(session.err, "error: {}", message);
+        return 1;
+    }
+    if config.count_empty_lines {
+        let _ = writeln!(
+            session.err,
+            "empty lines: {} of {} lines are empty or whitespace-only",
+            session.empty_lines, session.lines_read
+        );
+    }
+    if config.line_length_stats {
+        let _ = writeln!(session.err, "line lengths: {}", session.line_lengths);
+    }
+
+    // The report is printed either way; only the exit code tells the outcome
+    if let Some(word) = &config.require_word {
+        let minimum = config.require_count.unwrap_or(1);
+        if session.required_count < minimum {
+            let _ = writeln!(
+                session.err,
+                "note: '{}' occurred {} times, required at least {}",
+                word, session.required_count, minimum
+            );
+            return EXIT_REQUIREMENT_FAILED;
+        }
+    }
+    if config.validate.is_some() && session.invalid_lines > config.max_failures {
+        return EXIT_REQUIREMENT_FAILED;
+    }
+    0
+}
+
+/// Describes the inputs and settings of a run on `err` without reading
+/// any file contents.
+///
+/// # Returns
+///
+/// False when an input file does not exist, so the real run would fail.
+fn write_plan<E: Write>(config: &Config, err: &mut E) -> bool {
+    let mut all_found = true;
+    let _ = writeln!(err, "dry run: {} input files", config.files.len());
+    for file in &config.files {
+        if file == STDIN {
+            let _ = writeln!(err, "  {}: standard input", file);
+            continue;
+        }
+        let compression = config.read_options.compression.resolve(file).name();
+        if is_url(file) {
+            let _ = writeln!(err, "  {}: URL, compression {}", file, compression);
+            continue;
+        }
+        match std::fs::metadata(file) {
+            Ok(metadata) => {
+                let _ = writeln!(
+                    err,
+                    "  {}: {} bytes, compression {}",
+                    file,
+                    metadata.len(),
+                    compression
+                );
+            }
+            Err(e) => {
+                all_found = false;
+                let _ = writeln!(err, "  {}: error: {}", file, e);
+            }
+        }
+    }
+
+    let mode = if let Some(format) = &config.validate {
+        format!("lines not matching '{}'", format.as_str())
+    } else if config.tokenize_only {
+        "counted tokens in order".to_string()
+    } else if config.interactive {
+        "interactive queries".to_string()
+    } else if config.count_distinct {
+        "number of distinct words".to_string()
+    } else if config.approx_distinct {
+        "estimated number of distinct words".to_string()
+    } else if let Some(block_lines) = config.block_distinct {
+        format!("distinct words per block of {} lines", block_lines)
+    } else if config.runs {
+        format!("top {} runs of repeated lines", config.k)
+    } else if let (Some(from), Some(to)) = (&config.edge_from, &config.edge_to) {
+        format!("top {} transitions from '{}' to '{}'", config.k, from, to)
+    } else if config.path_tree {
+        format!("tree of path counts, top {} children per node", config.k)
+    } else if config.convergence {
+        format!("line after which the top {} stopped changing", config.k)
+    } else if let Some(pattern) = &config.group_by_named {
+        format!(
+            "top {} per value of each named group of '{}'",
+            config.k,
+            pattern.as_str()
+        )
+    } else if config.length_histogram {
+        format!(
+            "histogram of word lengths by {}",
+            config.histogram_unit.name()
+        )
+    } else if let Some(path) = &config.baseline {
+        format!("top {} deviations from the baseline '{}'", config.k, path)
+    } else if config.tfidf {
+        format!("top {} by TF-IDF per file", config.k)
+    } else if config.severity_weights.is_some() {
+        format!("top {} by severity-weighted score", config.k)
+    } else if let Some(field) = config.weight_field {
+        format!("top {} by the weight in field {}", config.k, field.index)
+    } else if let Some(key) = &config.logfmt_key {
+        format!("top {} values of logfmt key '{}'", config.k, key)
+    } else if config.shape {
+        format!("top {} word shapes", config.k)
+    } else if config.sort == SortOrder::None {
+        format!("first {} words in order of appearance", config.k)
+    } else {
+        format!("top {} by count", config.k)
+    };
+    let mode = if config.top_and_bottom {
+        format!("{}, and bottom {}", mode, config.k)
+    } else {
+        mode
+    };
+    let mode = match config.sort_by {
+        Some(key) => format!(
+            "{}, printed by {} {}",
+            mode,
+            key.name(),
+            config.sort_dir.unwrap_or(key.default_direction()).name()
+        ),
+        None => mode,
+    };
+    let scope = if config.per_file {
+        "per file"
+    } else if config.compare {
+        "compared side by side"
+    } else if streams(config) {
+        "merged as read"
+    } else {
+        "merged"
+    };
+    let _ = writeln!(
+        err,
+        "mode: {}, {}, format {}",
+        mode,
+        scope,
+        config.format.name()
+    );
+
+    let token_filters: Vec<&str> = config
+        .token_filter
+        .active()
+        .into_iter()
+        .map(DropReason::option)
+        .collect();
+    for (kind, filters) in [("line", line_filters(config)), ("token", token_filters)] {
+        let list = if filters.is_empty() {
+            "none".to_string()
+        } else {
+            filters.join(", ")
+        };
+        let _ = writeln!(err, "{} filters: {}", kind, list);
+    }
+    all_found
+}
+
+/// Returns true when the report is cut to the top-K, rather than
+/// ignoring k like `--count-distinct` or `--validate`.
+fn ranks_top_k(config: &Config) -> bool {
+    !(config.validate.is_some()
+        || config.tokenize_only
+        || config.count_distinct
+        || config.approx_distinct
+        || config.interactive
+        || config.block_distinct.is_some()
+        || config.length_histogram)
+}
+
+/// The options of the configured line filters and transforms, in the
+/// order they apply.
+fn line_filters(config: &Config) -> Vec<&'static str> {
+    let mut line_filters = Vec::new();
+    if !config.markers.is_empty() {
+        line_filters.push("--start-after/--stop-at");
+    }
+    if !config.time_range.is_empty() {
+        line_filters.push("--since/--until");
+    }
+    if config.json_field.is_some() {
+        line_filters.push("--json-field");
+    }
+    if config.capture.is_some() {
+        line_filters.push("--capture");
+    }
+    if config.preprocessor.trim {
+        line_filters.push("--trim");
+    }
+    if config.preprocessor.strip_prefix.is_some() {
+        line_filters.push("--strip-prefix");
+    }
+    if !config.preprocessor.redact.is_empty() {
+        line_filters.push("--redact");
+    }
+    if config.preprocessor.hex.is_some() {
+        line_filters.push("--normalize-hex");
+    }
+    line_filters
+}
+
+/// State shared by all stages of one run: the diagnostics stream and the
+/// statistics reported at the end.
+struct Session<'e, E: Write> {
+    /// Destination of notes, warnings and reports
+    err: &'e mut E,
+    /// What the line and token filters removed so far
+    stats: FilterStats,
+    /// Occurrences of the `--require-word` word counted so far
+    required_count: usize,
+    /// Tokens counted in the frequency maps ranked so far
+    counted_tokens: usize,
+    /// Lines found not to match the `--validate` format so far
+    invalid_lines: usize,
+    /// Lines read from the inputs before any line filter
+    lines_read: usize,
+    /// Lines read that were empty or held only whitespace
+    empty_lines: usize,
+    /// Lengths of the lines read, before any line filter
+    line_lengths: LineLengthStats,
+    /// Worker threads requested with `--jobs`
+    pool: Option<ThreadPool>,
+}
+
+impl<'e, E: Write> Session<'e, E> {
+    /// Starts a session writing diagnostics to `err`.
+    fn new(err: &'e mut E) -> Self {
+        Session {
+            err,
+            stats: FilterStats::default(),
+            required_count: 0,
+            counted_tokens: 0,
+            invalid_lines: 0,
+            lines_read: 0,
+            empty_lines: 0,
+            line_lengths: LineLengthStats::default(),
+            pool: None,
+        }
+    }
+}
+
+/// Runs [`execute`], wrapping its output in the `--json-envelope` object
+/// when asked to.
+fn emit<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    if !config.json_envelope {
+        return execute(config, out, session);
+    }
+    let write_error = |e: std::io::Error| format!("Unable to write output: {}", e);
+    let mut envelope = JsonEnvelope::new(out).map_err(write_error)?;
+    execute(config, &mut envelope, session)?;
+    envelope.finish().map_err(write_error)
+}
+
+/// Reads the configured inputs and writes the requested report.
+fn execute<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    if let Some(format) = &config.validate {
+        report_validation(config, format, out, session)?;
+    } else if config.tokenize_only {
+        report_tokens(config, out, session)?;
+    } else if config.interactive {
+        interact(config, out, session)?;
+    } else if let Some(block_lines) = config.block_distinct {
+        report_blocks(config, block_lines, out, session)?;
+    } else if config.length_histogram {
+        report_histogram(config, out, session)?;
+    } else if config.path_tree {
+        report_path_tree(config, out, session)?;
+    } else if config.convergence {
+        report_convergence(config, out, session)?;
+    } else if let Some(pattern) = &config.group_by_named {
+        report_named_groups(config, pattern, out, session)?;
+    } else if config.tfidf {
+        report_tfidf(config, out, session)?;
+    } else if config.compare {
+        report_compare(config, out, session)?;
+    } else if config.top_and_bottom {
+        report_top_and_bottom(config, out, session)?;
+    } else if config.count_distinct {
+        let logs = load_all(config, session)?;
+        let distinct = count_filtered(config, &logs, session).len();
+        writeln!(out, "{}", distinct).map_err(|e| format!("Unable to write output: {}", e))?;
+    } else if config.approx_distinct {
+        report_approx_distinct(config, out, session)?;
+    } else if let (Some(from), Some(to)) = (&config.edge_from, &config.edge_to) {
+        report(config, out, session, |logs, _, session| {
+            let (counts, skipped) = edge_counts(logs, from, to);
+            session.stats.skip_lines("--edge-from/--edge-to", skipped);
+            Ok(select_top_k(counts, config.k))
+        })?;
+    } else if config.runs {
+        report(config, out, session, |logs, _, _| {
+            let runs = top_k_runs(logs, config.k);
+            Ok(runs
+                .into_iter()
+                .map(|(line, length)| (line, RunLength(length)))
+                .collect())
+        })?;
+    } else if let Some(path) = &config.baseline {
+        let lines = read_lines(path, &config.read_options)?;
+        // The baseline is counted like the input but is not part of it
+        let stats = session.stats.clone();
+        let baseline = count_filtered(config, &lines, session);
+        session.stats = stats;
+        report(config, out, session, |logs, _, session| {
+            let observed = count_filtered(config, logs, session);
+            Ok(select_top_k_scored(
+                deviation_scores(&observed, &baseline),
+                config.k,
+            ))
+        })?;
+    } else if let Some(weights) = &config.severity_weights {
+        report(config, out, session, |logs, _, session| {
+            let (scores, stats) = weighted_scores(logs, weights, &config.token_filter);
+            session.stats.merge(stats);
+            Ok(select_top_k_scored(scores, config.k))
+        })?;
+    } else if let Some(limit) = config.include_positions {
+        report(config, out, session, |logs, label, session| {
+            let results = analyze(config, logs, label, session)?;
+            let words: Vec<String> = results.iter().map(|(word, _)| word.clone()).collect();
+            let key = |token: &str| counted_key(config, token);
+            let mut positions = if let Some(pool) = &session.pool {
+                pool.install(|| positions_by_key_parallel(logs, &words, limit, key))
+            } else if config.parallel {
+                positions_by_key_parallel(logs, &words, limit, key)
+            } else {
+                positions_by_key(logs, &words, limit, key)
+            };
+            Ok(results
+                .into_iter()
+                .map(|(word, count)| {
+                    let lines = positions.remove(&word).unwrap_or_default();
+                    (word, CountWithLines { count, lines })
+                })
+                .collect())
+        })?;
+    } else if config.format == OutputFormat::Parquet {
+        report_parquet(config, out, session)?;
+    } else if config.time_span {
+        report(config, out, session, |logs, label, session| {
+            let results = analyze(config, logs, label, session)?;
+            let words: Vec<String> = results.iter().map(|(word, _)| word.clone()).collect();
+            let spans = spans_by_key(
+                logs,
+                &words,
+                config.token_filter.max_tokens_per_line,
+                |token| counted_key(config, token),
+            );
+            Ok(results
+                .into_iter()
+                .map(|(word, count)| {
+                    let span = spans.get(&word).copied();
+                    (word, CountWithSpan { count, span })
+                })
+                .collect())
+        })?;
+    } else if streams(config) {
+        report_streamed(config, out, session)?;
+    } else {
+        report(config, out, session, |logs, label, session| {
+            analyze(config, logs, label, session)
+        })?;
+    }
+
+    // Counting was incomplete if the unique-word limit turned words away
+    if let Some(limit) = config.token_filter.max_unique {
+        let refused = session
+            .stats
+            .tokens_dropped
+            .get(&DropReason::UniqueLimit)
+            .copied()
+            .unwrap_or(0);
+        if refused > 0 {
+            let _ = writeln!(
+                session.err,
+                "warning: reached --max-unique limit of {} distinct words; {} tokens of new words were not counted",
+                limit, refused
+            );
+        }
+    }
+    // Pruning makes counts lossy; say by how much at most
+    if let Some(threshold) = config.token_filter.prune_threshold
+        && session.stats.prune_passes > 0
+    {
+        let _ = writeln!(
+            session.err,
+            "warning: --prune-threshold dropped {} rare words in {} passes; counts may be low by up to {}",
+            session.stats.words_pruned,
+            session.stats.prune_passes,
+            session.stats.prune_error_bound(threshold)
+        );
+    }
+    if config.explain {
+        let _ = session.stats.write_report(session.err);
+    }
+    Ok(())
+}
+
+/// Loads the inputs, ranks them with `rank` and writes the rows.
+///
+/// `rank` receives the lines of one input, the input's label when
+/// reporting per file, and the session.
+fn report<T, W, E, F>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+    rank: F,
+) -> Result<(), String>
+where
+    T: ResultValue,
+    W: Write,
+    E: Write,
+    F: Fn(&[String], Option<&str>, &mut Session<E>) -> Result<Vec<(String, T)>, String>,
+{
+    let display = config.display.unwrap_or(config.k);
+
+    if config.per_file {
+        // Count each file on its own and label every section by file name
+        let mut sections = Vec::with_capacity(config.files.len());
+        for (file, logs) in config.files.iter().zip(load_each(config, session)?) {
+            let mut results = rank(&logs, Some(file), session)?;
+            results.truncate(display);
+            sections.push((file.clone(), results));
+        }
+        return write_outputs(config, out, |mut writer, format| {
+            write_sections(&sections, "file", &mut writer, format)
+        });
+    }
+
+    // Merge all files into a single log before counting
+    let logs = load_all(config, session)?;
+
+    // Process the logs and get top K words, capping the printed rows
+    let mut results = rank(&logs, None, session)?;
+    results.truncate(display);
+    write_ranked(config, out, &results)
+}
+
+/// Writes the merged ranking, in sections with `--group-by-initial` or
+/// `--tiers`.
+fn write_ranked<T: ResultValue, W: Write>(
+    config: &Config,
+    out: &mut W,
+    results: &[(String, T)],
+) -> Result<(), String> {
+    if config.group_by_initial {
+        let groups = group_by_initial(results);
+        return write_outputs(config, out, |mut writer, format| {
+            write_sections(&groups, "initial", &mut writer, format)
+        });
+    }
+    if let Some(thresholds) = &config.tiers {
+        let tiers = group_by_tier(results, thresholds);
+        return write_outputs(config, out, |mut writer, format| {
+            write_sections(&tiers, "tier", &mut writer, format)
+        });
+    }
+    write_outputs(config, out, |mut writer, format| {
+        T::write_ranking(results, &mut writer, format)
+    })
+}
+
+/// Returns true when the plain top-K is counted line by line while the
+/// inputs are read instead of loading them first, so that a live source
+/// throttled by `--max-lines-per-sec` is counted as it arrives.
+///
+/// Per-file reports, `--sort none`, `--sort-by firstseen` and the counts
+/// of `--weight-field`, `--logfmt-key`, `--shape` and `--input-counts`
+/// need the loaded lines and never stream.
+fn streams(config: &Config) -> bool {
+    config.read_options.max_lines_per_sec.is_some()
+        && REPORT_MODES.iter().all(|mode| !mode.is_set(config))
+        && !config.per_file
+        && config.sort == SortOrder::Count
+        && config.sort_by != Some(SortKey::FirstSeen)
+        && config.weight_field.is_none()
+        && config.logfmt_key.is_none()
+        && !config.shape
+        && !config.input_counts
+}
+
+/// Writes the plain top-K of the inputs, counting every line as soon as
+/// it is read; see [`streams`].
+fn report_streamed<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let tokenizer = line_tokenizer(config);
+    let mut counter = WordCounter::new(&config.token_filter);
+    for file in &config.files {
+        for_each_line(config, file, session, |line| match &tokenizer {
+            Some(tokenizer) => counter.add_line_with(&line, tokenizer.as_ref()),
+            None => counter.add_line(&line),
+        })?;
+    }
+    let (frequency_map, stats) = counter.into_parts();
+    session.stats.merge(stats);
+
+    // Streaming rules out the orders that look at the lines again
+    let mut results = rank_counts(
+        config,
+        regroup_counts(config, frequency_map),
+        &[],
+        None,
+        session,
+    )?;
+    results.truncate(config.display.unwrap_or(config.k));
+    write_ranked(config, out, &results)
+}
+
+/// Writes a report with `write`, to `out` in the configured format and,
+/// with `--also-json`, once more as JSON to that file.
+fn write_outputs<W, F>(config: &Config, out: &mut W, write: F) -> Result<(), String>
+where
+    W: Write,
+    F: Fn(&mut dyn Write, OutputFormat) -> std::io::Result<()>,
+{
+    let write_error = |e: std::io::Error| format!("Unable to write output: {}", e);
+    write(out, config.format).map_err(write_error)?;
+    if let Some(path) = &config.also_json {
+        let file = File::create(path)
+            .map_err(|e| format!("Unable to create JSON output file '{}': {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+        write(&mut writer, OutputFormat::Json)
+            .and_then(|()| writer.flush())
+            .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// Writes the top-K TF-IDF words of every input file, one section each.
+fn report_tfidf<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    // Every file is one document of the collection
+    let mut documents = Vec::with_capacity(config.files.len());
+    for logs in load_each(config, session)? {
+        let mut counter = WordCounter::new(&config.token_filter);
+        counter.add_lines(&logs);
+        let (counts, stats) = counter.into_parts();
+        session.stats.merge(stats);
+        documents.push(counts);
+    }
+
+    let display = config.display.unwrap_or(config.k);
+    let sections: Vec<(String, Vec<(String, f64)>)> = config
+        .files
+        .iter()
+        .zip(tfidf_scores(&documents))
+        .map(|(file, scores)| {
+            let mut results = select_top_k_scored(scores, config.k);
+            results.truncate(display);
+            (file.clone(), results)
+        })
+        .collect();
+    write_sections(&sections, "file", out, config.format)
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes a HyperLogLog estimate of the number of distinct counted words
+/// with its expected relative error, e.g. `10234 (±0.81%)`.
+///
+/// Words go through the same filters, lemmas and buckets as when
+/// counting, but only the fixed-size sketch is kept instead of a map.
+fn report_approx_distinct<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let mut sketch = HyperLogLog::new();
+    for logs in load_each(config, session)? {
+        for line in &logs {
+            for_each_word_capped(line, config.token_filter.max_tokens_per_line, |token| {
+                if let Some(word) = counted_key(config, token) {
+                    sketch.insert(&word);
+                }
+            });
+        }
+    }
+    writeln!(
+        out,
+        "{:.0} (±{:.2}%)",
+        sketch.estimate(),
+        sketch.relative_error() * 100.0
+    )
+    .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes the top-K of the merged input as a Parquet file, with each
+/// word's percentage of all counted tokens.
+#[cfg(feature = "parquet")]
+fn report_parquet<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let mut results = analyze(config, &logs, None, session)?;
+    results.truncate(config.display.unwrap_or(config.k));
+
+    // The writer must be `Send`, and the file is small
+    let mut buffer = Vec::new();
+    write_parquet(&results, Some(session.counted_tokens), &mut buffer)
+        .and_then(|()| out.write_all(&buffer))
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn report_parquet<W: Write, E: Write>(
+    _config: &Config,
+    _out: &mut W,
+    _session: &mut Session<E>,
+) -> Result<(), String> {
+    Err("this build lacks the `parquet` feature".to_string())
+}
+
+/// Writes the word every token of the inputs is counted under, one per
+/// line in order of appearance, for debugging the tokenizer settings.
+///
+/// Tokens go through the line filters, preprocessing, token filters,
+/// lemmas and numeric buckets, as when counting; tokens a filter drops are
+/// not written, and `--max-unique` does not apply.
+fn report_tokens<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let limit = config.token_filter.max_tokens_per_line;
+    for logs in load_each(config, session)? {
+        for line in &logs {
+            let tokens = if let Some(tokenizer) = line_tokenizer(config) {
+                let mut tokens = tokenizer.tokenize(line);
+                tokens.truncate(limit.unwrap_or(usize::MAX));
+                tokens
+            } else {
+                let mut tokens = Vec::new();
+                for_each_word_capped(line, limit, |token| tokens.push(token.to_string()));
+                tokens
+            };
+            for word in tokens.iter().filter_map(|token| counted_key(config, token)) {
+                writeln!(out, "{}", word).map_err(|e| format!("Unable to write output: {}", e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Lists every line that does not match the `--validate` format as
+/// `file:line: text`, then a summary on the diagnostics stream.
+///
+/// Lines are checked as read, before any line filter or preprocessing, so
+/// the line numbers point into the files.
+fn report_validation<W: Write, E: Write>(
+    config: &Config,
+    format: &Regex,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    for file in &config.files {
+        let logs = read_lines(file, &config.read_options)?;
+        session.lines_read += logs.len();
+        for number in nonconforming_lines(&logs, format) {
+            writeln!(out, "{}:{}: {}", file, number, logs[number - 1])
+                .map_err(|e| format!("Unable to write output: {}", e))?;
+            session.invalid_lines += 1;
+        }
+    }
+    let _ = writeln!(
+        session.err,
+        "validate: {} of {} lines do not match '{}'",
+        session.invalid_lines,
+        session.lines_read,
+        format.as_str()
+    );
+    Ok(())
+}
+
+/// Writes the K most and the K least frequent words of the merged input
+/// as two sections, `top` and `bottom`, ranked from one frequency map.
+fn report_top_and_bottom<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let frequency_map = count_filtered(config, &logs, session);
+    let (mut top, mut bottom) = select_top_and_bottom_k(frequency_map, config.k, config.tie_break);
+    let display = config.display.unwrap_or(config.k);
+    top.truncate(display);
+    bottom.truncate(display);
+    let sections = [("top".to_string(), top), ("bottom".to_string(), bottom)];
+    write_sections(&sections, "section", out, config.format)
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes the top-K words of the two input files side by side.
+fn report_compare<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let display = config.display.unwrap_or(config.k);
+    let mut rankings = Vec::with_capacity(2);
+    for (file, logs) in config.files.iter().zip(load_each(config, session)?) {
+        let mut results = analyze(config, &logs, Some(file), session)?;
+        results.truncate(display);
+        rankings.push(results);
+    }
+    let rows = compare_rankings(&rankings[0], &rankings[1]);
+    let names = [config.files[0].as_str(), config.files[1].as_str()];
+    write_comparison(&rows, names, out, config.format)
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Reads all input files with [`load_each`] and merges them into one log.
+fn load_all<E: Write>(config: &Config, session: &mut Session<E>) -> Result<Vec<String>, String> {
+    Ok(load_each(config, session)?.concat())
+}
+
+/// Reads every input file and applies the line filters, in file order.
+///
+/// With `--jobs` the files are read on the worker pool, each into a
+/// session of its own. Their notes and statistics are folded back in file
+/// order, so the outcome matches a sequential run.
+fn load_each<E: Write>(
+    config: &Config,
+    session: &mut Session<E>,
+) -> Result<Vec<Vec<String>>, String> {
+    let Some(pool) = &session.pool else {
+        return config
+            .files
+            .iter()
+            .map(|file| load(config, file, session))
+            .collect();
+    };
+    let loaded: Vec<_> = pool.install(|| {
+        config
+            .files
+            .par_iter()
+            .map(|file| {
+                let mut notes = Vec::new();
+                let mut file_session = Session::new(&mut notes);
+                let logs = load(config, file, &mut file_session);
+                let Session {
+                    stats,
+                    lines_read,
+                    empty_lines,
+                    line_lengths,
+                    ..
+                } = file_session;
+                (logs, notes, stats, lines_read, empty_lines, line_lengths)
+            })
+            .collect()
+    });
+
+    let mut inputs = Vec::with_capacity(loaded.len());
+    for (logs, notes, stats, lines_read, empty_lines, line_lengths) in loaded {
+        let _ = session.err.write_all(&notes);
+        session.stats.merge(stats);
+        session.lines_read += lines_read;
+        session.empty_lines += empty_lines;
+        session.line_lengths.merge(line_lengths);
+        inputs.push(logs?);
+    }
+    Ok(inputs)
+}
+
+/// Reads one input file and applies the configured line filters.
+fn load<E: Write>(
+    config: &Config,
+    file: &str,
+    session: &mut Session<E>,
+) -> Result<Vec<String>, String> {
+    let mut logs = Vec::new();
+    for_each_line(config, file, session, |line| logs.push(line))?;
+    Ok(logs)
+}
+
+/// Reads one input file record by record, passing every line the
+/// configured line filters keep to `f`, transformed, as soon as it is read.
+fn for_each_line<E: Write>(
+    config: &Config,
+    file: &str,
+    session: &mut Session<E>,
+    mut f: impl FnMut(String),
+) -> Result<(), String> {
+    let mut markers = MarkerState::default();
+    let mut outside_markers = 0;
+    let mut outside_time_range = 0;
+    let mut without_field = 0;
+    let mut without_capture = 0;
+    stream_lines(file, &config.read_options, |line| {
+        session.lines_read += 1;
+        if config.count_empty_lines && line.trim().is_empty() {
+            session.empty_lines += 1;
+        }
+        if config.line_length_stats {
+            session.line_lengths.add(&line);
+        }
+        if !config.markers.is_empty() && !config.markers.admit(&line, &mut markers) {
+            outside_markers += 1;
+            return;
+        }
+        if !config.time_range.is_empty() && !config.time_range.admit(&line) {
+            outside_time_range += 1;
+            return;
+        }
+        let line = match &config.json_field {
+            Some(field) => match field.extract(&line) {
+                Some(value) => value,
+                None => {
+                    without_field += 1;
+                    return;
+                }
+            },
+            None => line,
+        };
+        let line = match &config.capture {
+            Some(capture) => match capture.extract(&line) {
+                Some(text) => text,
+                None => {
+                    without_capture += 1;
+                    return;
+                }
+            },
+            None => line,
+        };
+        if config.preprocessor.is_empty() {
+            f(line);
+        } else {
+            f(config.preprocessor.apply(&line));
+        }
+    })?;
+
+    if !config.markers.is_empty() {
+        session
+            .stats
+            .skip_lines("--start-after/--stop-at", outside_markers);
+    }
+    if !config.time_range.is_empty() {
+        session
+            .stats
+            .skip_lines("--since/--until", outside_time_range);
+    }
+    if config.json_field.is_some() {
+        if without_field > 0 {
+            let _ = writeln!(
+                session.err,
+                "note: skipped {} lines without a JSON string field in '{}'",
+                without_field, file
+            );
+        }
+        session.stats.skip_lines("--json-field", without_field);
+    }
+    if config.capture.is_some() {
+        session.stats.skip_lines("--capture", without_capture);
+    }
+    Ok(())
+}
+
+/// Writes the number of distinct counted words in each block of lines.
+///
+/// Blocks are taken from the merged input after all line filters, and
+/// every token filter and transform applies within a block.
+fn report_blocks<W: Write, E: Write>(
+    config: &Config,
+    block_lines: usize,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let rows: Vec<(usize, usize)> = logs
+        .chunks(block_lines)
+        .map(|block| count_filtered(config, block, session).len())
+        .enumerate()
+        .collect();
+    write_rows(&rows, ["block", "distinct"], out, config.format)
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes how many counted words or tokens have each length.
+///
+/// The debug format draws a bar chart; the other formats print
+/// `(length, count)` rows.
+fn report_histogram<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let counts = count_filtered(config, &logs, session);
+    if let Some(word) = &config.require_word {
+        session.required_count += counts.get(word).copied().unwrap_or(0);
+    }
+    let histogram = length_histogram(&counts, config.histogram_unit);
+    let result = if config.format == OutputFormat::Debug {
+        write_chart(&histogram, out)
+    } else {
+        let rows: Vec<(usize, usize)> = histogram.into_iter().collect();
+        let columns = ["length", config.histogram_unit.name()];
+        write_rows(&rows, columns, out, config.format)
+    };
+    result.map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes the path tree of the merged input, indented in the debug
+/// format and as nested objects in JSON, with the top K children of each
+/// node.
+fn report_path_tree<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let tree = path_tree(&logs);
+    let result = if config.format == OutputFormat::Json {
+        tree.write_json(config.k, out)
+    } else {
+        tree.write_indented(config.k, out)
+    };
+    result.map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes the line of the merged input after which its top-K stopped
+/// changing, out of all its lines, e.g. `120 of 5000 lines`.
+///
+/// Lines are numbered after the line filters, so with `--start-after` or
+/// `--since` they count from the first line kept.
+fn report_convergence<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let line = convergence_by_key(
+        &logs,
+        config.k,
+        config.token_filter.max_tokens_per_line,
+        |token| counted_key(config, token),
+    );
+    writeln!(out, "{} of {} lines", line, logs.len())
+        .map_err(|e| format!("Unable to write output: {}", e))
+}
+
+/// Writes the top-K words of the merged input's lines per value of every
+/// named capture group of `pattern`, split in one pass.
+///
+/// Each value's lines are counted with every configured filter and
+/// transform, as the plain report counts the whole input.
+fn report_named_groups<W: Write, E: Write>(
+    config: &Config,
+    pattern: &Regex,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let display = config.display.unwrap_or(config.k);
+    let mut groups = Vec::new();
+    for (name, values) in lines_by_named_group(&logs, pattern) {
+        let mut sections = Vec::with_capacity(values.len());
+        for (value, lines) in values {
+            let counts = count_filtered(config, &lines, session);
+            let mut results = select_top_k_by(counts, config.k, config.tie_break);
+            results.truncate(display);
+            sections.push((value, results));
+        }
+        groups.push((name, sections));
+    }
+    write_outputs(config, out, |mut writer, format| {
+        write_nested_sections(&groups, "group", &mut writer, format)
+    })
+}
+
+/// Counts all inputs once and hands the counts to an interactive session.
+fn interact<W: Write, E: Write>(
+    config: &Config,
+    out: &mut W,
+    session: &mut Session<E>,
+) -> Result<(), String> {
+    let logs = load_all(config, session)?;
+    let counts = count_filtered(config, &logs, session);
+
+    let mut repl = Repl::new(counts, config.format);
+    repl.run(std::io::stdin().lock(), out, session.err)
+        .map_err(|e| format!("Unable to run interactive session: {}", e))
+}
+
+/// Counts the words of one input with every configured token filter and
+/// transform applied, recording what the filters removed.
+///
+/// Lemmas are applied before numeric buckets, both after the token
+/// filters have seen the surface forms; `--fuzzy-merge` then merges the
+/// resulting words.
+///
+/// With `--weight-field` each occurrence adds its line's weight instead of
+/// one, and with `--logfmt-key` each line adds its value of that key
+/// instead of its words; with `--shape` each word adds to its shape. These
+/// and `--whitespace-tokens` or `--field-separator` run on a single
+/// thread even under `--parallel` or `--jobs`, as do `--max-unique` and
+/// `--prune-threshold`, which depend on the order the lines are seen in.
+fn count_filtered<E: Write>(
+    config: &Config,
+    logs: &[String],
+    session: &mut Session<E>,
+) -> HashMap<String, usize> {
+    let chunked = !config.token_filter.is_order_dependent();
+    let (frequency_map, stats) = if let Some(field) = config.weight_field {
+        let (counts, stats, skipped) = field_weighted_counts(logs, field, &config.token_filter);
+        session.stats.skip_lines("--weight-field", skipped);
+        (counts, stats)
+    } else if let Some(key) = &config.logfmt_key {
+        let (counts, stats, skipped) = logfmt_value_counts(logs, key, &config.token_filter);
+        session.stats.skip_lines("--logfmt-key", skipped);
+        (counts, stats)
+    } else if config.shape {
+        shape_counts(logs, config.shape_case, &config.token_filter)
+    } else if config.input_counts {
+        let (counts, stats, skipped) = precounted_counts(logs, &config.token_filter);
+        if skipped > 0 {
+            let _ = writeln!(
+                session.err,
+                "warning: skipped {} malformed lines (expected `word count`)",
+                skipped
+            );
+        }
+        session.stats.skip_lines("--input-counts", skipped);
+        (counts, stats)
+    } else if let Some(tokenizer) = line_tokenizer(config) {
+        let mut counter = WordCounter::new(&config.token_filter);
+        for line in logs {
+            counter.add_line_with(line, tokenizer.as_ref());
+        }
+        counter.into_parts()
+    } else if let Some(pool) = session.pool.as_ref().filter(|_| chunked) {
+        pool.install(|| count_words_parallel_filtered(logs, &config.token_filter))
+    } else if config.parallel && chunked {
+        count_words_parallel_filtered(logs, &config.token_filter)
+    } else {
+        let mut counter = WordCounter::new(&config.token_filter);
+        counter.add_lines(logs);
+        counter.into_parts()
+    };
+    session.stats.merge(stats);
+    regroup_counts(config, frequency_map)
+}
+
+/// Applies the lemmas, numeric buckets and fuzzy merging of
+/// [`count_filtered`] to freshly counted words.
+fn regroup_counts(
+    config: &Config,
+    frequency_map: HashMap<String, usize>,
+) -> HashMap<String, usize> {
+    let frequency_map = match &config.lemmatizer {
+        Some(lemmatizer) => regroup(frequency_map, |word| {
+            Some(lemmatizer.lemma(word).to_string())
+        }),
+        None => frequency_map,
+    };
+    let frequency_map = match &config.numeric_buckets {
+        Some(buckets) => regroup(frequency_map, |word| {
+            buckets.bucket_token(word, config.bucket_keep_words)
+        }),
+        None => frequency_map,
+    };
+    match config.fuzzy_merge {
+        Some(distance) => fuzzy_merge(frequency_map, distance),
+        None => frequency_map,
+    }
+}
+
+/// The tokenizer `--whitespace-tokens`, `--keep-apostrophes`,
+/// `--keep-units` or `--field-separator` selects in place of the built-in word splitting,
+/// if any.
+fn line_tokenizer(config: &Config) -> Option<Box<dyn Tokenizer>> {
+    if let Some(separator) = config.field_separator {
+        Some(Box::new(
+            FieldTokenizer::new(separator).trimmed(config.trim_fields),
+        ))
+    } else if config.whitespace_tokens {
+        Some(Box::new(WhitespaceTokenizer))
+    } else if config.keep_apostrophes {
+        Some(Box::new(ApostropheTokenizer))
+    } else if config.keep_units {
+        Some(Box::new(UnitTokenizer))
+    } else {
+        None
+    }
+}
+
+/// Returns the word a token is counted under after the token filters,
+/// lemmas and numeric buckets of [`count_filtered`], or `None` when it
+/// is not counted.
+fn counted_key(config: &Config, token: &str) -> Option<String> {
+    if config.token_filter.check(token).is_some() {
+        return None;
+    }
+    let word = match &config.lemmatizer {
+        Some(lemmatizer) => lemmatizer.lemma(token),
+        None => token,
+    };
+    match &config.numeric_buckets {
+        Some(buckets) => buckets.bucket_token(word, config.bucket_keep_words),
+        None => Some(word.to_string()),
+    }
+}
+
+/// Selects the top-K words of one input and reports summary statistics.
+///
+/// All summary math (such as coverage) uses the full K selection; the
+/// caller caps the printed rows by `--display` afterwards.
+fn analyze<E: Write>(
+    config: &Config,
+    logs: &[String],
+    label: Option<&str>,
+    session: &mut Session<E>,
+) -> Result<Vec<(String, usize)>, String> {
+    let frequency_map = count_filtered(config, logs, session);
+    rank_counts(config, frequency_map, logs, label, session)
+}
+
+/// Selects the top-K of the counts of [`analyze`], which were taken from
+/// `logs`, and reports summary statistics.
+///
+/// The lines are only read again for `--sort none` and `--sort-by
+/// firstseen`.
+fn rank_counts<E: Write>(
+    config: &Config,
+    mut frequency_map: HashMap<String, usize>,
+    logs: &[String],
+    label: Option<&str>,
+    session: &mut Session<E>,
+) -> Result<Vec<(String, usize)>, String> {
+    if let Some(path) = &config.state {
+        frequency_map = accumulate(path, frequency_map)?;
+    }
+    if let Some(word) = &config.require_word {
+        session.required_count += frequency_map.get(word).copied().unwrap_or(0);
+    }
+    let label = label.map(|name| format!(" ({})", name)).unwrap_or_default();
+    if config.summary {
+        let stats = FreqStats::from_counts(&frequency_map);
+        let _ = writeln!(session.err, "summary{}: {}", label, stats);
+    }
+    if config.entropy {
+        let bits = entropy(&frequency_map);
+        let _ = writeln!(session.err, "entropy{}: {:.3} bits", label, bits);
+    }
+    let total_tokens: usize = frequency_map.values().sum();
+    session.counted_tokens += total_tokens;
+    let mut results = match config.sort {
+        SortOrder::Count => {
+            // Only the counts are kept to spot ties cut off at the boundary
+            let counts: Vec<usize> = frequency_map.values().copied().collect();
+            let results = select_top_k_by(frequency_map, config.k, config.tie_break);
+            let tied = tied_beyond_cut(&counts, &results, config.k);
+            if tied > 0 {
+                let _ = writeln!(
+                    session.err,
+                    "note{}: {} more words tie with the last shown word at count {}; a k of {} shows them all",
+                    label,
+                    tied,
+                    results[results.len() - 1].1,
+                    config.k + tied
+                );
+            }
+            results
+        }
+        SortOrder::None => {
+            let order = discovery_order(logs).into_iter();
+            match &config.numeric_buckets {
+                Some(buckets) => {
+                    let keys = order
+                        .filter_map(|word| buckets.bucket_token(&word, config.bucket_keep_words));
+                    select_in_order(frequency_map, keys, config.k)
+                }
+                None => select_in_order(frequency_map, order, config.k),
+            }
+        }
+    };
+
+    if let Some(key) = config.sort_by {
+        order_rows(config, logs, &mut results, key);
+    }
+
+    if config.coverage {
+        let _ = writeln!(
+            session.err,
+            "coverage{}: top {} words cover {:.2}% of {} tokens",
+            label,
+            results.len(),
+            coverage_percent(&results, total_tokens),
+            total_tokens
+        );
+    }
+
+    Ok(results)
+}
+
+/// Reorders the selected rows by `key` in the configured direction,
+/// keeping rows the key does not tell apart in rank order.
+///
+/// First appearances are those of the words as counted, after the token
+/// filters, lemmas and buckets.
+fn order_rows(config: &Config, logs: &[String], rows: &mut [(String, usize)], key: SortKey) {
+    let direction = config.sort_dir.unwrap_or(key.default_direction());
+    let first_seen = if key == SortKey::FirstSeen {
+        first_appearances(config, logs)
+    } else {
+        HashMap::new()
+    };
+    rows.sort_by(|a, b| {
+        let order = match key {
+            SortKey::Count => a.1.cmp(&b.1),
+            SortKey::Alpha => a.0.cmp(&b.0),
+            SortKey::Length => a.0.chars().count().cmp(&b.0.chars().count()),
+            SortKey::FirstSeen => first_seen
+                .get(&a.0)
+                .unwrap_or(&usize::MAX)
+                .cmp(first_seen.get(&b.0).unwrap_or(&usize::MAX)),
+        };
+        match direction {
+            SortDirection::Asc => order,
+            SortDirection::Desc => order.reverse(),
+        }
+    });
+}
+
+/// Numbers the counted words of `logs` in the order they first appear.
+fn first_appearances(config: &Config, logs: &[String]) -> HashMap<String, usize> {
+    let mut order = HashMap::new();
+    let mut note = |token: &str| {
+        if let Some(word) = counted_key(config, token) {
+            let next = order.len();
+            order.entry(word).or_insert(next);
+        }
+    };
+    let tokenizer = line_tokenizer(config);
+    let limit = config.token_filter.max_tokens_per_line;
+    for line in logs {
+        match &tokenizer {
+            Some(tokenizer) => {
+                let tokens = tokenizer.tokenize(line);
+                tokens
+                    .iter()
+                    .take(limit.unwrap_or(usize::MAX))
+                    .for_each(|token| note(token));
+            }
+            None => for_each_word_capped(line, limit, &mut note),
+        }
+    }
+    order
+}
+
+/// How many words outside a top-`k` have the count of its last word, so
+/// the tie-break alone decided they were cut.
+fn tied_beyond_cut(counts: &[usize], results: &[(String, usize)], k: usize) -> usize {
+    match results.last() {
+        Some((_, last)) if results.len() == k => {
+            let shown = results.iter().filter(|(_, count)| count == last).count();
+            counts.iter().filter(|&count| count == last).count() - shown
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_util::temp_file;
+    use serde_json::{Value, json};
+
+    /// Runs the CLI with the given arguments and captures its output
+    pub(crate) fn run_cli(args: &[&str]) -> (i32, String, String) {
+        let args: Vec<String> = std::iter::once("lwa")
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&args, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    /// Test parsing of files, k and options
+    #[test]
+    fn test_parse_args() {
+        let args: Vec<String> = ["lwa", "a.log", "--format", "json", "b.log", "3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = Config::from_args(&args).unwrap();
+
+        assert_eq!(config.files, vec!["a.log".to_string(), "b.log".to_string()]);
+        assert_eq!(config.k, 3);
+        assert_eq!(config.format, OutputFormat::Json);
+        assert!(!config.per_file);
+        assert_eq!(config.display, None);
+    }
+
+    /// Test that conflicting options are named in the one conflict message
+    #[test]
+    fn test_conflicting_options() {
+        let parse = |extra: &[&str]| {
+            let args: Vec<String> = ["lwa", "a.log", "3"]
+                .iter()
+                .chain(extra)
+                .map(|s| s.to_string())
+                .collect();
+            Config::from_args(&args).err()
+        };
+
+        assert_eq!(
+            parse(&["--tfidf", "--runs"]).as_deref(),
+            Some("--tfidf cannot be combined with --runs")
+        );
+        assert_eq!(
+            parse(&["--per-file", "--tiers", "2"]).as_deref(),
+            Some("--per-file cannot be combined with --tiers")
+        );
+        assert_eq!(
+            parse(&["--shape", "--whitespace-tokens"]).as_deref(),
+            Some("--shape cannot be combined with --whitespace-tokens")
+        );
+        assert_eq!(
+            parse(&["--tfidf", "--per-file", "--sort-dir", "asc"]).as_deref(),
+            Some("--sort-dir needs --sort-by")
+        );
+    }
+
+    /// Test that missing or invalid arguments are rejected
+    #[test]
+    fn test_invalid_args() {
+        let (code, out, err) = run_cli(&["logs.txt"]);
+        assert_eq!(code, 1);
+        assert!(out.is_empty());
+        assert!(err.contains("Usage:"));
+
+        let (code, _, err) = run_cli(&["logs.txt", "five"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("k must be a non-negative integer, got 'five'"));
+    }
+
+    /// Test that k accepts a sign and a whole decimal but not negatives
+    #[test]
+    fn test_lenient_k() {
+        let file = temp_file("lenient_k.log", "error disk\nerror\n");
+
+        for k in ["+5", "5.0", "+1.00"] {
+            let config =
+                Config::from_args(&["lwa".to_string(), file.clone(), k.to_string()]).unwrap();
+            assert_eq!(config.k, if k == "+1.00" { 1 } else { 5 });
+        }
+        let (code, out, _) = run_cli(&[&file, "+1"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2)]\n");
+
+        for k in ["-3", "abc", "5.5", "+-5", "."] {
+            let (code, _, err) = run_cli(&[&file, k]);
+            assert_eq!(code, 1);
+            assert!(
+                err.contains(&format!("k must be a non-negative integer, got '{}'", k)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    /// Test k = usize::MAX and a k that overflows usize
+    #[test]
+    fn test_huge_k() {
+        let file = temp_file("huge_k.log", "error disk\nerror\n");
+
+        let max = usize::MAX.to_string();
+        let (code, out, _) = run_cli(&[&file, &max, "--display", &max]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2), (\"disk\", 1)]\n");
+
+        let overflow = format!("{}0", usize::MAX);
+        let (code, _, err) = run_cli(&[&file, &overflow]);
+        assert_eq!(code, 1);
+        assert!(err.contains(&format!("k is too large, got '{}'", overflow)));
+
+        let (code, _, err) = run_cli(&[&file, "2", "--display", &overflow]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--display is too large"));
+    }
+
+    /// Test that multiple files are merged into one aggregate result
+    #[test]
+    fn test_multiple_files_aggregate() {
+        let a = temp_file("aggregate_a.log", "error disk\nerror\n");
+        let b = temp_file("aggregate_b.log", "disk network\n");
+
+        let (code, out, _) = run_cli(&[&a, &b, "2"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 2), (\"error\", 2)]\n");
+    }
+
+    /// Test that --per-file reports separate results headed by file name
+    #[test]
+    fn test_per_file_sections() {
+        let a = temp_file("per_file_a.log", "error disk\nerror\n");
+        let b = temp_file("per_file_b.log", "network network disk\n");
+
+        let (code, out, _) = run_cli(&[&a, &b, "1", "--per-file"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            format!(
+                "==> {} <==\n[(\"error\", 2)]\n\n==> {} <==\n[(\"network\", 2)]\n",
+                a, b
+            )
+        );
+
+        let (code, out, _) = run_cli(&[&a, &b, "1", "--per-file", "--format", "json"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            format!(
+                "{{\"{}\":[{{\"word\":\"error\",\"count\":2}}],\"{}\":[{{\"word\":\"network\",\"count\":2}}]}}\n",
+                a, b
+            )
+        );
+    }
+
+    /// Test that --display caps printed rows while coverage uses the full K
+    #[test]
+    fn test_display_independent_of_k() {
+        // 150 distinct words: w0 appears 151 times, w1..w149 once each
+        let mut contents = String::new();
+        for _ in 0..150 {
+            contents.push_str("w0\n");
+        }
+        for i in 0..150 {
+            contents.push_str(&format!("w{}\n", i));
+        }
+        let file = temp_file("display.log", &contents);
+
+        let (code, out, err) = run_cli(&[&file, "100", "--display", "10", "--coverage"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out.matches("(\"").count(), 10);
+        assert!(out.starts_with("[(\"w0\", 151), (\"w1\", 1)"));
+
+        // The top 100 cover w0 (151) plus 99 singletons out of 300 tokens
+        assert_eq!(
+            err,
+            "note: 50 more words tie with the last shown word at count 1; a k of 150 shows them all\n\
+             coverage: top 100 words cover 83.33% of 300 tokens\n"
+        );
+    }
+
+    /// Test that --start-after/--stop-at count only the lines between markers
+    #[test]
+    fn test_marker_range() {
+        let file = temp_file(
+            "markers.log",
+            "warning boot\nGET /checkout id=7\nerror timeout\nerror retry\nDONE id=7\nwarning after\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "5", "--start-after", "id=7", "--stop-at", "^DONE"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2), (\"retry\", 1), (\"timeout\", 1)]\n");
+    }
+
+    /// Test that --tokenize-only prints the filtered tokens in order
+    #[test]
+    fn test_tokenize_only_option() {
+        let file = temp_file(
+            "tokenize_only.log",
+            "ERROR: user's \"disk-0\" (sda1) failed!!\n\n--- a.b,c ---\n",
+        );
+
+        let (code, out, _) = run_cli(&["--tokenize-only", &file]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "error\nuser\ns\ndisk\n0\nsda1\nfailed\na\nb\nc\n");
+
+        let (code, out, _) = run_cli(&[
+            "--tokenize-only",
+            "--stop-words",
+            "failed",
+            "--min-len",
+            "2",
+            "--whitespace-tokens",
+            &file,
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "error\nuser's\ndisk-0\nsda1\na.b,c\n");
+    }
+
+    /// Test that --print-config reports flag values over the defaults
+    #[test]
+    fn test_print_config_option() {
+        let file = temp_file("print_config.log", "error disk\n");
+
+        let (code, out, err) = run_cli(&[
+            &file,
+            "3",
+            "--print-config",
+            "--min-len",
+            "4",
+            "--format",
+            "csv",
+            "--tie-break",
+            "length",
+            "--strip-prefix",
+            r"^\d+ ",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "word,count\nerror,1\ndisk,1\n");
+
+        let config: Value = serde_json::from_str(&err).unwrap();
+        assert_eq!(config["k"], 3);
+        assert_eq!(config["token_filter"]["min_len"], 4);
+        assert_eq!(config["format"], "csv");
+        assert_eq!(config["tie_break"], "length_then_alpha");
+        assert_eq!(config["read_options"]["separator"], json!({"byte": 10}));
+        assert_eq!(config["preprocessor"]["strip_prefix"], r"^\d+ ");
+        assert_eq!(config["files"], json!([file]));
+        assert_eq!(config["time_range"]["since"], Value::Null);
+    }
+
+    /// Test that --validate lists nonconforming lines and sets the exit code
+    #[test]
+    fn test_validate_option() {
+        let file = temp_file(
+            "validate.log",
+            "[INFO] up\n[WARN] slow\ngarbage\n[ERROR] down\n\n",
+        );
+        let format = r"^\[(INFO|WARN|ERROR)\] ";
+
+        let (code, out, err) = run_cli(&["--validate", format, &file]);
+        assert_eq!(code, EXIT_REQUIREMENT_FAILED);
+        assert_eq!(out, format!("{0}:3: garbage\n{0}:5: \n", file));
+        assert!(err.contains("validate: 2 of 5 lines do not match"));
+
+        let (code, _, _) = run_cli(&["--validate", format, "--max-failures", "2", &file]);
+        assert_eq!(code, 0);
+
+        let (code, _, err) = run_cli(&[&file, "3", "--max-failures", "2"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--max-failures needs --validate"));
+    }
+
+    /// Test the exit code of --require-word for present and absent words
+    #[test]
+    fn test_require_word_exit_code() {
+        let file = temp_file("require.log", "error timeout\nerror Timeout retry\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--require-word", "TIMEOUT"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2)]\n");
+
+        let (code, out, err) = run_cli(&[&file, "1", "--require-word", "panic"]);
+        assert_eq!(code, EXIT_REQUIREMENT_FAILED);
+        assert_eq!(out, "[(\"error\", 2)]\n");
+        assert!(err.contains("note: 'panic' occurred 0 times, required at least 1"));
+
+        let (code, _, _) = run_cli(&[
+            &file,
+            "1",
+            "--require-word",
+            "timeout",
+            "--require-count",
+            "3",
+        ]);
+        assert_eq!(code, EXIT_REQUIREMENT_FAILED);
+
+        let (code, _, err) = run_cli(&[&file, "1", "--require-word", "disk-full"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("error: --require-word must be a single word, got 'disk-full'"));
+    }
+
+    /// Test that --files-from counts every listed file together
+    #[test]
+    fn test_files_from_manifest() {
+        let first = temp_file("manifest_a.log", "error disk\nerror timeout\n");
+        let second = temp_file("manifest_b.log", "error retry\ndisk\n");
+        let manifest = temp_file(
+            "manifest.txt",
+            &format!("# nightly inputs\n{}\n\n  {}  \n", first, second),
+        );
+
+        let (code, out, _) = run_cli(&["3", "--files-from", &manifest]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3), (\"disk\", 2), (\"retry\", 1)]\n");
+    }
+
+    /// Test reading a directory with and without --recursive
+    #[test]
+    fn test_recursive_directory_input() {
+        let root = std::env::temp_dir().join(format!("lwa_{}_recursive", std::process::id()));
+        let nested = root.join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join("app.log"), "error disk\nerror timeout\n").unwrap();
+        std::fs::write(nested.join("db.log"), "error retry\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "ignored words\n").unwrap();
+        let root = root.to_string_lossy().into_owned();
+
+        let (code, out, _) = run_cli(&[&root, "2", "--recursive", "--glob", "*.log"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3), (\"disk\", 1)]\n");
+
+        let (code, out, _) = run_cli(&[&root, "5", "--recursive", "--per-file"]);
+        assert_eq!(code, 0);
+        assert_eq!(out.matches("==> ").count(), 3);
+
+        let (code, _, err) = run_cli(&[&root, "2"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("is a directory; pass --recursive"));
+    }
+
+    /// Test that --newest reads only the most recently modified files
+    #[test]
+    fn test_newest_option() {
+        let root = std::env::temp_dir().join(format!("lwa_{}_newest", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (age, word) in [(4, "oldest"), (1, "newest"), (3, "older"), (2, "newer")] {
+            let path = root.join(format!("{}.log", word));
+            std::fs::write(&path, format!("{} error\n", word)).unwrap();
+            let modified = std::time::SystemTime::now() - Duration::from_secs(age * 3600);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        std::fs::write(root.join("notes.txt"), "ignored\n").unwrap();
+        let root = root.to_string_lossy().into_owned();
+
+        let (code, out, _) = run_cli(&[
+            &root,
+            "5",
+            "--recursive",
+            "--glob",
+            "*.log",
+            "--newest",
+            "2",
+            "--per-file",
+            "--format",
+            "csv",
+        ]);
+
+        assert_eq!(code, 0);
+        // Newest first: newest.log, then newer.log
+        let newest = out.find("newest.log").unwrap();
+        let newer = out.find("newer.log").unwrap();
+        assert!(newest < newer);
+        assert!(!out.contains("older") && !out.contains("oldest"));
+
+        // The files directly in the directory, summed into one count
+        let (code, out, err) = run_cli(&[&root, "5", "--glob", "*.log", "--newest", "2"]);
+        assert_eq!(code, 0, "{}", err);
+        assert_eq!(out, "[(\"error\", 2), (\"newer\", 1), (\"newest\", 1)]\n");
+
+        let (code, _, err) = run_cli(&[&root, "5", "--glob", "*.log"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--glob needs --recursive or --newest"));
+    }
+
+    /// Test that --weight-field lets a heavy line outrank frequent words
+    #[test]
+    fn test_weight_field_option() {
+        let file = temp_file(
+            "weighted.log",
+            "GET /health 2\nGET /health 2\nGET /health 2\nGET /backup.tar 100\nGET /broken -\n",
+        );
+
+        let (code, out, err) = run_cli(&[&file, "3", "--weight-field", "3", "--explain"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"get\", 106), (\"backup\", 100), (\"tar\", 100)]\n");
+        assert!(err.contains("explain: 1 lines skipped by --weight-field\n"));
+    }
+
+    /// Test that two runs against one --state file report running totals
+    #[test]
+    fn test_state_accumulates_across_runs() {
+        let state = temp_file("state_cli.json", "");
+        let day1 = temp_file("state_day1.log", "error disk\nerror timeout\n");
+        let day2 = temp_file("state_day2.log", "disk full\ndisk retry\n");
+
+        let (code, out, _) = run_cli(&[&day1, "2", "--state", &state]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2), (\"disk\", 1)]\n");
+
+        let (code, out, _) = run_cli(&[&day2, "2", "--state", &state]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 3), (\"error\", 2)]\n");
+    }
+
+    /// Test --block-distinct rows including a final partial block
+    #[test]
+    fn test_block_distinct_option() {
+        let file = temp_file("blocks.log", "a b\na\nc d e\nf\ng g\n");
+
+        let (code, out, _) = run_cli(&[&file, "10", "--block-distinct", "2", "--format", "csv"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "block,distinct\n0,2\n1,4\n2,1\n");
+    }
+
+    /// Test that --lemma-file counts surface forms under their lemma
+    #[test]
+    fn test_lemma_file_option() {
+        let lemmas = temp_file("lemmas.tsv", "ran\trun\nruns\trun\n");
+        let file = temp_file("lemma_input.log", "job ran\njob runs\nrun again\n");
+
+        let (code, out, _) = run_cli(&[&file, "2", "--lemma-file", &lemmas]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"run\", 3), (\"job\", 2)]\n");
+    }
+
+    /// Test that --dry-run lists the inputs without counting them
+    #[test]
+    fn test_dry_run_lists_inputs() {
+        let file = temp_file("dry_run.log", "error disk\n");
+
+        let (code, out, err) = run_cli(&[&file, "3", "--dry-run", "--min-len", "2"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "");
+        assert!(err.starts_with("dry run: 1 input files\n"));
+        assert!(err.contains(&format!("  {}: 11 bytes, compression none\n", file)));
+        assert!(err.contains("mode: top 3 by count, merged, format debug\n"));
+        assert!(err.contains("line filters: none\ntoken filters: --min-len\n"));
+
+        let (code, _, err) = run_cli(&["no_such_file.log", "3", "--dry-run"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("  no_such_file.log: error: "));
+    }
+
+    /// Test that --interactive refuses stdin as an input file
+    #[test]
+    fn test_interactive_rejects_stdin_input() {
+        let (code, _, err) = run_cli(&["-", "5", "--interactive"]);
+
+        assert_eq!(code, 1);
+        assert!(err.contains("error: --interactive reads commands from stdin"));
+    }
+
+    /// Test that --summary reports the count distribution on stderr
+    #[test]
+    fn test_summary_option() {
+        let file = temp_file("summary.log", "error error error disk disk timeout\n");
+
+        let (code, out, err) = run_cli(&[&file, "1", "--summary"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3)]\n");
+        assert_eq!(
+            err,
+            "summary: 3 distinct words, count min 1, max 3, mean 2.00, median 2, p90 3, p99 3\n"
+        );
+    }
+
+    /// Test that --count-distinct reports the vocabulary left by the filters
+    #[test]
+    fn test_count_distinct_with_stop_words() {
+        let file = temp_file(
+            "count_distinct.log",
+            "the disk is full\nthe disk is gone\nretry the write\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "1", "--count-distinct"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "7\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--count-distinct", "--stop-words", "the,is"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "5\n");
+    }
+
+    /// Test that --approx-distinct estimates the filtered vocabulary
+    #[test]
+    fn test_approx_distinct_option() {
+        let file = temp_file(
+            "approx_distinct.log",
+            "the disk is full\nthe disk is gone\nretry the write\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "1", "--approx-distinct", "--stop-words", "the,is"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "5 (±0.81%)\n");
+    }
+
+    /// Test the top and bottom sections of --top-and-bottom
+    #[test]
+    fn test_top_and_bottom_sections() {
+        let file = temp_file(
+            "top_bottom.log",
+            "error error error disk disk\nerror timeout retry disk\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--top-and-bottom"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "==> top <==\n[(\"error\", 4), (\"disk\", 3)]\n\n\
+             ==> bottom <==\n[(\"retry\", 1), (\"timeout\", 1)]\n"
+        );
+    }
+
+    /// Test that --compare shows the counts of both files
+    #[test]
+    fn test_compare_two_files() {
+        let before = temp_file("compare_before.log", "error error error disk\ndisk retry\n");
+        let after = temp_file("compare_after.log", "timeout timeout error\n");
+
+        let (code, out, _) = run_cli(&[&before, &after, "2", "--compare"]);
+
+        assert_eq!(code, 0);
+        let width = before.len().max("timeout".len());
+        let expected = format!(
+            "{:<w$}  {:<f$}  {}\n{:<w$}  {:<f$}  {}\n{:<w$}  {:<f$}  {}\n{:<w$}  {:<f$}  {}\n",
+            "word",
+            before,
+            after,
+            "error",
+            "#1 3",
+            "#2 1",
+            "disk",
+            "#2 2",
+            "-",
+            "timeout",
+            "-",
+            "#1 2",
+            w = "timeout".len(),
+            f = width,
+        );
+        assert_eq!(out, expected);
+
+        let (code, _, err) = run_cli(&[&before, "2", "--compare"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--compare needs exactly two input files, got 1"));
+    }
+
+    /// Test the entropy line of --entropy
+    #[test]
+    fn test_entropy_option() {
+        let file = temp_file("entropy.log", "error disk\ntimeout retry\n");
+
+        let (code, _, err) = run_cli(&[&file, "4", "--entropy"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(err, "entropy: 2.000 bits\n");
+    }
+
+    /// Test that --contains keeps only tokens with the substring
+    #[test]
+    fn test_contains_substring() {
+        let file = temp_file(
+            "contains.log",
+            "Error writing to STDERR\nwarning: error retry\nERRNO 5\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "5", "--contains", "ERR"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2), (\"errno\", 1), (\"stderr\", 1)]\n");
+    }
+
+    /// Test that a k of 0 with filters set reports nothing, with a note
+    /// that this is intended
+    #[test]
+    fn test_zero_k_with_filters() {
+        let file = temp_file("zero_k.log", "error disk error\n");
+
+        let (code, out, err) = run_cli(&[&file, "0", "--min-len", "4", "--trim"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[]\n");
+        assert_eq!(
+            err,
+            "note: k is 0, so no words are reported whatever --trim, --min-len keep; pass a larger k to see them\n"
+        );
+
+        // No filters, nothing to clarify
+        let (_, _, err) = run_cli(&[&file, "0"]);
+        assert_eq!(err, "");
+        // --count-distinct ignores k
+        let (_, out, err) = run_cli(&[&file, "0", "--count-distinct", "--min-len", "4"]);
+        assert_eq!((out.as_str(), err.as_str()), ("2\n", ""));
+    }
+
+    /// Test every --sort-by key in both directions
+    #[test]
+    fn test_sort_by_options() {
+        // Counts 4, 3, 2, 1; lengths 3, 5, 2, 4; first seen in reverse
+        let file = temp_file(
+            "sort_by.log",
+            "zeta bb alpha mid\nalpha mid bb\nalpha mid\nmid\n",
+        );
+        let order = |args: &[&str]| {
+            let mut full = vec![file.as_str(), "4", "--format", "lines"];
+            full.extend(args);
+            let (code, out, _) = run_cli(&full);
+            assert_eq!(code, 0);
+            out.lines()
+                .map(|line| line.split_once(' ').unwrap().1.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        assert_eq!(order(&["--sort-by", "count"]), "mid alpha bb zeta");
+        assert_eq!(
+            order(&["--sort-by", "count", "--sort-dir", "asc"]),
+            "zeta bb alpha mid"
+        );
+        assert_eq!(order(&["--sort-by", "alpha"]), "alpha bb mid zeta");
+        assert_eq!(
+            order(&["--sort-by", "alpha", "--sort-dir", "desc"]),
+            "zeta mid bb alpha"
+        );
+        assert_eq!(order(&["--sort-by", "length"]), "alpha zeta mid bb");
+        assert_eq!(
+            order(&["--sort-by", "length", "--sort-dir", "asc"]),
+            "bb mid zeta alpha"
+        );
+        assert_eq!(order(&["--sort-by", "firstseen"]), "zeta bb alpha mid");
+        assert_eq!(
+            order(&["--sort-by", "firstseen", "--sort-dir", "desc"]),
+            "mid alpha bb zeta"
+        );
+
+        // The top-K is still chosen by count
+        let (_, out, _) = run_cli(&[&file, "2", "--sort-by", "alpha"]);
+        assert_eq!(out, "[(\"alpha\", 3), (\"mid\", 4)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "2", "--sort-dir", "asc"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--sort-dir needs --sort-by"));
+    }
+
+    /// Test that --sort none reports words in first-appearance order
+    #[test]
+    fn test_sort_none_keeps_discovery_order() {
+        let file = temp_file(
+            "discovery.log",
+            "boot disk\nerror disk timeout\nerror error retry\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "4", "--sort", "none", "--stop-words", "disk"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[(\"boot\", 1), (\"error\", 3), (\"timeout\", 1), (\"retry\", 1)]\n"
+        );
+    }
+
+    /// Test that --since/--until count only lines inside a one-hour window
+    #[test]
+    fn test_time_range() {
+        let file = temp_file(
+            "time_range.log",
+            "2024-05-01T00:15:00Z boot\n\
+             2024-05-01T08:59:59Z warning queue\n\
+             2024-05-01T09:00:00Z error disk\n\
+             2024-05-01T09:30:00Z error timeout\n\
+             2024-05-01T11:45:00+02:00 error disk\n\
+             unstamped error\n\
+             2024-05-01T10:00:01Z warning late\n\
+             2024-05-01T23:50:00Z shutdown\n",
+        );
+
+        let (code, out, err) = run_cli(&[
+            &file,
+            "5",
+            "--since",
+            "2024-05-01T09:00:00Z",
+            "--until",
+            "2024-05-01T10:00:00Z",
+            "--strip-prefix",
+            r"\S+\s*",
+            "--explain",
+        ]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3), (\"disk\", 2), (\"timeout\", 1)]\n");
+        assert!(err.contains("explain: 5 lines skipped by --since/--until\n"));
+    }
+
+    /// Test that a malformed --since timestamp is rejected
+    #[test]
+    fn test_invalid_since() {
+        let (code, _, err) = run_cli(&["logs.txt", "5", "--since", "yesterday"]);
+
+        assert_eq!(code, 1);
+        assert!(err.contains("error: invalid RFC3339 timestamp 'yesterday'"));
+    }
+
+    /// Test severity-weighted output with floating-point scores
+    #[test]
+    fn test_severity_weights() {
+        let file = temp_file("severity.log", "ERROR kernel\nINFO retry\nINFO retry\n");
+
+        let (code, out, _) = run_cli(&[&file, "2", "--severity-weights", "ERROR=5,INFO=1"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 5.0), (\"kernel\", 5.0)]\n");
+    }
+
+    /// Test counting words of a JSON field and reporting skipped lines
+    #[test]
+    fn test_json_field() {
+        let file = temp_file(
+            "json_field.log",
+            "{\"message\":\"disk full\"}\n{\"message\":\"disk error\"}\nnot json\n",
+        );
+
+        let (code, out, err) = run_cli(&[&file, "1", "--json-field", "message"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 2)]\n");
+        assert!(err.contains("skipped 1 lines"));
+    }
+
+    /// Test the length histogram as a chart and as CSV rows
+    #[test]
+    fn test_length_histogram_option() {
+        let file = temp_file("length_histogram.log", "a an an disk\nerror disk\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--length-histogram"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            format!(
+                "1 | {0} 1\n2 | {0} 1\n4 | {0} 1\n5 | {0} 1\n",
+                "#".repeat(40)
+            )
+        );
+
+        let (code, out, _) = run_cli(&[
+            &file,
+            "1",
+            "--length-histogram",
+            "--histogram-tokens",
+            "--format",
+            "csv",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "length,tokens\n1,1\n2,2\n4,2\n5,1\n");
+    }
+
+    /// Test that --path-tree prints the top children of each node indented
+    #[test]
+    fn test_path_tree_option() {
+        let file = temp_file(
+            "path_tree.log",
+            "GET /api/users 200\nGET /api/users 500\nGET /api/orders 200\nGET /health 200\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "1", "--path-tree"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "/ 4\n  /api 3\n    /api/users 2\n");
+
+        let (code, _, err) = run_cli(&[&file, "1", "--path-tree", "--format", "csv"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--path-tree prints the debug or json format only"));
+    }
+
+    /// Test that --convergence reports the line the top-K settled on
+    #[test]
+    fn test_convergence_option() {
+        let lines = format!(
+            "error disk\nerror disk\nerror\n{}",
+            "error disk retry\n".repeat(10)
+        );
+        let file = temp_file("convergence.log", &lines);
+
+        let (code, out, _) = run_cli(&[&file, "2", "--convergence"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "3 of 13 lines\n");
+
+        let (code, _, err) = run_cli(&[&file, "2", "--convergence", "--also-json", &file]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--also-json cannot be combined with --convergence"));
+    }
+
+    /// Test that empty and whitespace-only lines are reported on stderr
+    #[test]
+    fn test_count_empty_lines_option() {
+        let file = temp_file("empty_lines.log", "error disk\n\n   \nerror\n\t\nretry\n");
+
+        let (code, out, err) = run_cli(&[&file, "1", "--count-empty-lines"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2)]\n");
+        assert!(err.contains("empty lines: 3 of 6 lines are empty or whitespace-only"));
+    }
+
+    /// Test that line length statistics are reported on stderr
+    #[test]
+    fn test_line_length_stats_option() {
+        let file = temp_file("line_lengths.log", "ok\nerror disk\nlonger line here\n");
+
+        let (code, _, err) = run_cli(&[&file, "1", "--line-length-stats", "--jobs", "2"]);
+
+        assert_eq!(code, 0);
+        assert!(err.contains(
+            "line lengths: 3 lines, length mean 9.33, median 10, p90 16, p99 16, max 16 characters"
+        ));
+    }
+
+    /// Test that a glob stop word removes a family of words
+    #[test]
+    fn test_stopword_glob_option() {
+        let file = temp_file(
+            "stopword_glob.log",
+            "request failed\nrequests error\nreq* literal\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "5", "--stop-words", "req*", "--stopword-glob"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 1), (\"failed\", 1), (\"literal\", 1)]\n");
+
+        // Without the flag the entry only matches the literal token "req*"
+        let (code, out, _) = run_cli(&[&file, "6", "--stop-words", "req*"]);
+        assert_eq!(code, 0);
+        assert!(out.contains("(\"requests\", 1)"));
+    }
+
+    /// Test counting state transitions between two logfmt keys
+    #[test]
+    fn test_edge_options() {
+        let file = temp_file(
+            "edges.log",
+            "state from=idle to=busy\nstate from=busy to=idle\nstate from=idle to=busy\nnoise\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "5", "--edge-from", "from", "--edge-to", "to"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"idle -> busy\", 2), (\"busy -> idle\", 1)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "5", "--edge-from", "from"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--edge-from and --edge-to must be given together"));
+    }
+
+    /// Test that consecutive repeats are reported by run length
+    #[test]
+    fn test_runs_option() {
+        let file = temp_file(
+            "runs.log",
+            "start\nretry\nretry\nretry\nretry\nretry\nok\nstart\nok\nok\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--runs"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"retry\", 5), (\"ok\", 2)]\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--runs", "--format", "json"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[{\"word\":\"retry\",\"run\":5}]\n");
+    }
+
+    /// Test word-cloud output with a custom weight range
+    #[test]
+    fn test_wordcloud_format() {
+        let file = temp_file(
+            "wordcloud.log",
+            "error error error disk\nerror retry disk\n",
+        );
+
+        let (code, out, _) =
+            run_cli(&[&file, "3", "--format", "wordcloud", "--cloud-range", "1-7"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[{\"text\":\"error\",\"weight\":7},{\"text\":\"disk\",\"weight\":3},{\"text\":\"retry\",\"weight\":1}]\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "3", "--cloud-range", "1-7"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--cloud-range needs --format wordcloud"));
+    }
+
+    /// Test that lines output read back with --weight-field 1 keeps the counts
+    #[test]
+    fn test_lines_output_chains() {
+        let file = temp_file("chain.log", "error error error disk\nerror retry disk\n");
+
+        let (code, out, _) = run_cli(&[&file, "3", "--format", "lines"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "4 error\n2 disk\n1 retry\n");
+
+        let chained = temp_file("chained.log", &out);
+        let (code, out, _) = run_cli(&[&chained, "2", "--weight-field", "1"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 4), (\"disk\", 2)]\n");
+    }
+
+    /// Test the capped line numbers added by --include-positions
+    #[test]
+    fn test_include_positions_option() {
+        let file = temp_file(
+            "include_positions.log",
+            "error disk\nok\nerror error\ndisk\nerror\nerror again\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--format", "json", "--include-positions", "3"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[{\"word\":\"error\",\"count\":5,\"lines\":[1,3,5]},\
+             {\"word\":\"disk\",\"count\":2,\"lines\":[1,4]}]\n"
+        );
+
+        // A lone number after the flag is still k
+        let (code, out, _) = run_cli(&[&file, "--format", "json", "--include-positions", "1"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[{\"word\":\"error\",\"count\":5,\"lines\":[1,3,5,6]}]\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "2", "--include-positions"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--include-positions needs --format json"));
+    }
+
+    /// Test that --parallel and --jobs list the same line numbers as a
+    /// sequential run when positions span several chunks
+    #[test]
+    fn test_include_positions_parallel_matches_sequential() {
+        let lines: Vec<String> = (1..=10_000)
+            .map(|number| {
+                if number % 1500 == 0 {
+                    "marker ok\n".to_string()
+                } else {
+                    "info ok\n".to_string()
+                }
+            })
+            .collect();
+        let file = temp_file("include_positions_parallel.log", &lines.concat());
+        let args = [&file, "3", "--format", "json", "--include-positions", "5"];
+
+        let sequential = run_cli(&args);
+        assert_eq!(sequential.0, 0);
+        assert!(
+            sequential
+                .1
+                .contains("{\"word\":\"marker\",\"count\":6,\"lines\":[1500,3000,4500,6000,7500]}")
+        );
+        let mut parallel_args = args.to_vec();
+        parallel_args.push("--parallel");
+        assert_eq!(run_cli(&parallel_args), sequential);
+        let mut jobs_args = args.to_vec();
+        jobs_args.extend(["--jobs", "4"]);
+        assert_eq!(run_cli(&jobs_args), sequential);
+    }
+
+    /// Test the first and last timestamps added by --time-span
+    #[test]
+    fn test_time_span_option() {
+        let file = temp_file(
+            "time_span.log",
+            "2024-01-01 10:00:00 error failed\n\
+             2024-01-01 10:10:00 error retry\n\
+             unstamped error\n\
+             2024-01-01 09:55:00+00:00 error\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "1", "--time-span", "--min-len", "5"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[(\"error\", 4, \"2024-01-01T09:55:00Z\", \"2024-01-01T10:10:00Z\")]\n"
+        );
+
+        let (code, out, _) = run_cli(&[
+            &file,
+            "2",
+            "--time-span",
+            "--format",
+            "json",
+            "--min-len",
+            "5",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[{\"word\":\"error\",\"count\":4,\"first\":\"2024-01-01T09:55:00Z\",\"last\":\"2024-01-01T10:10:00Z\"},\
+             {\"word\":\"failed\",\"count\":1,\"first\":\"2024-01-01T10:00:00Z\",\"last\":\"2024-01-01T10:00:00Z\"}]\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "2", "--time-span", "--format", "csv"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--time-span needs --format debug or json"));
+    }
+
+    /// Test that --jobs gives the same report and statistics as a sequential run
+    #[test]
+    fn test_jobs_match_sequential() {
+        let files: Vec<String> = (0..6)
+            .map(|i| {
+                let lines: Vec<String> = (0..200)
+                    .map(|line| format!("error code{} host{} retry{}\n", line % 17, i, line % 5))
+                    .collect();
+                temp_file(&format!("jobs_{}.log", i), &lines.concat())
+            })
+            .collect();
+        let mut args: Vec<&str> = files.iter().map(String::as_str).collect();
+        args.extend(["25", "--min-len", "5", "--explain"]);
+
+        let sequential = run_cli(&args);
+        for jobs in ["1", "3", "8"] {
+            let mut parallel_args = args.clone();
+            parallel_args.extend(["--jobs", jobs]);
+            assert_eq!(
+                run_cli(&parallel_args),
+                sequential,
+                "--jobs {} differs",
+                jobs
+            );
+
+            parallel_args.push("--per-file");
+            let mut per_file_args = args.clone();
+            per_file_args.push("--per-file");
+            assert_eq!(run_cli(&parallel_args), run_cli(&per_file_args));
+        }
+        assert_eq!(sequential.0, 0);
+
+        // A unique-word limit keeps the words of a single pass, even when
+        // the merged input spans several parallel chunks
+        let large = temp_file(
+            "jobs_max_unique.log",
+            &format!("{}b a\n", "a\n".repeat(4096)),
+        );
+        let mut args = vec![large.as_str()];
+        args.extend(files.iter().map(String::as_str));
+        args.extend(["5", "--max-unique", "3", "--explain"]);
+        let sequential = run_cli(&args);
+        assert!(sequential.1.contains("(\"a\", 4097)"));
+        for jobs in ["1", "3", "8"] {
+            let mut parallel_args = args.clone();
+            parallel_args.extend(["--jobs", jobs]);
+            assert_eq!(
+                run_cli(&parallel_args),
+                sequential,
+                "--jobs {} --max-unique differs",
+                jobs
+            );
+        }
+    }
+
+    /// Test that words of the reference file are left out of the results
+    #[test]
+    fn test_new_against_reference() {
+        let reference = temp_file("new_against_reference.txt", "error\nDisk retry\n");
+        let file = temp_file(
+            "new_against.log",
+            "error disk full\nerror retry\nsegfault in worker\nsegfault again\n",
+        );
+
+        let (code, out, err) = run_cli(&[&file, "3", "--new-against", &reference, "--explain"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"segfault\", 2), (\"again\", 1), (\"full\", 1)]\n");
+        assert!(err.contains("--new-against"));
+    }
+
+    /// Test merging precounted dumps and warning about malformed lines
+    #[test]
+    fn test_input_counts_option() {
+        let first = temp_file("counts_a.txt", "error 10\ndisk 4\n");
+        let second = temp_file("counts_b.txt", "disk 9\nerror two\ntimeout 1\n");
+
+        let (code, out, err) = run_cli(&[&first, &second, "2", "--input-counts"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 13), (\"error\", 10)]\n");
+        assert!(err.contains("warning: skipped 1 malformed lines"));
+    }
+
+    /// Test that --keep-apostrophes counts contractions as single words
+    #[test]
+    fn test_keep_apostrophes_option() {
+        let file = temp_file(
+            "keep_apostrophes.log",
+            "don't retry, it's 'down'\nit's down, don't\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "3", "--keep-apostrophes"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"don't\", 2), (\"down\", 2), (\"it's\", 2)]\n");
+
+        // The built-in splitting counts "don", "t", "it" and "s" instead
+        let (_, out, _) = run_cli(&[&file, "6"]);
+        assert_eq!(
+            out,
+            "[(\"don\", 2), (\"down\", 2), (\"it\", 2), (\"s\", 2), (\"t\", 2), (\"retry\", 1)]\n"
+        );
+    }
+
+    /// Test that --keep-units counts durations whole, without a bare unit
+    #[test]
+    fn test_keep_units_option() {
+        let file = temp_file("keep_units.log", "took 12ms\ntook 200ms\ntook 12MS\n");
+
+        let (code, out, _) = run_cli(&[&file, "5", "--keep-units"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"took\", 3), (\"12ms\", 2), (\"200ms\", 1)]\n");
+        assert!(!out.contains("(\"ms\""));
+
+        let (code, _, err) = run_cli(&[&file, "5", "--keep-units", "--keep-apostrophes"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--keep-apostrophes cannot be combined with --keep-units"));
+
+        let (code, _, err) = run_cli(&[&file, "5", "--keep-units", "--sort", "none"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--sort none cannot be combined with --keep-units"));
+    }
+
+    /// Test that --whitespace-tokens keeps internal punctuation
+    #[test]
+    fn test_whitespace_tokens_option() {
+        let file = temp_file(
+            "whitespace_tokens.log",
+            "GET /api/v1, ok\nGET /api/v1 failed: config.yaml\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "3", "--whitespace-tokens"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "[(\"/api/v1\", 2), (\"get\", 2), (\"config.yaml\", 1)]\n"
+        );
+    }
+
+    /// Test that --byte-start and --byte-end count the lines beginning in
+    /// a middle byte range
+    #[test]
+    fn test_byte_range_options() {
+        // Lines begin at bytes 0, 6, 12, 20 and 26
+        let file = temp_file("byte_range.log", "alpha\nbravo\ncharlie\ndelta\necho\n");
+
+        let (code, out, _) = run_cli(&[&file, "5", "--byte-start", "8", "--byte-end", "21"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"charlie\", 1), (\"delta\", 1)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "5", "--byte-start", "8", "--byte-end", "8"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--byte-end must be greater than --byte-start"));
+    }
+
+    /// Test that the streamed count under --max-lines-per-sec matches the
+    /// loaded one
+    #[test]
+    fn test_max_lines_per_sec_streams() {
+        let file = temp_file(
+            "max_lines_per_sec.log",
+            "boot\n--- start\n12ms error disk\n12ms error\n  warn disk \n--- stop\nerror\n",
+        );
+        let options = [
+            "--start-after",
+            "^--- start",
+            "--stop-at",
+            "^--- stop",
+            "--stop-words",
+            "warn",
+            "--trim",
+            "--explain",
+        ];
+        let run = |extra: &[&str]| {
+            let args: Vec<&str> = [file.as_str(), "3"]
+                .iter()
+                .chain(&options)
+                .chain(extra)
+                .copied()
+                .collect();
+            run_cli(&args)
+        };
+
+        let loaded = run(&[]);
+        let streamed = run(&["--max-lines-per-sec", "1000"]);
+        assert_eq!(loaded.0, 0);
+        assert_eq!(loaded.1, "[(\"12ms\", 2), (\"disk\", 2), (\"error\", 2)]\n");
+        assert_eq!(streamed, loaded);
+        assert_eq!(
+            run(&["--max-lines-per-sec", "1000", "--keep-units"]),
+            run(&["--keep-units"])
+        );
+    }
+
+    /// Test that --idle-timeout reports a FIFO whose writer stays open but quiet
+    #[cfg(unix)]
+    #[test]
+    fn test_idle_timeout_option() {
+        let fifo = std::env::temp_dir().join(format!("lwa_{}_idle.fifo", std::process::id()));
+        let _ = std::fs::remove_file(&fifo);
+        let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+        if !made.is_ok_and(|status| status.success()) {
+            return;
+        }
+        let writer_path = fifo.clone();
+        std::thread::spawn(move || {
+            let mut writer = std::fs::OpenOptions::new()
+                .write(true)
+                .open(writer_path)
+                .unwrap();
+            writer.write_all(b"error disk\nerror full\n").unwrap();
+            // Hold the FIFO open without sending EOF
+            std::thread::sleep(Duration::from_secs(30));
+        });
+        let start = std::time::Instant::now();
+
+        let path = fifo.to_string_lossy();
+        let (code, out, _) = run_cli(&[&path, "1", "--idle-timeout", "0.3"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2)]\n");
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(300),
+            "ended after {:?}",
+            elapsed
+        );
+        assert!(
+            elapsed < Duration::from_secs(10),
+            "ended after {:?}",
+            elapsed
+        );
+        let _ = std::fs::remove_file(&fifo);
+
+        let (code, _, err) = run_cli(&[&path, "1", "--idle-timeout", "0"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("positive number of seconds"));
+    }
+
+    /// Test the note about words tying with the last one at the K boundary
+    #[test]
+    fn test_boundary_tie_note() {
+        let file = temp_file("boundary_tie.log", "error error disk disk full full gone\n");
+
+        let (code, out, err) = run_cli(&[&file, "2"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 2), (\"error\", 2)]\n");
+        assert!(err.contains(
+            "note: 1 more words tie with the last shown word at count 2; a k of 3 shows them all"
+        ));
+
+        // No note when the cut falls between different counts
+        let (code, _, err) = run_cli(&[&file, "3"]);
+        assert_eq!(code, 0);
+        assert!(!err.contains("tie with"));
+    }
+
+    /// Test that --field-separator counts whole CSV fields
+    #[test]
+    fn test_field_separator_option() {
+        let file = temp_file(
+            "field_separator.csv",
+            "GET,/api/v1,200,Disk Full\nGET, /api/v1 ,500,\nPOST,/login,200,disk full\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--field-separator", ",", "--trim-fields"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"/api/v1\", 2), (\"200\", 2)]\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--field-separator", ",", "--min-len", "5"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk full\", 2)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "1", "--field-separator", ",;"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("single character"));
+    }
+
+    /// Test that --shape counts letter/digit patterns, optionally by case
+    #[test]
+    fn test_shape_option() {
+        let file = temp_file("shape.log", "error404 fatal500 12ab\nDisk 99zz\n");
+
+        let (code, out, _) = run_cli(&[&file, "2", "--shape"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"00aa\", 2), (\"aaaaa000\", 2)]\n");
+
+        let (code, out, _) = run_cli(&[&file, "5", "--shape", "--shape-case", "--min-len", "5"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"aaaaa000\", 2)]\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--shape", "--shape-case"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"00aa\", 2)]\n");
+    }
+
+    /// Test that --logfmt-key counts whole values of one key
+    #[test]
+    fn test_logfmt_key_option() {
+        let file = temp_file(
+            "logfmt_key.log",
+            "level=error msg=\"disk full\"\nlevel=info msg=ok\nlevel=error msg=\"disk full\"\nplain line\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "3", "--logfmt-key", "msg"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk full\", 2), (\"ok\", 1)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "3", "--logfmt-key", "msg", "--tfidf"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--tfidf cannot be combined with --logfmt-key"));
+    }
+
+    /// Test that --capture counts only the selected group
+    #[test]
+    fn test_capture_option() {
+        let file = temp_file(
+            "capture.log",
+            "GET /api/users 200\nGET /api/orders 500\nstartup complete\n",
+        );
+
+        let (code, out, err) = run_cli(&[
+            &file,
+            "2",
+            "--capture",
+            r"(GET|POST) (\S+)",
+            "--capture-group",
+            "2",
+            "--explain",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"api\", 2), (\"orders\", 1)]\n");
+        assert!(err.contains("--capture"));
+
+        let (code, _, err) = run_cli(&[
+            &file,
+            "2",
+            "--capture",
+            r"GET (\S+)",
+            "--capture-group",
+            "2",
+        ]);
+        assert_eq!(code, 1);
+        assert!(err.contains("has no capture group 2"));
+    }
+
+    /// Test that --strip-prefix keeps timestamps out of the counts
+    #[test]
+    fn test_strip_prefix_option() {
+        let file = temp_file(
+            "strip_prefix.log",
+            "  12:00:01 error disk\n12:00:02 error\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "5", "--trim", "--strip-prefix", r"\d+:\d+:\d+"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 2), (\"disk\", 1)]\n");
+    }
+
+    /// Test that --explain reports the tokens dropped by the stop-word filter
+    #[test]
+    fn test_explain_stop_words() {
+        let file = temp_file(
+            "explain.log",
+            "the disk is full\nthe network is down\nBEGIN\nthe end\n",
+        );
+
+        let (code, out, err) = run_cli(&[
+            &file,
+            "2",
+            "--stop-words",
+            "the,is",
+            "--stop-at",
+            "BEGIN",
+            "--explain",
+        ]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 1), (\"down\", 1)]\n");
+        assert_eq!(
+            err,
+            "note: 2 more words tie with the last shown word at count 1; a k of 4 shows them all\n\
+             explain: 2 lines skipped by --start-after/--stop-at\n\
+             explain: 4 tokens dropped by --stop-words\n\
+             explain: 4 tokens counted\n"
+        );
+    }
+
+    /// Test --tfidf ranks words distinctive to each file first
+    #[test]
+    fn test_tfidf_option() {
+        let a = temp_file("tfidf_a.log", "error timeout\nerror timeout\n");
+        let b = temp_file("tfidf_b.log", "error disk\n");
+
+        let (code, out, _) = run_cli(&[&a, &b, "1", "--tfidf", "--format", "csv"]);
+
+        assert_eq!(code, 0);
+        let score = 0.5 * 2f64.ln();
+        assert_eq!(
+            out,
+            format!(
+                "file,word,score\n{0},timeout,{2}\n{1},disk,{2}\n",
+                a, b, score
+            )
+        );
+    }
+
+    /// Test that --max-tokens-per-line ignores the tail of long lines
+    #[test]
+    fn test_max_tokens_per_line_option() {
+        let file = temp_file(
+            "max_tokens.log",
+            "error disk disk disk disk disk\ndisk error\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--max-tokens-per-line", "2"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 2), (\"error\", 2)]\n");
+    }
+
+    /// Test that --max-unique warns when new words are turned away
+    #[test]
+    fn test_max_unique_warning() {
+        let file = temp_file("max_unique.log", "error disk\nerror full\nerror down\n");
+
+        let (code, out, err) = run_cli(&[&file, "5", "--max-unique", "2"]);
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3), (\"disk\", 1)]\n");
+        assert!(err.contains("reached --max-unique limit of 2 distinct words; 2 tokens"));
+    }
+
+    /// Test that --parallel keeps the words a single pass keeps under
+    /// --max-unique when the input spans several parallel chunks
+    #[test]
+    fn test_max_unique_parallel_matches_sequential() {
+        let file = temp_file(
+            "max_unique_parallel.log",
+            &format!("{}b a\n", "a\n".repeat(4096)),
+        );
+        let args = [&file, "5", "--max-unique", "1", "--explain"];
+
+        let sequential = run_cli(&args);
+        assert_eq!(sequential.1, "[(\"a\", 4097)]\n");
+        assert!(sequential.2.contains("1 tokens dropped by --max-unique"));
+        let mut parallel_args = args.to_vec();
+        parallel_args.push("--parallel");
+        assert_eq!(run_cli(&parallel_args), sequential);
+    }
+
+    /// Test --numeric-bucket with and without keeping words
+    #[test]
+    fn test_numeric_bucket_option() {
+        let file = temp_file("buckets.log", "GET 200 12\nGET 201 7\nGET 404 1500\n");
+
+        let (code, out, _) = run_cli(&[&file, "3", "--numeric-bucket", "magnitude"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"100-999\", 3), (\"0-9\", 1), (\"10-99\", 1)]\n");
+
+        let (code, out, _) = run_cli(&[
+            &file,
+            "2",
+            "--numeric-bucket",
+            "200-299",
+            "--bucket-keep-words",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"get\", 3), (\"200-299\", 2)]\n");
+    }
+
+    /// Test that --record-frequency counts a word once per multi-line record
+    #[test]
+    fn test_record_frequency_option() {
+        let file = temp_file(
+            "record_frequency.log",
+            "error: disk\nerror again\nerror\nerror\0timeout\ndisk\0",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--record-separator", "\\0"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 4), (\"disk\", 2)]\n");
+
+        let (code, out, err) = run_cli(&[
+            &file,
+            "2",
+            "--record-separator",
+            "\\0",
+            "--record-frequency",
+            "--explain",
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"disk\", 2), (\"again\", 1)]\n");
+        assert!(err.contains("explain: 3 tokens dropped by --record-frequency"));
+    }
+
+    /// Test that --prune-threshold keeps the top K and warns about the error bound
+    #[test]
+    fn test_prune_threshold_option() {
+        let mut contents = String::new();
+        for i in 0..3000 {
+            contents.push_str(&format!("error id{}\n", i));
+        }
+        let file = temp_file("prune.log", &contents);
+
+        let (code, out, err) = run_cli(&[&file, "1", "--prune-threshold", "2"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"error\", 3000)]\n");
+        assert!(err.contains("warning: --prune-threshold dropped"));
+        assert!(err.contains("counts may be low by up to"));
+
+        let (code, _, err) = run_cli(&[&file, "1", "--prune-threshold", "1"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("at least 2"));
+    }
+
+    /// Test --fuzzy-merge with the default distance and a trailing distance
+    #[test]
+    fn test_fuzzy_merge_option() {
+        let file = temp_file(
+            "fuzzy.log",
+            "connection reset\nconnection lost\nconection reset\nconnectoin lost\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "1", "--fuzzy-merge"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"connection\", 3)]\n");
+
+        // A number after the flag is the distance once k is present
+        let (code, out, _) = run_cli(&[&file, "2", "--fuzzy-merge", "2"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"connection\", 4), (\"lost\", 2)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "2", "--fuzzy-merge", "--approx-distinct"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--approx-distinct cannot be combined with --fuzzy-merge"));
+    }
+
+    /// Test that --normalize-hex collapses addresses, with a configurable length
+    #[test]
+    fn test_normalize_hex_option() {
+        let file = temp_file(
+            "normalize_hex.log",
+            "free 0x55d0c3a2 ok\nfree 0x55d0c3b8 ok\nhash 3fa9c01b e7\n",
+        );
+
+        let (code, out, _) = run_cli(&[&file, "2", "--normalize-hex"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"hex\", 3), (\"free\", 2)]\n");
+
+        // A shorter threshold also takes the two-digit token
+        let (code, out, _) = run_cli(&[&file, "1", "--normalize-hex", "--hex-min-len", "2"]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "[(\"hex\", 4)]\n");
+
+        let (code, _, err) = run_cli(&[&file, "1", "--hex-min-len", "4"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--hex-min-len needs --normalize-hex"));
+    }
+
+    /// Test that one run prints a markdown table and writes the same rows
+    /// as JSON with --also-json
+    #[test]
+    fn test_also_json_option() {
+        let file = temp_file("also_json.log", "error disk error\nretry error\n");
+        let json = temp_file("also_json.json", "");
+
+        let (code, out, _) = run_cli(&[&file, "2", "--format", "markdown", "--also-json", &json]);
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "| Rank | Word | Count |\n|---:|---|---:|\n| 1 | error | 3 |\n| 2 | disk | 1 |\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&json).unwrap(),
+            "[{\"word\":\"error\",\"count\":3},{\"word\":\"disk\",\"count\":1}]\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "2", "--count-distinct", "--also-json", &json]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--also-json cannot be combined with --count-distinct"));
+    }
+
+    /// Test the versioned --json-envelope wrapper
+    #[test]
+    fn test_json_envelope_option() {
+        let file = temp_file("json_envelope.log", "error disk error\n");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--format", "json", "--json-envelope"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "{\"schema_version\":1,\"results\":[{\"word\":\"error\",\"count\":2}]}\n"
+        );
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["results"][0]["word"], "error");
+
+        let (code, _, err) = run_cli(&[&file, "1", "--json-envelope"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--json-envelope needs --format json"));
+    }
+
+    /// Test --output writing the report to a file and the parquet checks
+    #[test]
+    fn test_output_option() {
+        let file = temp_file("output.log", "error disk error\n");
+        let report = temp_file("output.csv", "");
+
+        let (code, out, _) = run_cli(&[&file, "1", "--format", "csv", "--output", &report]);
+        assert_eq!(code, 0);
+        assert_eq!(out, "");
+        assert_eq!(
+            std::fs::read_to_string(&report).unwrap(),
+            "word,count\nerror,2\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "1", "--format", "parquet"]);
+        assert_eq!(code, 1);
+        if cfg!(feature = "parquet") {
+            assert!(err.contains("--format parquet needs --output"));
+            let (code, _, err) = run_cli(&[
+                &file,
+                "1",
+                "--format",
+                "parquet",
+                "--output",
+                &report,
+                "--per-file",
+            ]);
+            assert_eq!(code, 1);
+            assert!(err.contains("--per-file cannot be combined with --format parquet"));
+        } else {
+            assert!(err.contains("`parquet` feature"));
+        }
+    }
+
+    /// Test --group-by-initial sections in debug and CSV output
+    #[test]
+    fn test_group_by_initial_option() {
+        let file = temp_file("initial.log", "error disk error\n404 dns\n");
+
+        let (code, out, _) = run_cli(&[&file, "4", "--group-by-initial"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "==> # <==\n[(\"404\", 1)]\n\n==> D <==\n[(\"disk\", 1), (\"dns\", 1)]\n\n==> E <==\n[(\"error\", 2)]\n"
+        );
+
+        let (_, out, _) = run_cli(&[&file, "1", "--group-by-initial", "--format", "csv"]);
+        assert_eq!(out, "initial,word,count\nE,error,2\n");
+    }
+
+    /// Test that --group-by-named gives one breakdown per named capture
+    /// from a single read of the input
+    #[test]
+    fn test_group_by_named_option() {
+        let file = temp_file(
+            "named_groups.log",
+            "ERROR db: timeout\nERROR api: timeout timeout\nINFO db: connected\n",
+        );
+        let pattern = "^(?P<level>[A-Z]+) (?P<service>[a-z]+):";
+
+        let (code, out, _) =
+            run_cli(&[&file, "1", "--group-by-named", pattern, "--format", "json"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "{\"level\":{\"ERROR\":[{\"word\":\"timeout\",\"count\":3}],\"INFO\":[{\"word\":\"connected\",\"count\":1}]},\"service\":{\"db\":[{\"word\":\"db\",\"count\":2}],\"api\":[{\"word\":\"timeout\",\"count\":2}]}}\n"
+        );
+
+        let (_, out, _) = run_cli(&[&file, "1", "--group-by-named", pattern, "--format", "csv"]);
+        assert_eq!(
+            out,
+            "group,word,count\nlevel=ERROR,timeout,3\nlevel=INFO,connected,1\nservice=db,db,2\nservice=api,timeout,2\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "1", "--group-by-named", "([A-Z]+)"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--group-by-named needs a regex with named groups"));
+    }
+
+    /// Test that --baseline ranks a word spiking against the reference first
+    #[test]
+    fn test_baseline_option() {
+        let mut reference = String::new();
+        let mut current = String::new();
+        for i in 0..40 {
+            reference.push_str(if i % 10 == 0 {
+                "GET /api timeout\n"
+            } else {
+                "GET /api ok\n"
+            });
+            current.push_str(if i % 2 == 0 {
+                "GET /api timeout\n"
+            } else {
+                "GET /api ok\n"
+            });
+        }
+        let reference = temp_file("baseline_reference.log", &reference);
+        let file = temp_file("baseline_current.log", &current);
+
+        let (code, out, _) = run_cli(&[&file, "1", "--baseline", &reference, "--format", "csv"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("word,score\ntimeout,"), "{}", out);
+
+        let (code, _, err) = run_cli(&[&file, "1", "--baseline", &reference, "--tfidf"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--tfidf cannot be combined with --baseline"));
+    }
+
+    /// Test --tiers sections and that words land in their highest tier
+    #[test]
+    fn test_tiers_option() {
+        let mut contents = "error ".repeat(12);
+        contents.push_str(&"disk ".repeat(10));
+        contents.push_str("timeout timeout dns\n");
+        let file = temp_file("tiers.log", &contents);
+
+        let (code, out, _) = run_cli(&[&file, "4", "--tiers", "1,10,100"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "==> >= 10 <==\n[(\"error\", 12), (\"disk\", 10)]\n\n==> >= 1 <==\n[(\"timeout\", 2), (\"dns\", 1)]\n"
+        );
+
+        let (_, out, _) = run_cli(&[&file, "3", "--tiers", "10,2", "--format", "csv"]);
+        assert_eq!(
+            out,
+            "tier,word,count\n>= 10,error,12\n>= 10,disk,10\n>= 2,timeout,2\n"
+        );
+
+        let (code, _, err) = run_cli(&[&file, "3", "--tiers", "10,x"]);
+        assert_eq!(code, 1);
+        assert!(err.contains("--tiers must be a positive number, got 'x'"));
+    }
+}
@@ -0,0 +1,98 @@
+//! Side-by-side comparison of the top-K words of two inputs, such as a
+//! log from before and after a deploy.
+
+use std::collections::HashMap;
+
+/// One word of a comparison with its rank and count on either side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareRow {
+    /// The compared word
+    pub word: String,
+    /// `(rank, count)` in the first ranking, ranks starting at 1, or `None`
+    /// when the word is not among its top K
+    pub first: Option<(usize, usize)>,
+    /// `(rank, count)` in the second ranking
+    pub second: Option<(usize, usize)>,
+}
+
+/// Merges two rankings into one row per word.
+///
+/// Rows follow the first ranking, and words only the second ranking holds
+/// come after them in its order, so new words stand out at the bottom.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::compare::compare_rankings;
+///
+/// let before = vec![("error".to_string(), 3), ("disk".to_string(), 1)];
+/// let after = vec![("timeout".to_string(), 4), ("error".to_string(), 2)];
+/// let rows = compare_rankings(&before, &after);
+///
+/// assert_eq!(rows[0].word, "error");
+/// assert_eq!((rows[0].first, rows[0].second), (Some((1, 3)), Some((2, 2))));
+/// assert_eq!((rows[2].word.as_str(), rows[2].first), ("timeout", None));
+/// ```
+pub fn compare_rankings(first: &[(String, usize)], second: &[(String, usize)]) -> Vec<CompareRow> {
+    let ranks = |ranking: &[(String, usize)]| -> HashMap<String, (usize, usize)> {
+        ranking
+            .iter()
+            .enumerate()
+            .map(|(index, (word, count))| (word.clone(), (index + 1, *count)))
+            .collect()
+    };
+    let first_ranks = ranks(first);
+    let second_ranks = ranks(second);
+
+    let mut rows: Vec<CompareRow> = first
+        .iter()
+        .map(|(word, _)| CompareRow {
+            word: word.clone(),
+            first: first_ranks.get(word).copied(),
+            second: second_ranks.get(word).copied(),
+        })
+        .collect();
+    rows.extend(
+        second
+            .iter()
+            .filter(|(word, _)| !first_ranks.contains_key(word))
+            .map(|(word, _)| CompareRow {
+                word: word.clone(),
+                first: None,
+                second: second_ranks.get(word).copied(),
+            }),
+    );
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that words of both rankings appear once with both sides
+    #[test]
+    fn test_compare_rankings_union() {
+        let first = vec![
+            ("error".to_string(), 5),
+            ("disk".to_string(), 3),
+            ("retry".to_string(), 1),
+        ];
+        let second = vec![("disk".to_string(), 6), ("network".to_string(), 2)];
+
+        let rows = compare_rankings(&first, &second);
+
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|row| (row.word.as_str(), row.first, row.second))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("error", Some((1, 5)), None),
+                ("disk", Some((2, 3)), Some((1, 6))),
+                ("retry", Some((3, 1)), None),
+                ("network", None, Some((2, 2))),
+            ]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! The point at which a streamed top-K stops changing, to tell how much
+//! of a log is needed for a representative answer.
+
+use std::collections::HashMap;
+
+use crate::for_each_word_capped;
+
+/// Finds the line after which the top-K words, and their order, never
+/// changed again.
+///
+/// The top-K is followed line by line as the counts grow. Lines are
+/// numbered from 1; 0 means no line ever produced a word.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::convergence::convergence_line;
+///
+/// let logs: Vec<String> = ["disk", "error", "error", "error disk", "error"]
+///     .map(String::from)
+///     .to_vec();
+/// // From line 3 on, "error" leads "disk"
+/// assert_eq!(convergence_line(&logs, 2), 3);
+/// ```
+pub fn convergence_line(logs: &[String], k: usize) -> usize {
+    convergence_by_key(logs, k, None, |word| Some(word.to_string()))
+}
+
+/// Like [`convergence_line`], but a token counts as the word `key` maps
+/// it to, and only the first `limit` tokens of each line are read.
+///
+/// The top-K is only ranked again when a line touches a word that is in
+/// it or could displace its last word, so quiet stretches of the log cost
+/// little more than counting.
+pub(crate) fn convergence_by_key(
+    logs: &[String],
+    k: usize,
+    limit: Option<usize>,
+    key: impl Fn(&str) -> Option<String>,
+) -> usize {
+    if k == 0 {
+        return 0;
+    }
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut top: Vec<(String, usize)> = Vec::new();
+    let mut last_change = 0;
+
+    for (index, line) in logs.iter().enumerate() {
+        let mut touched = Vec::new();
+        for_each_word_capped(line, limit, |token| {
+            if let Some(word) = key(token) {
+                *counts.entry(word.clone()).or_insert(0) += 1;
+                touched.push(word);
+            }
+        });
+
+        let cut = top.last().map_or(0, |(_, count)| *count);
+        let may_change = touched.iter().any(|word| {
+            top.len() < k || counts[word] >= cut || top.iter().any(|(shown, _)| shown == word)
+        });
+        if !may_change {
+            continue;
+        }
+        let ranked = rank(&counts, k);
+        let reordered = ranked.len() != top.len()
+            || ranked
+                .iter()
+                .zip(&top)
+                .any(|((word, _), (shown, _))| word != shown);
+        if reordered {
+            last_change = index + 1;
+        }
+        top = ranked;
+    }
+    last_change
+}
+
+/// The K most frequent words by count (descending) and alphabetically for
+/// ties, without consuming the counts.
+fn rank(counts: &HashMap<String, usize>, k: usize) -> Vec<(String, usize)> {
+    let order =
+        |a: &(&String, usize), b: &(&String, usize)| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0));
+    let mut entries: Vec<(&String, usize)> =
+        counts.iter().map(|(word, &count)| (word, count)).collect();
+    if entries.len() > k {
+        entries.select_nth_unstable_by(k - 1, order);
+        entries.truncate(k);
+    }
+    entries.sort_by(order);
+    entries
+        .into_iter()
+        .map(|(word, count)| (word.clone(), count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a top-K settling early reports the line it settled on,
+    /// however long the log goes on
+    #[test]
+    fn test_early_convergence() {
+        let mut logs = vec![
+            "error disk".to_string(),
+            "error disk".to_string(),
+            // "error" pulls ahead of "disk" here and stays there
+            "error".to_string(),
+        ];
+        logs.extend((0..10).map(|_| "error disk retry".to_string()));
+
+        assert_eq!(convergence_line(&logs, 2), 3);
+        // "retry" enters the top 3 on line 4 and stays third
+        assert_eq!(convergence_line(&logs, 3), 4);
+    }
+
+    /// Test that a late newcomer overtaking the top-K moves the point
+    #[test]
+    fn test_late_change() {
+        let mut logs: Vec<String> = (0..5).map(|_| "error".to_string()).collect();
+        logs.extend((0..6).map(|_| "timeout".to_string()));
+
+        assert_eq!(convergence_line(&logs, 1), 11);
+        assert_eq!(convergence_line(&[], 1), 0);
+    }
+}
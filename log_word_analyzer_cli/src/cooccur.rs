@@ -0,0 +1,100 @@
+//! Counting of words that appear together on the same line.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::for_each_word;
+
+/// Finds the K word pairs that most often co-occur within a line.
+///
+/// Pairs are unordered and made of two distinct words, stored with the
+/// alphabetically smaller word first. Counting is document-frequency
+/// style: every line contributes at most one occurrence per pair, no
+/// matter how often either word repeats within it, so the count is the
+/// number of lines in which both words appear.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `k` - The number of top pairs to return
+///
+/// # Returns
+///
+/// A vector of `((first, second), lines)` tuples sorted by count
+/// (descending) and then by pair (ascending).
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::cooccur::top_k_cooccurrences;
+///
+/// let logs = vec!["connection refused".to_string(), "Connection refused again".to_string()];
+/// let result = top_k_cooccurrences(&logs, 1);
+/// assert_eq!(result, vec![(("connection".to_string(), "refused".to_string()), 2)]);
+/// ```
+pub fn top_k_cooccurrences(logs: &[String], k: usize) -> Vec<((String, String), usize)> {
+    let mut pair_counts: HashMap<(String, String), usize> = HashMap::new();
+
+    for line in logs {
+        // Distinct words of the line in sorted order, so each pair is (smaller, larger)
+        let mut words = BTreeSet::new();
+        for_each_word(line, |word| {
+            words.insert(word.to_string());
+        });
+
+        let words: Vec<String> = words.into_iter().collect();
+        for (index, first) in words.iter().enumerate() {
+            for second in &words[index + 1..] {
+                *pair_counts
+                    .entry((first.clone(), second.clone()))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<((String, String), usize)> = pair_counts.into_iter().collect();
+
+    // Sort by count descending, then by pair for a stable order among ties
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    pairs.truncate(k);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(first: &str, second: &str, count: usize) -> ((String, String), usize) {
+        ((first.to_string(), second.to_string()), count)
+    }
+
+    /// Test that connection+refused tops the pair ranking
+    #[test]
+    fn test_connection_refused_tops_ranking() {
+        let logs = vec![
+            "ERROR connection refused".to_string(),
+            "retrying after connection refused".to_string(),
+            "connection refused by host".to_string(),
+            "connection ok".to_string(),
+        ];
+
+        let result = top_k_cooccurrences(&logs, 2);
+
+        assert_eq!(
+            result,
+            vec![
+                pair("connection", "refused", 3),
+                pair("after", "connection", 1)
+            ]
+        );
+    }
+
+    /// Test that repeated words count a pair once per line and never pair with themselves
+    #[test]
+    fn test_pairs_counted_once_per_line() {
+        let logs = vec!["retry retry error retry".to_string(), "retry".to_string()];
+
+        let result = top_k_cooccurrences(&logs, 10);
+
+        assert_eq!(result, vec![pair("error", "retry", 1)]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Pre-aggregated input of `word count` pairs, such as frequency dumps of
+//! earlier runs, which are added up instead of tokenized.
+
+use std::collections::HashMap;
+
+use crate::filter::{DropReason, FilterStats, TokenFilter};
+use crate::select_top_k;
+
+/// Parses a `word count` line into its word and count.
+///
+/// The two fields may be separated by any whitespace. Lines with another
+/// number of fields or a count that is not a non-negative integer yield
+/// `None`.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::counts::parse_count_line;
+///
+/// assert_eq!(parse_count_line("error 42"), Some(("error", 42)));
+/// assert_eq!(parse_count_line("error\t7"), Some(("error", 7)));
+/// assert_eq!(parse_count_line("error many"), None);
+/// ```
+pub fn parse_count_line(line: &str) -> Option<(&str, usize)> {
+    let mut fields = line.split_whitespace();
+    let (Some(word), Some(count), None) = (fields.next(), fields.next(), fields.next()) else {
+        return None;
+    };
+    Some((word, count.parse().ok()?))
+}
+
+/// Finds the top K words of `word count` lines, summing the counts of
+/// words listed more than once.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::counts::top_k_precounted;
+///
+/// let dumps = vec!["error 3".to_string(), "disk 5".to_string(), "error 4".to_string()];
+/// assert_eq!(top_k_precounted(&dumps, 1), vec![("error".to_string(), 7)]);
+/// ```
+pub fn top_k_precounted(logs: &[String], k: usize) -> Vec<(String, usize)> {
+    let (counts, _, _) = precounted_counts(logs, &TokenFilter::default());
+    select_top_k(counts, k)
+}
+
+/// Adds up the counts of every `word count` line whose word the filter
+/// keeps. Blank lines are ignored.
+///
+/// # Returns
+///
+/// The summed counts, the filter statistics and the number of malformed
+/// lines that were skipped.
+pub(crate) fn precounted_counts(
+    logs: &[String],
+    filter: &TokenFilter,
+) -> (HashMap<String, usize>, FilterStats, usize) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut stats = FilterStats::for_filter(filter);
+    let mut skipped = 0;
+
+    for line in logs {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((word, count)) = parse_count_line(line) else {
+            skipped += 1;
+            continue;
+        };
+        if !filter.admit(word, &mut stats) {
+            continue;
+        }
+        if let Some(total) = counts.get_mut(word) {
+            *total = total.saturating_add(count);
+        } else if filter.max_unique.is_none_or(|limit| counts.len() < limit) {
+            counts.insert(word.to_string(), count);
+        } else {
+            stats.tokens_counted -= 1;
+            *stats
+                .tokens_dropped
+                .entry(DropReason::UniqueLimit)
+                .or_insert(0) += 1;
+        }
+    }
+
+    (counts, stats, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test merging count pairs into a top-K and skipping malformed lines
+    #[test]
+    fn test_precounted_top_k() {
+        let logs: Vec<String> = [
+            "error 10",
+            "disk 4",
+            "",
+            "timeout\t6",
+            "error 5",
+            "disk four",
+            "retry 1 extra",
+            "disk 3",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        let (counts, _, skipped) = precounted_counts(&logs, &TokenFilter::default());
+
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            select_top_k(counts, 3),
+            vec![
+                ("error".to_string(), 15),
+                ("disk".to_string(), 7),
+                ("timeout".to_string(), 6),
+            ]
+        );
+    }
+}
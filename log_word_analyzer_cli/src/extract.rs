@@ -0,0 +1,178 @@
+//! Extraction of the text to count from structured log lines.
+
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A dotted path to a string field inside JSON log lines, e.g. `error.message`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonField {
+    path: Vec<String>,
+}
+
+impl FromStr for JsonField {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let path: Vec<String> = spec.split('.').map(str::to_string).collect();
+        if path.iter().any(String::is_empty) {
+            return Err(format!("invalid JSON field path '{}'", spec));
+        }
+        Ok(JsonField { path })
+    }
+}
+
+impl JsonField {
+    /// Parses one line as JSON and returns the field's string value.
+    ///
+    /// # Returns
+    ///
+    /// `None` when the line is not a JSON object, the path does not exist,
+    /// or the value at the path is not a string.
+    pub fn extract(&self, line: &str) -> Option<String> {
+        let mut value: Value = serde_json::from_str(line).ok()?;
+        for key in &self.path {
+            value = value.as_object_mut()?.remove(key)?;
+        }
+        match value {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Replaces every line by the field's value, dropping lines without it.
+    ///
+    /// # Returns
+    ///
+    /// The extracted values and the number of skipped lines (non-JSON
+    /// lines and lines missing the field).
+    pub fn extract_all(&self, logs: Vec<String>) -> (Vec<String>, usize) {
+        let total = logs.len();
+        let values: Vec<String> = logs.iter().filter_map(|line| self.extract(line)).collect();
+        let skipped = total - values.len();
+        (values, skipped)
+    }
+}
+
+/// One capture group of a line-matching regex, e.g. the path of an
+/// access log request.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureGroup {
+    #[serde(serialize_with = "crate::serialize::regex")]
+    regex: Regex,
+    group: usize,
+}
+
+impl CaptureGroup {
+    /// Selects group `group` of `regex`; group 0 is the whole match.
+    ///
+    /// Without an explicit group the first capture group is used, or the
+    /// whole match when the regex has none.
+    pub fn new(regex: Regex, group: Option<usize>) -> Result<CaptureGroup, String> {
+        let groups = regex.captures_len() - 1;
+        let group = group.unwrap_or(usize::from(groups > 0));
+        if group > groups {
+            return Err(format!(
+                "regex '{}' has no capture group {} (it has {})",
+                regex, group, groups
+            ));
+        }
+        Ok(CaptureGroup { regex, group })
+    }
+
+    /// Returns the text of the group at the first match in `line`.
+    ///
+    /// # Returns
+    ///
+    /// `None` when the regex does not match or the group took no part in
+    /// the match.
+    pub fn extract(&self, line: &str) -> Option<String> {
+        let captures = self.regex.captures(line)?;
+        captures
+            .get(self.group)
+            .map(|text| text.as_str().to_string())
+    }
+
+    /// Replaces every line by the group's text, dropping lines without it.
+    ///
+    /// # Returns
+    ///
+    /// The extracted texts and the number of skipped lines.
+    pub fn extract_all(&self, logs: Vec<String>) -> (Vec<String>, usize) {
+        let total = logs.len();
+        let texts: Vec<String> = logs.iter().filter_map(|line| self.extract(line)).collect();
+        let skipped = total - texts.len();
+        (texts, skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::top_k_words;
+
+    /// Test extraction of top-level and nested fields
+    #[test]
+    fn test_extract_nested_field() {
+        let line = r#"{"level":"error","error":{"message":"disk full","code":28}}"#;
+
+        let nested: JsonField = "error.message".parse().unwrap();
+        assert_eq!(nested.extract(line), Some("disk full".to_string()));
+
+        let numeric: JsonField = "error.code".parse().unwrap();
+        assert_eq!(numeric.extract(line), None);
+
+        let missing: JsonField = "message".parse().unwrap();
+        assert_eq!(missing.extract(line), None);
+        assert!("error..message".parse::<JsonField>().is_err());
+    }
+
+    /// Test counting words of the `message` field across JSONL input
+    #[test]
+    fn test_count_message_field() {
+        let logs = vec![
+            r#"{"level":"error","message":"Disk full on /dev/sda"}"#.to_string(),
+            r#"{"level":"error","message":"disk quota exceeded"}"#.to_string(),
+            r#"{"level":"info","msg":"no message field"}"#.to_string(),
+            "plain text line".to_string(),
+        ];
+        let field: JsonField = "message".parse().unwrap();
+
+        let (messages, skipped) = field.extract_all(logs);
+        let result = top_k_words(&messages, 2);
+
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            result,
+            vec![("disk".to_string(), 2), ("dev".to_string(), 1)]
+        );
+    }
+
+    /// Test counting only the path segments captured from access log lines
+    #[test]
+    fn test_count_capture_group() {
+        let logs = vec![
+            r#"10.0.0.1 "GET /api/users/42 HTTP/1.1" 200"#.to_string(),
+            r#"10.0.0.2 "POST /api/orders HTTP/1.1" 500"#.to_string(),
+            r#"10.0.0.1 "GET /static/app.js HTTP/1.1" 200"#.to_string(),
+            "health check ok".to_string(),
+        ];
+        let regex = Regex::new(r#""(GET|POST) (\S+) HTTP"#).unwrap();
+        let path = CaptureGroup::new(regex.clone(), Some(2)).unwrap();
+
+        let (paths, skipped) = path.extract_all(logs);
+
+        assert_eq!(skipped, 1);
+        assert_eq!(
+            top_k_words(&paths, 3),
+            vec![
+                ("api".to_string(), 2),
+                ("42".to_string(), 1),
+                ("app".to_string(), 1),
+            ]
+        );
+        assert!(CaptureGroup::new(regex, Some(3)).is_err());
+    }
+}
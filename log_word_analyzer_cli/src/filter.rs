@@ -0,0 +1,488 @@
+//! Line-level filters applied before tokenizing and token-level filters
+//! deciding which words are counted.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+
+use regex::Regex;
+use serde::Serialize;
+
+/// Restricts counting to the lines between a start and a stop marker.
+///
+/// Only the first marked region is used: counting begins on the line
+/// after the first match of `start_after` and ends just before the first
+/// subsequent match of `stop_at`. The marker lines themselves are never
+/// counted. A missing start marker means "from the first line", a missing
+/// stop marker means "until the end of the input".
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MarkerRange {
+    /// Counting starts after the first line matching this pattern
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub start_after: Option<Regex>,
+    /// Counting stops at the first later line matching this pattern
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub stop_at: Option<Regex>,
+}
+
+impl MarkerRange {
+    /// Returns true when neither marker is configured.
+    pub fn is_empty(&self) -> bool {
+        self.start_after.is_none() && self.stop_at.is_none()
+    }
+
+    /// Keeps only the lines inside the marked region.
+    ///
+    /// # Arguments
+    ///
+    /// * `logs` - All log lines of one input, in file order
+    ///
+    /// # Returns
+    ///
+    /// The lines strictly between the markers. If a start marker is set
+    /// but never matches, the result is empty.
+    pub fn select(&self, logs: Vec<String>) -> Vec<String> {
+        let mut state = MarkerState::default();
+        logs.into_iter()
+            .filter(|line| self.admit(line, &mut state))
+            .collect()
+    }
+
+    /// Decides for the next line of a stream whether it lies inside the
+    /// marked region, as [`MarkerRange::select`] does for a whole input.
+    ///
+    /// `state` tracks the markers seen so far; start every input with a
+    /// fresh [`MarkerState`].
+    pub fn admit(&self, line: &str, state: &mut MarkerState) -> bool {
+        if state.stopped {
+            return false;
+        }
+        if !state.started {
+            match &self.start_after {
+                // The start marker line itself is not counted
+                Some(start_after) => {
+                    state.started = start_after.is_match(line);
+                    return false;
+                }
+                // Without a start marker the region is open from the first line
+                None => state.started = true,
+            }
+        }
+        if self.stop_at.as_ref().is_some_and(|re| re.is_match(line)) {
+            state.stopped = true;
+            return false;
+        }
+        true
+    }
+}
+
+/// How far a stream of lines has got through a [`MarkerRange`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkerState {
+    started: bool,
+    stopped: bool,
+}
+
+/// Why a token was left out of the counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DropReason {
+    /// The token is a configured stop word
+    StopWord,
+    /// The token is shorter than the minimum length
+    TooShort,
+    /// The token consists only of digits
+    Numeric,
+    /// The token does not contain the required substring
+    MissingSubstring,
+    /// The token occurs in the reference vocabulary
+    KnownWord,
+    /// The token is a new word but the unique-word limit was reached
+    UniqueLimit,
+    /// The word was already counted for the same record
+    RepeatedInRecord,
+}
+
+impl DropReason {
+    /// The command-line option that enables this filter.
+    pub fn option(self) -> &'static str {
+        match self {
+            DropReason::StopWord => "--stop-words",
+            DropReason::TooShort => "--min-len",
+            DropReason::Numeric => "--exclude-numeric",
+            DropReason::MissingSubstring => "--contains",
+            DropReason::KnownWord => "--new-against",
+            DropReason::UniqueLimit => "--max-unique",
+            DropReason::RepeatedInRecord => "--record-frequency",
+        }
+    }
+}
+
+/// Token-level filters applied to every lowercased word before counting.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TokenFilter {
+    /// Words that are never counted (stored lowercase)
+    #[serde(serialize_with = "crate::serialize::sorted")]
+    pub stop_words: HashSet<String>,
+    /// Glob patterns of words that are never counted (stored lowercase);
+    /// `*` matches any run of characters and `?` a single one
+    pub stop_globs: Vec<String>,
+    /// Minimum token length in characters; `0` keeps everything
+    pub min_len: usize,
+    /// Drop tokens made only of ASCII digits
+    pub exclude_numeric: bool,
+    /// Keep only tokens containing this substring (stored lowercase)
+    pub contains: Option<String>,
+    /// Reference vocabulary whose words are never counted, so only novel
+    /// words remain (stored lowercase); loaded from `--new-against`
+    #[serde(skip)]
+    pub known_words: HashSet<String>,
+    /// Stop tracking new words once this many distinct words are counted.
+    ///
+    /// Words already in the map keep being incremented, so frequent words
+    /// (which tend to appear early and often) are preserved while memory
+    /// stays bounded. Enforced by [`crate::WordCounter`], not by [`TokenFilter::check`].
+    pub max_unique: Option<usize>,
+    /// Tokenize at most this many words of each line; the rest of a
+    /// longer line is ignored, bounding the work pathological lines cause.
+    /// Applied by the counting functions, not by [`TokenFilter::check`].
+    pub max_tokens_per_line: Option<usize>,
+    /// Whenever the map of a [`crate::WordCounter`] has doubled (and holds
+    /// at least [`crate::PRUNE_MIN_WORDS`] words), drop the words counted
+    /// fewer than this many times so far.
+    ///
+    /// This bounds memory on high-cardinality streams in the manner of
+    /// Misra-Gries, at the cost of exactness: a word loses at most
+    /// `threshold - 1` occurrences per pruning pass, so every count is
+    /// low by at most `(threshold - 1) * passes` and a word whose true
+    /// count exceeds that bound is always kept. Heavy hitters survive,
+    /// rare words may vanish from the counts.
+    pub prune_threshold: Option<usize>,
+    /// Count each word at most once per line, or per record with a
+    /// record separator other than newline, so counts tell in how many
+    /// records a word occurs. Enforced by [`crate::WordCounter`].
+    pub once_per_record: bool,
+}
+
+impl TokenFilter {
+    /// Keeps only tokens containing `needle`, matched case-insensitively.
+    pub fn set_contains(&mut self, needle: &str) {
+        self.contains = Some(needle.to_lowercase());
+    }
+
+    /// Adds stop words, lowercasing them to match the counted tokens.
+    pub fn add_stop_words<'a>(&mut self, words: impl IntoIterator<Item = &'a str>) {
+        self.stop_words.extend(
+            words
+                .into_iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+    }
+
+    /// Turns the stop words containing `*` or `?` into glob patterns.
+    ///
+    /// Entries without wildcards keep matching literally.
+    pub fn use_stop_globs(&mut self) {
+        let (globs, literals): (HashSet<String>, HashSet<String>) = self
+            .stop_words
+            .drain()
+            .partition(|word| word.contains(['*', '?']));
+        self.stop_words = literals;
+        self.stop_globs.extend(globs);
+        self.stop_globs.sort();
+    }
+
+    /// Whether counting depends on the order of all lines seen so far, as
+    /// with `max_unique` and `prune_threshold`, so the lines cannot be
+    /// counted in independent chunks and merged.
+    pub fn is_order_dependent(&self) -> bool {
+        self.max_unique.is_some() || self.prune_threshold.is_some()
+    }
+
+    /// The filters that are switched on, in reporting order.
+    pub fn active(&self) -> Vec<DropReason> {
+        let mut active = Vec::new();
+        if !self.stop_words.is_empty() || !self.stop_globs.is_empty() {
+            active.push(DropReason::StopWord);
+        }
+        if self.min_len > 0 {
+            active.push(DropReason::TooShort);
+        }
+        if self.exclude_numeric {
+            active.push(DropReason::Numeric);
+        }
+        if self.contains.is_some() {
+            active.push(DropReason::MissingSubstring);
+        }
+        if !self.known_words.is_empty() {
+            active.push(DropReason::KnownWord);
+        }
+        if self.max_unique.is_some() {
+            active.push(DropReason::UniqueLimit);
+        }
+        if self.once_per_record {
+            active.push(DropReason::RepeatedInRecord);
+        }
+        active
+    }
+
+    /// Decides whether a lowercased token is counted.
+    ///
+    /// # Returns
+    ///
+    /// `None` when the token is kept, otherwise the first filter that
+    /// rejected it.
+    pub fn check(&self, word: &str) -> Option<DropReason> {
+        if self.stop_words.contains(word)
+            || self
+                .stop_globs
+                .iter()
+                .any(|pattern| glob_match(pattern, word))
+        {
+            return Some(DropReason::StopWord);
+        }
+        if self.min_len > 0 && word.chars().count() < self.min_len {
+            return Some(DropReason::TooShort);
+        }
+        if self.exclude_numeric && word.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(DropReason::Numeric);
+        }
+        if let Some(needle) = &self.contains
+            && !word.contains(needle.as_str())
+        {
+            return Some(DropReason::MissingSubstring);
+        }
+        if self.known_words.contains(word) {
+            return Some(DropReason::KnownWord);
+        }
+        None
+    }
+
+    /// Checks a token and records the outcome in `stats`.
+    ///
+    /// # Returns
+    ///
+    /// True when the token passed every filter and should be counted.
+    pub fn admit(&self, word: &str, stats: &mut FilterStats) -> bool {
+        match self.check(word) {
+            Some(reason) => {
+                *stats.tokens_dropped.entry(reason).or_insert(0) += 1;
+                false
+            }
+            None => {
+                stats.tokens_counted += 1;
+                true
+            }
+        }
+    }
+}
+
+/// What the line and token filters removed, as reported by `--explain`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    /// Lines removed, keyed by the option of the line filter
+    pub lines_skipped: BTreeMap<&'static str, usize>,
+    /// Tokens removed, keyed by the token filter that dropped them
+    pub tokens_dropped: BTreeMap<DropReason, usize>,
+    /// Tokens that passed every filter and were counted
+    pub tokens_counted: usize,
+    /// Times the counts were pruned below the `prune_threshold`
+    pub prune_passes: usize,
+    /// Words removed from the counts by those passes
+    pub words_pruned: usize,
+}
+
+impl FilterStats {
+    /// Creates statistics with a zero entry for every active token filter.
+    pub fn for_filter(filter: &TokenFilter) -> Self {
+        FilterStats {
+            tokens_dropped: filter
+                .active()
+                .into_iter()
+                .map(|reason| (reason, 0))
+                .collect(),
+            ..FilterStats::default()
+        }
+    }
+
+    /// Records lines removed by the line filter behind `option`.
+    pub fn skip_lines(&mut self, option: &'static str, lines: usize) {
+        *self.lines_skipped.entry(option).or_insert(0) += lines;
+    }
+
+    /// Adds the statistics of another run or chunk to these.
+    pub fn merge(&mut self, other: FilterStats) {
+        for (option, lines) in other.lines_skipped {
+            *self.lines_skipped.entry(option).or_insert(0) += lines;
+        }
+        for (reason, tokens) in other.tokens_dropped {
+            *self.tokens_dropped.entry(reason).or_insert(0) += tokens;
+        }
+        self.tokens_counted += other.tokens_counted;
+        self.prune_passes += other.prune_passes;
+        self.words_pruned += other.words_pruned;
+    }
+
+    /// The most occurrences pruning can have taken from any one count:
+    /// `threshold - 1` per pass.
+    pub fn prune_error_bound(&self, threshold: usize) -> usize {
+        threshold
+            .saturating_sub(1)
+            .saturating_mul(self.prune_passes)
+    }
+
+    /// Writes one `explain:` line per active filter and the kept total.
+    pub fn write_report<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (option, lines) in &self.lines_skipped {
+            writeln!(writer, "explain: {} lines skipped by {}", lines, option)?;
+        }
+        for (reason, tokens) in &self.tokens_dropped {
+            writeln!(
+                writer,
+                "explain: {} tokens dropped by {}",
+                tokens,
+                reason.option()
+            )?;
+        }
+        if self.prune_passes > 0 {
+            writeln!(
+                writer,
+                "explain: {} words dropped by --prune-threshold in {} passes",
+                self.words_pruned, self.prune_passes
+            )?;
+        }
+        writeln!(writer, "explain: {} tokens counted", self.tokens_counted)
+    }
+}
+
+/// Matches a whole word against a glob where `*` stands for any run of
+/// characters and `?` for exactly one.
+pub(crate) fn glob_match(pattern: &str, word: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let (mut p, mut w) = (0, 0);
+    // Position of the last `*` and the word position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while w < word.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, w));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == word[w] => {
+                p += 1;
+                w += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    p = star + 1;
+                    w = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    /// Test that only the lines between the two markers are kept
+    #[test]
+    fn test_select_between_markers() {
+        let range = MarkerRange {
+            start_after: Some(Regex::new("request id=42").unwrap()),
+            stop_at: Some(Regex::new("^END").unwrap()),
+        };
+        let logs = lines(&[
+            "boot ok",
+            "request id=42 started",
+            "error disk full",
+            "error retry",
+            "END of request",
+            "error after stop",
+        ]);
+
+        assert_eq!(
+            range.select(logs),
+            lines(&["error disk full", "error retry"])
+        );
+    }
+
+    /// Test open-ended ranges and a start marker that never matches
+    #[test]
+    fn test_select_open_ranges() {
+        let logs = lines(&["a", "mark", "b", "stop", "c"]);
+
+        let start_only = MarkerRange {
+            start_after: Some(Regex::new("mark").unwrap()),
+            stop_at: None,
+        };
+        assert_eq!(start_only.select(logs.clone()), lines(&["b", "stop", "c"]));
+
+        let stop_only = MarkerRange {
+            start_after: None,
+            stop_at: Some(Regex::new("stop").unwrap()),
+        };
+        assert_eq!(stop_only.select(logs.clone()), lines(&["a", "mark", "b"]));
+
+        let missing = MarkerRange {
+            start_after: Some(Regex::new("absent").unwrap()),
+            stop_at: None,
+        };
+        assert!(missing.select(logs).is_empty());
+    }
+
+    /// Test each token filter and the reported drop reason
+    #[test]
+    fn test_token_filter_check() {
+        let mut filter = TokenFilter {
+            min_len: 3,
+            exclude_numeric: true,
+            ..TokenFilter::default()
+        };
+        filter.add_stop_words(["The", " and ", ""]);
+
+        assert_eq!(filter.check("the"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("and"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("ok"), Some(DropReason::TooShort));
+        assert_eq!(filter.check("404"), Some(DropReason::Numeric));
+        assert_eq!(filter.check("error404"), None);
+        assert_eq!(
+            filter.active(),
+            vec![
+                DropReason::StopWord,
+                DropReason::TooShort,
+                DropReason::Numeric
+            ]
+        );
+    }
+
+    /// Test that glob stop words drop a family of tokens and plain entries stay literal
+    #[test]
+    fn test_stop_word_globs() {
+        let mut filter = TokenFilter::default();
+        filter.add_stop_words(["REQ*", "t?meout", "err"]);
+        filter.use_stop_globs();
+
+        assert_eq!(filter.check("request"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("requests"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("req"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("timeout"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("err"), Some(DropReason::StopWord));
+        assert_eq!(filter.check("error"), None);
+        assert_eq!(filter.check("prereq"), None);
+        assert_eq!(filter.check("tmeout"), None);
+
+        assert!(glob_match("*a*b", "xaybab"));
+        assert!(!glob_match("*a*b", "xaybx"));
+    }
+}
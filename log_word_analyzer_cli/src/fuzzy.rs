@@ -0,0 +1,117 @@
+//! Merging of near-duplicate words, such as typos, by edit distance.
+
+use std::collections::HashMap;
+
+/// Computes the Levenshtein distance between two words: the fewest
+/// single-character insertions, deletions and substitutions turning one
+/// into the other.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::fuzzy::levenshtein;
+///
+/// assert_eq!(levenshtein("conection", "connection"), 1);
+/// assert_eq!(levenshtein("disk", "desk"), 1);
+/// assert_eq!(levenshtein("disk", "dns"), 2);
+/// ```
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // One row of the dynamic-programming table at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Counts words within `distance` edits of each other together, under
+/// the most frequent spelling.
+///
+/// Words are visited from the most to the least frequent (alphabetically
+/// among equal counts). Each is merged into the first canonical spelling
+/// within `distance`, or becomes a canonical spelling itself, so merging
+/// never chains past a more frequent word. The work grows with the
+/// product of the vocabulary and the number of canonical spellings.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::{count_words, select_top_k};
+/// use log_word_analyzer_cli::fuzzy::fuzzy_merge;
+///
+/// let counts = count_words(&["connection conection connection reset".to_string()]);
+/// let merged = fuzzy_merge(counts, 1);
+/// assert_eq!(select_top_k(merged, 1), vec![("connection".to_string(), 3)]);
+/// ```
+pub fn fuzzy_merge(
+    frequency_map: HashMap<String, usize>,
+    distance: usize,
+) -> HashMap<String, usize> {
+    let mut words: Vec<(String, usize)> = frequency_map.into_iter().collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut canonical: Vec<(String, usize, usize)> = Vec::new();
+    for (word, count) in words {
+        let length = word.chars().count();
+        let target = canonical.iter_mut().find(|(spelling, spelling_length, _)| {
+            // Words whose lengths differ by more than `distance` cannot match
+            length.abs_diff(*spelling_length) <= distance
+                && levenshtein(spelling, &word) <= distance
+        });
+        match target {
+            Some((_, _, total)) => *total += count,
+            None => canonical.push((word, length, count)),
+        }
+    }
+
+    canonical
+        .into_iter()
+        .map(|(word, _, total)| (word, total))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_words, select_top_k};
+
+    /// Test that a typo merges into the more frequent spelling
+    #[test]
+    fn test_typo_merges_into_frequent_spelling() {
+        let logs = vec![
+            "connection reset".to_string(),
+            "connection refused".to_string(),
+            "conection timeout".to_string(),
+        ];
+
+        let merged = fuzzy_merge(count_words(&logs), 1);
+
+        assert_eq!(merged.get("connection"), Some(&3));
+        assert_eq!(merged.get("conection"), None);
+        // "reset" and "refused" are further apart and stay separate
+        assert_eq!(
+            select_top_k(merged, 3),
+            vec![
+                ("connection".to_string(), 3),
+                ("refused".to_string(), 1),
+                ("reset".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test distances including insertions and multibyte characters
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+}
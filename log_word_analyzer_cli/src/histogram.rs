@@ -0,0 +1,129 @@
+//! Word-length histograms for characterizing a log's vocabulary.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+/// Widest bar [`write_chart`] draws, in characters.
+const CHART_WIDTH: usize = 40;
+
+/// What each word adds to the bucket of its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    /// Every distinct word counts once
+    #[default]
+    Distinct,
+    /// Every occurrence of a word counts once
+    Tokens,
+}
+
+impl LengthUnit {
+    /// Returns the column name used for this unit in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            LengthUnit::Distinct => "words",
+            LengthUnit::Tokens => "tokens",
+        }
+    }
+}
+
+/// Groups counted words by their length in characters.
+///
+/// # Returns
+///
+/// A map from word length to the number of distinct words or token
+/// occurrences of that length, in ascending length order. Lengths without
+/// any word are absent.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::count_words;
+/// use log_word_analyzer_cli::histogram::{LengthUnit, length_histogram};
+///
+/// let counts = count_words(&["error disk disk".to_string()]);
+/// let tokens = length_histogram(&counts, LengthUnit::Tokens);
+/// assert_eq!(tokens.into_iter().collect::<Vec<_>>(), vec![(4, 2), (5, 1)]);
+/// ```
+pub fn length_histogram(
+    frequency_map: &HashMap<String, usize>,
+    unit: LengthUnit,
+) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for (word, count) in frequency_map {
+        let added = match unit {
+            LengthUnit::Distinct => 1,
+            LengthUnit::Tokens => *count,
+        };
+        *histogram.entry(word.chars().count()).or_insert(0) += added;
+    }
+    histogram
+}
+
+/// Draws a histogram as one bar of `#` per length, scaled so the largest
+/// count fills the chart width.
+///
+/// Every nonzero count gets at least one `#`, so rare lengths stay visible.
+pub fn write_chart<W: Write>(histogram: &BTreeMap<usize, usize>, writer: &mut W) -> io::Result<()> {
+    let max = histogram.values().copied().max().unwrap_or(0);
+    let label_width = histogram
+        .keys()
+        .last()
+        .map_or(1, |length| length.to_string().len());
+    for (length, count) in histogram {
+        let bar = (count * CHART_WIDTH).div_ceil(max.max(1));
+        writeln!(
+            writer,
+            "{:>width$} | {} {}",
+            length,
+            "#".repeat(bar),
+            count,
+            width = label_width
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_words;
+
+    /// Test per-length counts of distinct words and of token occurrences
+    #[test]
+    fn test_length_histogram_known_input() {
+        let logs = vec![
+            "a an the the error".to_string(),
+            "a disk disk errors timeout".to_string(),
+        ];
+        let counts = count_words(&logs);
+
+        let distinct = length_histogram(&counts, LengthUnit::Distinct);
+        let tokens = length_histogram(&counts, LengthUnit::Tokens);
+
+        assert_eq!(
+            distinct.into_iter().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1), (7, 1)]
+        );
+        assert_eq!(
+            tokens.into_iter().collect::<Vec<_>>(),
+            vec![(1, 2), (2, 1), (3, 2), (4, 2), (5, 1), (6, 1), (7, 1)]
+        );
+    }
+
+    /// Test that bars scale to the largest count
+    #[test]
+    fn test_write_chart() {
+        let histogram = BTreeMap::from([(3, 4), (10, 1)]);
+        let mut buffer = Vec::new();
+
+        write_chart(&histogram, &mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(" 3 | {} 4\n10 | {} 1\n", "#".repeat(40), "#".repeat(10))
+        );
+    }
+}
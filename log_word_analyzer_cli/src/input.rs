@@ -0,0 +1,1199 @@
+//! Reading log input from files and, with the `http` feature, URLs.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::filter::glob_match;
+
+/// Character encodings accepted by `--encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Strict UTF-8; invalid byte sequences are an error
+    #[default]
+    Utf8,
+    /// ISO-8859-1, decoded as its Windows-1252 superset like browsers do
+    Latin1,
+    /// Windows-1252 (Western European)
+    Windows1252,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            _ => Err(format!(
+                "unknown encoding '{}' (expected utf8, latin1 or windows-1252)",
+                name
+            )),
+        }
+    }
+}
+
+impl Encoding {
+    /// Decodes the raw bytes of one line into text.
+    fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec())
+                .map_err(|_| "stream did not contain valid UTF-8".to_string()),
+            Encoding::Latin1 | Encoding::Windows1252 => {
+                // Single-byte encodings map every byte, so decoding never fails
+                let (text, _) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes);
+                Ok(text.into_owned())
+            }
+        }
+    }
+}
+
+/// Byte order mark that Windows tools put at the start of UTF-8 files.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Byte that separates one record (log line) from the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSeparator {
+    /// Records end at this byte, e.g. `\n` or NUL for `journalctl -0`
+    Byte(u8),
+    /// Records end at `\n`, `\r\n` or a bare `\r`, so Windows and classic
+    /// Mac line endings can be mixed in one input
+    AnyNewline,
+}
+
+impl Default for RecordSeparator {
+    fn default() -> Self {
+        RecordSeparator::Byte(b'\n')
+    }
+}
+
+impl FromStr for RecordSeparator {
+    type Err = String;
+
+    /// Parses `any` (every newline convention), `\0`, `\n`, `\r`, `\t`, a
+    /// hex byte such as `0x1e`, or any single ASCII character.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let byte = match spec {
+            "any" => return Ok(RecordSeparator::AnyNewline),
+            "\\0" | "nul" => 0,
+            "\\n" => b'\n',
+            "\\r" => b'\r',
+            "\\t" => b'\t',
+            _ if spec.len() == 1 && spec.is_ascii() => spec.as_bytes()[0],
+            _ => spec
+                .strip_prefix("0x")
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid record separator '{}'", spec))?,
+        };
+        Ok(RecordSeparator::Byte(byte))
+    }
+}
+
+/// Options controlling how input files are turned into lines.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReadOptions {
+    /// Character encoding of the input bytes
+    pub encoding: Encoding,
+    /// Separator between records; newline by default
+    pub separator: RecordSeparator,
+    /// Compression of the input files
+    pub compression: Compression,
+    /// Upper bound on records consumed per second, for live sources
+    pub max_lines_per_sec: Option<f64>,
+    /// Times a transiently failing open or read is retried, with backoff
+    pub read_retries: usize,
+    /// Treat a source that sends no data for this long as ended, so a
+    /// FIFO or live stream that goes quiet still gets reported
+    #[serde(serialize_with = "crate::serialize::option_secs")]
+    pub idle_timeout: Option<Duration>,
+    /// Read only the records beginning at or after this byte offset
+    pub byte_start: u64,
+    /// Stop at the first record beginning at or after this byte offset
+    pub byte_end: Option<u64>,
+}
+
+impl ReadOptions {
+    /// Returns true when only a byte range of each input is read.
+    pub fn has_byte_range(&self) -> bool {
+        self.byte_start > 0 || self.byte_end.is_some()
+    }
+}
+
+/// Input name that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// Returns true for `http://` and `https://` inputs, which are fetched
+/// instead of opened as files.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Limits how fast records are consumed by sleeping between them.
+///
+/// The throttle keeps the average rate since it was created at or below
+/// the limit: after `n` records at least `n / rate` seconds have passed.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    interval: Duration,
+    start: Instant,
+    records: u32,
+}
+
+impl Throttle {
+    /// Creates a throttle allowing `max_per_sec` records per second.
+    pub fn new(max_per_sec: f64) -> Self {
+        Throttle {
+            interval: Duration::from_secs_f64(1.0 / max_per_sec),
+            start: Instant::now(),
+            records: 0,
+        }
+    }
+
+    /// Accounts for one record, sleeping if the rate would be exceeded.
+    pub fn tick(&mut self) {
+        self.records = self.records.saturating_add(1);
+        let due = self.interval * self.records;
+        let elapsed = self.start.elapsed();
+        if elapsed < due {
+            thread::sleep(due - elapsed);
+        }
+    }
+}
+
+/// Wait before the first retry of [`RetryReader`]; it doubles with every
+/// further attempt.
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Retries reads failing with a transient error, such as a timeout or an
+/// I/O error on a flaky network mount, instead of giving up at once.
+///
+/// A failed read is retried up to `retries` times, waiting `backoff`
+/// before the first retry and twice as long before each next one. Any
+/// successful read resets the count. Permanent errors, such as a missing
+/// file or invalid data, are returned immediately.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+/// use std::time::Duration;
+/// use log_word_analyzer_cli::input::RetryReader;
+///
+/// let mut reader = RetryReader::new("error disk".as_bytes(), 3, Duration::ZERO);
+/// let mut text = String::new();
+/// reader.read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "error disk");
+/// ```
+#[derive(Debug)]
+pub struct RetryReader<R> {
+    inner: R,
+    retries: usize,
+    backoff: Duration,
+}
+
+impl<R: Read> RetryReader<R> {
+    /// Wraps `inner`, retrying each failing read up to `retries` times.
+    pub fn new(inner: R, retries: usize, backoff: Duration) -> Self {
+        RetryReader {
+            inner,
+            retries,
+            backoff,
+        }
+    }
+}
+
+impl<R: Read> Read for RetryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        retry(self.retries, self.backoff, || self.inner.read(buf))
+    }
+}
+
+/// Runs `operation`, retrying it after transient errors as described for
+/// [`RetryReader`].
+fn retry<T>(
+    retries: usize,
+    backoff: Duration,
+    mut operation: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut wait = backoff;
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                thread::sleep(wait);
+                wait *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns true for errors that may go away when the operation is simply
+/// tried again.
+fn is_transient(error: &io::Error) -> bool {
+    // EIO, as network file systems report a lost server
+    const EIO: i32 = 5;
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::StaleNetworkFileHandle
+    ) || (cfg!(unix) && error.raw_os_error() == Some(EIO))
+}
+
+/// Ends a stream that sends no data for a while, as if it had reached
+/// end of file, so reading a FIFO or live source that never closes
+/// finishes once it goes quiet.
+///
+/// The source is read on a background thread in chunks of up to 8 KiB.
+/// When no chunk arrives within `timeout`, this reader reports end of
+/// file and the thread is left blocked on the source; it ends with the
+/// process. Errors of the source are passed on.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+/// use std::time::Duration;
+/// use log_word_analyzer_cli::input::IdleTimeoutReader;
+///
+/// let mut reader = IdleTimeoutReader::new("error disk".as_bytes(), Duration::from_secs(1));
+/// let mut text = String::new();
+/// reader.read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "error disk");
+/// ```
+#[derive(Debug)]
+pub struct IdleTimeoutReader {
+    chunks: mpsc::Receiver<io::Result<Vec<u8>>>,
+    timeout: Duration,
+    /// Received bytes not yet returned by `read`
+    pending: Vec<u8>,
+    offset: usize,
+    ended: bool,
+}
+
+impl IdleTimeoutReader {
+    /// Starts reading `source` in the background, ending after `timeout`
+    /// without data.
+    pub fn new<R: Read + Send + 'static>(mut source: R, timeout: Duration) -> Self {
+        // A small bound keeps a fast source from buffering without limit
+        let (sender, chunks) = mpsc::sync_channel(16);
+        thread::spawn(move || {
+            let mut buffer = vec![0; 8192];
+            loop {
+                let chunk = match source.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => Ok(buffer[..read].to_vec()),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e),
+                };
+                let failed = chunk.is_err();
+                if sender.send(chunk).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        IdleTimeoutReader {
+            chunks,
+            timeout,
+            pending: Vec::new(),
+            offset: 0,
+            ended: false,
+        }
+    }
+}
+
+impl Read for IdleTimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.pending.len() {
+            if self.ended {
+                return Ok(0);
+            }
+            match self.chunks.recv_timeout(self.timeout) {
+                Ok(chunk) => {
+                    self.pending = chunk?;
+                    self.offset = 0;
+                }
+                // A quiet source and a closed one both end the stream
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                    self.ended = true;
+                    return Ok(0);
+                }
+            }
+        }
+        let read = buf.len().min(self.pending.len() - self.offset);
+        buf[..read].copy_from_slice(&self.pending[self.offset..self.offset + read]);
+        self.offset += read;
+        Ok(read)
+    }
+}
+
+/// Compression format of an input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// Pick the decoder from the file extension (`.xz`, `.lz4`)
+    #[default]
+    Auto,
+    /// Plain, uncompressed text
+    None,
+    /// xz / LZMA2 (requires the `xz` feature)
+    Xz,
+    /// LZ4 frame format (requires the `lz4` feature)
+    Lz4,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "auto" => Ok(Compression::Auto),
+            "none" => Ok(Compression::None),
+            "xz" => Ok(Compression::Xz),
+            "lz4" => Ok(Compression::Lz4),
+            _ => Err(format!(
+                "unknown compression '{}' (expected auto, none, xz or lz4)",
+                name
+            )),
+        }
+    }
+}
+
+impl Compression {
+    /// Lowercase name, matching the `--compression` value and Cargo feature.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Auto => "auto",
+            Compression::None => "none",
+            Compression::Xz => "xz",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    /// Resolves `Auto` to a concrete format using the file extension.
+    pub fn resolve(self, path: &str) -> Compression {
+        if self != Compression::Auto {
+            return self;
+        }
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("xz") => Compression::Xz,
+            Some("lz4") => Compression::Lz4,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Opens the file at `path` and wraps it in the matching decoder.
+///
+/// The path `-` reads standard input instead, and URLs (see [`is_url`])
+/// are streamed from the response body of a GET request. With
+/// `options.read_retries`, opening a file and reading the raw bytes are
+/// retried after transient errors (see [`RetryReader`]).
+///
+/// With a byte range, the file is read from one byte before
+/// `options.byte_start`, so that [`read_records`] can tell whether a
+/// record begins exactly there. Only uncompressed files can seek.
+///
+/// The compression format comes from `options.compression`, falling back
+/// to the file extension when it is `Auto`. Formats whose Cargo feature is
+/// disabled are reported as an error instead of being read as garbage.
+///
+/// # Returns
+///
+/// A buffered reader yielding the decompressed bytes.
+pub fn open_reader(path: &str, options: &ReadOptions) -> Result<Box<dyn BufRead>, String> {
+    if options.has_byte_range()
+        && (path == STDIN || is_url(path) || options.compression.resolve(path) != Compression::None)
+    {
+        return Err(format!(
+            "Unable to read a byte range of '{}': only uncompressed files can seek",
+            path
+        ));
+    }
+
+    // Standard input is always read as plain text
+    if path == STDIN {
+        return Ok(match options.idle_timeout {
+            Some(timeout) => Box::new(BufReader::new(IdleTimeoutReader::new(io::stdin(), timeout))),
+            None => Box::new(io::stdin().lock()),
+        });
+    }
+
+    let source: Box<dyn Read + Send> = if is_url(path) {
+        open_url(path)?
+    } else {
+        let mut file = retry(options.read_retries, RETRY_BACKOFF, || File::open(path))
+            .map_err(|e| format!("Unable to open file '{}': {}", path, e))?;
+        // Opening a directory succeeds on Unix, only reading it fails
+        if file.metadata().is_ok_and(|metadata| metadata.is_dir()) {
+            return Err(format!("'{}' is a directory", path));
+        }
+        if options.byte_start > 0 {
+            file.seek(SeekFrom::Start(options.byte_start - 1))
+                .map_err(|e| format!("Unable to seek in file '{}': {}", path, e))?;
+        }
+        Box::new(file)
+    };
+    let source: Box<dyn Read + Send> = if options.read_retries > 0 {
+        Box::new(RetryReader::new(
+            source,
+            options.read_retries,
+            RETRY_BACKOFF,
+        ))
+    } else {
+        source
+    };
+    let source: Box<dyn Read> = match options.idle_timeout {
+        Some(timeout) => Box::new(IdleTimeoutReader::new(source, timeout)),
+        None => source,
+    };
+
+    match options.compression.resolve(path) {
+        Compression::Auto | Compression::None => Ok(Box::new(BufReader::new(source))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(BufReader::new(xz2::read::XzDecoder::new(source)))),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => Ok(Box::new(BufReader::new(
+            lz4_flex::frame::FrameDecoder::new(source),
+        ))),
+        #[allow(unreachable_patterns)]
+        unsupported => {
+            let name = unsupported.name();
+            Err(format!(
+                "'{}' is {}-compressed but this build lacks the `{}` feature",
+                path, name, name
+            ))
+        }
+    }
+}
+
+/// Sends a GET request for `url` and returns the response body.
+///
+/// A gzip `Content-Encoding` is negotiated and decoded transparently, and
+/// error statuses are reported like a file that cannot be opened.
+#[cfg(feature = "http")]
+fn open_url(url: &str) -> Result<Box<dyn Read + Send>, String> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Unable to fetch '{}': {}", url, e))?;
+    Ok(Box::new(response.into_body().into_reader()))
+}
+
+#[cfg(not(feature = "http"))]
+fn open_url(url: &str) -> Result<Box<dyn Read + Send>, String> {
+    Err(format!(
+        "'{}' is a URL but this build lacks the `http` feature",
+        url
+    ))
+}
+
+/// Reads every line of the file at `path` into a vector.
+///
+/// Compressed files are decoded first (see [`open_reader`]). Records are
+/// split on the configured separator (by default `\n`, with a trailing
+/// `\r` removed) and each record's raw bytes are decoded with the
+/// configured encoding. A UTF-8 byte order mark at the very start of the
+/// file is dropped so it never becomes part of the first line.
+///
+/// # Arguments
+///
+/// * `path` - Path to the log file to read
+/// * `options` - How to decompress and decode the file contents
+///
+/// # Returns
+///
+/// The lines of the file, or a human-readable error message when the
+/// file cannot be opened or read.
+pub fn read_lines(path: &str, options: &ReadOptions) -> Result<Vec<String>, String> {
+    let mut logs = Vec::new();
+    stream_lines(path, options, |line| logs.push(line))?;
+    Ok(logs)
+}
+
+/// Reads the file at `path` like [`read_lines`], but passes every line to
+/// `f` as soon as it is read instead of collecting them, so a live source
+/// such as standard input is processed as it arrives.
+pub fn stream_lines(
+    path: &str,
+    options: &ReadOptions,
+    f: impl FnMut(String),
+) -> Result<(), String> {
+    let reader = open_reader(path, options)?;
+    for_each_record(reader, options, f)
+        .map_err(|e| format!("Unable to read line from '{}': {}", path, e))
+}
+
+/// Lists the files below the directory `root`, descending into
+/// subdirectories.
+///
+/// Only files whose name matches the glob `pattern` (such as `*.log`) are
+/// listed when one is given. Symbolic links to files are listed, links to
+/// directories are not followed.
+///
+/// # Returns
+///
+/// The file paths in sorted order, and a warning for every entry that
+/// could not be read and was skipped.
+pub fn walk_dir(root: &str, pattern: Option<&str>) -> (Vec<String>, Vec<String>) {
+    find_files(root, pattern, true)
+}
+
+/// Lists the files directly in the directory `root`, like [`walk_dir`]
+/// but without descending into subdirectories.
+pub fn list_dir(root: &str, pattern: Option<&str>) -> (Vec<String>, Vec<String>) {
+    find_files(root, pattern, false)
+}
+
+/// Lists the files of [`walk_dir`], or only those directly in `root`
+/// unless `descend` is set.
+fn find_files(root: &str, pattern: Option<&str>, descend: bool) -> (Vec<String>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut warnings = Vec::new();
+    let mut pending = vec![Path::new(root).to_path_buf()];
+
+    while let Some(directory) = pending.pop() {
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warnings.push(format!("skipping '{}': {}", directory.display(), e));
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warnings.push(format!(
+                        "skipping entry of '{}': {}",
+                        directory.display(),
+                        e
+                    ));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let is_file = match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    if descend {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+                Ok(kind) if kind.is_symlink() => fs::metadata(&path).is_ok_and(|m| m.is_file()),
+                Ok(kind) => kind.is_file(),
+                Err(e) => {
+                    warnings.push(format!("skipping '{}': {}", path.display(), e));
+                    continue;
+                }
+            };
+            let name = entry.file_name();
+            if is_file && pattern.is_none_or(|glob| glob_match(glob, &name.to_string_lossy())) {
+                files.push(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    files.sort();
+    (files, warnings)
+}
+
+/// Picks the `count` most recently modified of `files`.
+///
+/// # Returns
+///
+/// The picked paths, newest first; files modified at the same time are
+/// in path order.
+///
+/// # Errors
+///
+/// If the modification time of a file cannot be read.
+pub fn newest_files(files: &[String], count: usize) -> Result<Vec<String>, String> {
+    let mut dated = Vec::with_capacity(files.len());
+    for file in files {
+        let modified = fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| format!("Unable to read the modification time of '{}': {}", file, e))?;
+        dated.push((modified, file));
+    }
+    dated.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    Ok(dated
+        .into_iter()
+        .take(count)
+        .map(|(_, file)| file.clone())
+        .collect())
+}
+
+/// Reads the input paths listed in a manifest file.
+///
+/// The manifest holds one path per line and may be `-` for standard
+/// input. Surrounding whitespace is trimmed; blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_manifest(path: &str) -> Result<Vec<String>, String> {
+    Ok(parse_manifest(read_lines(path, &ReadOptions::default())?))
+}
+
+/// Extracts the paths from the lines of a manifest; see [`read_manifest`].
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::input::parse_manifest;
+///
+/// let paths = parse_manifest(vec!["# nightly".into(), "app.log".into(), "".into(), " db.log ".into()]);
+/// assert_eq!(paths, ["app.log", "db.log"]);
+/// ```
+pub fn parse_manifest(lines: Vec<String>) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Splits a byte stream into decoded records.
+///
+/// With a byte range, only the records beginning within
+/// `options.byte_start..options.byte_end` are kept. The stream must then
+/// start one byte before `byte_start`, as [`open_reader`] leaves it; the
+/// partial record running into the range is skipped, and the record
+/// running past its end is read whole. With `--record-separator any`,
+/// records begin after a `\n` for this purpose.
+///
+/// # Returns
+///
+/// The records in stream order, or the reason reading or decoding failed.
+pub fn read_records(reader: impl BufRead, options: &ReadOptions) -> Result<Vec<String>, String> {
+    let mut logs = Vec::new();
+    for_each_record(reader, options, |line| logs.push(line))?;
+    Ok(logs)
+}
+
+/// Splits a byte stream into decoded records like [`read_records`],
+/// passing each record to `f` as soon as it is complete.
+///
+/// With `options.max_lines_per_sec`, the [`Throttle`] is ticked once for
+/// every record handed to `f`, so the rate holds for records rather than
+/// reads even when one `\n`-terminated read splits into several records.
+pub fn for_each_record(
+    mut reader: impl BufRead,
+    options: &ReadOptions,
+    mut f: impl FnMut(String),
+) -> Result<(), String> {
+    // Read raw records so that non-UTF-8 encodings can be decoded per record;
+    // with any newline, `\n`-terminated chunks are split on `\r` afterwards
+    let (separator, split_cr) = match options.separator {
+        RecordSeparator::Byte(byte) => (byte, false),
+        RecordSeparator::AnyNewline => (b'\n', true),
+    };
+    let mut throttle = options.max_lines_per_sec.map(Throttle::new);
+    let mut records_read = 0;
+    let mut buffer = Vec::new();
+
+    // Offset of the next record; the byte before the range tells whether
+    // a record begins at its start or the first one is partial
+    let mut offset = options.byte_start.saturating_sub(1);
+    if options.byte_start > 0 {
+        offset += reader
+            .read_until(separator, &mut buffer)
+            .map_err(|e| e.to_string())? as u64;
+    }
+    loop {
+        if options.byte_end.is_some_and(|end| offset >= end) {
+            break;
+        }
+        buffer.clear();
+        let read = reader
+            .read_until(separator, &mut buffer)
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+
+        // Strip the terminator; newlines also drop a preceding `\r` like
+        // `BufRead::lines`, as does the end of the stream
+        if buffer.last() == Some(&separator) {
+            buffer.pop();
+        }
+        if separator == b'\n' && buffer.last() == Some(&b'\r') {
+            buffer.pop();
+        }
+
+        let records: Vec<&[u8]> = if split_cr {
+            buffer.split(|&byte| byte == b'\r').collect()
+        } else {
+            vec![&buffer]
+        };
+        for record in records {
+            // Only the first bytes of the stream may carry a byte order mark
+            let line = if records_read == 0 && options.byte_start == 0 {
+                record.strip_prefix(UTF8_BOM).unwrap_or(record)
+            } else {
+                record
+            };
+            let line = options.encoding.decode(line)?;
+            if let Some(throttle) = throttle.as_mut() {
+                throttle.tick();
+            }
+            records_read += 1;
+            f(line);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{temp_bytes, temp_file};
+
+    /// Returns its data, then blocks like a live source that went quiet.
+    struct StallingReader {
+        data: Option<&'static [u8]>,
+    }
+
+    impl Read for StallingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.data.take() {
+                Some(data) => {
+                    buf[..data.len()].copy_from_slice(data);
+                    Ok(data.len())
+                }
+                None => {
+                    thread::sleep(Duration::from_secs(60));
+                    Ok(0)
+                }
+            }
+        }
+    }
+
+    /// Fails with a transient error a number of times before every read
+    /// that returns data.
+    struct FlakyReader<'a> {
+        data: &'a [u8],
+        failures: u32,
+        failed: u32,
+    }
+
+    impl Read for FlakyReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failed < self.failures {
+                self.failed += 1;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "server not responding",
+                ));
+            }
+            self.failed = 0;
+            // A few bytes at a time, so the failures hit every chunk
+            let length = buf.len().min(self.data.len()).min(4);
+            buf[..length].copy_from_slice(&self.data[..length]);
+            self.data = &self.data[length..];
+            Ok(length)
+        }
+    }
+
+    /// Test that transient failures are retried until reading completes
+    #[test]
+    fn test_retry_reader_recovers() {
+        let flaky = FlakyReader {
+            data: b"error disk\nerror retry\n",
+            failures: 2,
+            failed: 0,
+        };
+        let reader = BufReader::new(RetryReader::new(flaky, 2, Duration::ZERO));
+
+        let logs = read_records(reader, &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, vec!["error disk", "error retry"]);
+        assert_eq!(crate::top_k_words(&logs, 1), vec![("error".to_string(), 2)]);
+    }
+
+    /// Test that retries run out and permanent errors are not retried
+    #[test]
+    fn test_retry_reader_gives_up() {
+        let flaky = FlakyReader {
+            data: b"error\n",
+            failures: 3,
+            failed: 0,
+        };
+        let mut reader = RetryReader::new(flaky, 2, Duration::ZERO);
+        let error = reader.read(&mut [0; 8]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        let mut attempts = 0;
+        let result = retry(5, Duration::ZERO, || -> io::Result<()> {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    /// Test that line terminators are stripped like `BufRead::lines`
+    #[test]
+    fn test_read_lines_utf8() {
+        let path = temp_file("read_utf8.log", "first\r\nsecond\n\nlast");
+
+        let logs = read_lines(&path, &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, vec!["first", "second", "", "last"]);
+    }
+
+    /// Test that invalid UTF-8 is rejected under the default encoding
+    #[test]
+    fn test_read_lines_invalid_utf8() {
+        let path = temp_bytes("read_invalid.log", b"caf\xe9\n");
+
+        let error = read_lines(&path, &ReadOptions::default()).unwrap_err();
+
+        assert!(error.contains("valid UTF-8"));
+    }
+
+    /// Test decoding a Windows-1252 fixture with accented characters
+    #[test]
+    fn test_read_lines_windows_1252() {
+        // "café crème – naïve" plus a euro sign, encoded as Windows-1252
+        let path = temp_bytes(
+            "read_cp1252.log",
+            b"caf\xe9 cr\xe8me \x96 na\xefve\r\nprice 5\x80\n",
+        );
+        let options = ReadOptions {
+            encoding: "windows-1252".parse().unwrap(),
+            ..ReadOptions::default()
+        };
+
+        let logs = read_lines(&path, &options).unwrap();
+
+        assert_eq!(logs, vec!["café crème – naïve", "price 5€"]);
+    }
+
+    /// Test that a leading BOM is stripped from the first line only
+    #[test]
+    fn test_read_lines_strips_leading_bom() {
+        let path = temp_file("read_bom.log", "\u{FEFF}Error: error disk\n\u{FEFF}kept\n");
+
+        let logs = read_lines(&path, &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, vec!["Error: error disk", "\u{FEFF}kept"]);
+        assert_eq!(crate::top_k_words(&logs, 1), vec![("error".to_string(), 2)]);
+    }
+
+    /// Test parsing of the record separator specification
+    #[test]
+    fn test_parse_record_separator() {
+        let parse = |spec: &str| spec.parse::<RecordSeparator>();
+
+        assert_eq!(parse("\\0"), Ok(RecordSeparator::Byte(0)));
+        assert_eq!(parse("\\n"), Ok(RecordSeparator::Byte(b'\n')));
+        assert_eq!(parse(";"), Ok(RecordSeparator::Byte(b';')));
+        assert_eq!(parse("0x1e"), Ok(RecordSeparator::Byte(0x1e)));
+        assert!(parse("ab").is_err());
+    }
+
+    /// Test that bare-CR, CRLF and LF endings all end records with `any`
+    #[test]
+    fn test_read_any_newline_records() {
+        let options = ReadOptions {
+            separator: "any".parse().unwrap(),
+            ..ReadOptions::default()
+        };
+
+        let logs = read_records(
+            &b"error disk\rerror net\r\rwarn\r\ndone\nlast\r"[..],
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            logs,
+            vec!["error disk", "error net", "", "warn", "done", "last"]
+        );
+        assert_eq!(crate::top_k_words(&logs, 1), vec![("error".to_string(), 2)]);
+    }
+
+    /// Test that a final `\r` without a newline is dropped too
+    #[test]
+    fn test_read_trailing_cr_at_end() {
+        let logs = read_records(&b"first\r\nlast\r"[..], &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, vec!["first", "last"]);
+    }
+
+    /// Test splitting NUL-separated records that contain newlines
+    #[test]
+    fn test_read_nul_separated_records() {
+        let path = temp_bytes(
+            "read_nul.log",
+            b"error disk\nfull\0error network\0\0warning\0",
+        );
+        let options = ReadOptions {
+            separator: RecordSeparator::Byte(0),
+            ..ReadOptions::default()
+        };
+
+        let logs = read_lines(&path, &options).unwrap();
+
+        assert_eq!(
+            logs,
+            vec!["error disk\nfull", "error network", "", "warning"]
+        );
+        assert_eq!(
+            crate::top_k_words(&logs, 2),
+            vec![("error".to_string(), 2), ("disk".to_string(), 1)]
+        );
+    }
+
+    /// Test that the decoder is picked by extension unless set explicitly
+    #[test]
+    fn test_compression_resolve() {
+        assert_eq!(Compression::Auto.resolve("app.log.xz"), Compression::Xz);
+        assert_eq!(Compression::Auto.resolve("app.lz4"), Compression::Lz4);
+        assert_eq!(Compression::Auto.resolve("app.log"), Compression::None);
+        assert_eq!(Compression::Lz4.resolve("app.log"), Compression::Lz4);
+        assert_eq!(Compression::None.resolve("app.xz"), Compression::None);
+    }
+
+    #[cfg(any(feature = "xz", feature = "lz4"))]
+    const PLAIN_LOG: &str = "Error: disk full\nerror: network down\nERROR disk error\n";
+
+    /// Test that an .xz fixture yields the same counts as the plaintext
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_read_xz() {
+        use std::io::Write;
+
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(PLAIN_LOG.as_bytes()).unwrap();
+        let path = temp_bytes("read_fixture.log.xz", &encoder.finish().unwrap());
+        let plain = temp_file("read_fixture_xz.log", PLAIN_LOG);
+
+        let options = ReadOptions::default();
+        let decoded = read_lines(&path, &options).unwrap();
+
+        assert_eq!(decoded, read_lines(&plain, &options).unwrap());
+        assert_eq!(
+            crate::top_k_words(&decoded, 2),
+            vec![("error".to_string(), 4), ("disk".to_string(), 2)]
+        );
+    }
+
+    /// Test that an .lz4 fixture yields the same counts as the plaintext
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_read_lz4() {
+        use std::io::Write;
+
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(PLAIN_LOG.as_bytes()).unwrap();
+        let path = temp_bytes("read_fixture.lz4", &encoder.finish().unwrap());
+        let plain = temp_file("read_fixture_lz4.log", PLAIN_LOG);
+
+        let options = ReadOptions::default();
+        let decoded = read_lines(&path, &options).unwrap();
+
+        assert_eq!(decoded, read_lines(&plain, &options).unwrap());
+        assert_eq!(
+            crate::top_k_words(&decoded, 2),
+            vec![("error".to_string(), 4), ("disk".to_string(), 2)]
+        );
+    }
+
+    /// Test that a format without its feature is a clear error
+    #[cfg(not(feature = "xz"))]
+    #[test]
+    fn test_xz_without_feature() {
+        let path = temp_bytes("read_nofeature.xz", b"\xfd7zXZ\0");
+
+        let error = read_lines(&path, &ReadOptions::default()).unwrap_err();
+
+        assert!(error.contains("lacks the `xz` feature"));
+    }
+
+    /// Test that a byte range keeps the lines beginning within it, whole
+    #[test]
+    fn test_read_lines_byte_range() {
+        // Lines begin at bytes 0, 6, 12, 20 and 26
+        let path = temp_file(
+            "input_byte_range.log",
+            "alpha\nbravo\ncharlie\ndelta\necho\n",
+        );
+        let read = |start, end| {
+            let options = ReadOptions {
+                byte_start: start,
+                byte_end: end,
+                ..ReadOptions::default()
+            };
+            read_lines(&path, &options).unwrap()
+        };
+
+        // "bravo" is partial at byte 8, "delta" runs past byte 21
+        assert_eq!(read(8, Some(21)), ["charlie", "delta"]);
+        // A line beginning exactly at either end is in, respectively out
+        assert_eq!(read(6, Some(12)), ["bravo"]);
+        assert_eq!(read(27, None), Vec::<String>::new());
+        assert_eq!(read(0, Some(1)), ["alpha"]);
+    }
+
+    /// Test that throttled reading stays roughly under the configured rate
+    #[test]
+    fn test_read_records_throttled() {
+        let input: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        let options = ReadOptions {
+            max_lines_per_sec: Some(200.0),
+            ..ReadOptions::default()
+        };
+
+        let start = Instant::now();
+        let logs = read_records(input.as_bytes(), &options).unwrap();
+        let elapsed = start.elapsed();
+
+        // 20 records at 200/s need at least 100ms (minus timer slack)
+        assert_eq!(logs.len(), 20);
+        assert!(elapsed >= Duration::from_millis(95), "took {:?}", elapsed);
+        let rate = logs.len() as f64 / elapsed.as_secs_f64();
+        assert!(rate <= 210.0, "rate {} exceeds the limit", rate);
+
+        // With any newline one read holds four records, each of them ticked
+        let options = ReadOptions {
+            separator: RecordSeparator::AnyNewline,
+            ..options
+        };
+        let start = Instant::now();
+        let logs = read_records("a\rb\rc\rd\n".repeat(5).as_bytes(), &options).unwrap();
+        assert_eq!(logs.len(), 20);
+        assert!(
+            start.elapsed() >= Duration::from_millis(95),
+            "took {:?}",
+            start.elapsed()
+        );
+    }
+
+    /// Test that records are passed on before the stream has ended
+    #[test]
+    fn test_for_each_record_streams() {
+        let reader = StallingReader {
+            data: Some(b"first\nsecond\n"),
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let options = ReadOptions::default();
+            let _ = for_each_record(BufReader::new(reader), &options, |line| {
+                let _ = sender.send(line);
+            });
+        });
+
+        let received: Vec<String> = receiver.iter().take(2).collect();
+        assert_eq!(received, ["first", "second"]);
+    }
+
+    /// Serves one HTTP response on a free local port and returns its URL.
+    #[cfg(feature = "http")]
+    fn serve_once(headers: &'static str, body: &'static [u8]) -> String {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/app.log", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Read the request head before answering
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n",
+                body.len(),
+                headers
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+        });
+        url
+    }
+
+    /// Test reading a log served by a local HTTP server
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_read_url() {
+        let url = serve_once("", b"Error: disk full\nerror: network down\n");
+
+        let logs = read_lines(&url, &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, vec!["Error: disk full", "error: network down"]);
+    }
+
+    /// Test that a gzip content-encoding is decoded
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_read_url_gzip() {
+        // "error disk full\nerror network down\nwarning disk\n", gzipped
+        const GZIPPED: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x2d, 0x2a, 0xca,
+            0x2f, 0x52, 0x48, 0xc9, 0x2c, 0xce, 0x56, 0x48, 0x2b, 0xcd, 0xc9, 0xe1, 0x4a, 0x05,
+            0xf3, 0xf3, 0x52, 0x4b, 0xca, 0xf3, 0x8b, 0xb2, 0x15, 0x52, 0xf2, 0xcb, 0xf3, 0xb8,
+            0xca, 0x13, 0x8b, 0xf2, 0x32, 0xf3, 0xd2, 0xc1, 0x8a, 0xb8, 0x00, 0x80, 0x74, 0xf7,
+            0x47, 0x30, 0x00, 0x00, 0x00,
+        ];
+        let url = serve_once("Content-Encoding: gzip\r\n", GZIPPED);
+
+        let logs = read_lines(&url, &ReadOptions::default()).unwrap();
+
+        assert_eq!(
+            crate::top_k_words(&logs, 2),
+            vec![("disk".to_string(), 2), ("error".to_string(), 2)]
+        );
+    }
+
+    /// Test that URLs are refused when the `http` feature is disabled
+    #[cfg(not(feature = "http"))]
+    #[test]
+    fn test_url_without_feature() {
+        let error = read_lines("http://127.0.0.1:1/app.log", &ReadOptions::default()).unwrap_err();
+
+        assert!(error.contains("lacks the `http` feature"));
+    }
+
+    /// Test that a source going quiet ends the stream after the idle timeout
+    #[test]
+    fn test_idle_timeout_ends_quiet_stream() {
+        let timeout = Duration::from_millis(200);
+        let source = StallingReader {
+            data: Some(b"error disk\nerror full\npartial"),
+        };
+        let start = Instant::now();
+
+        let reader = BufReader::new(IdleTimeoutReader::new(source, timeout));
+        let logs = read_records(reader, &ReadOptions::default()).unwrap();
+
+        assert_eq!(logs, ["error disk", "error full", "partial"]);
+        // Generous tolerance for a loaded machine
+        let elapsed = start.elapsed();
+        assert!(elapsed >= timeout, "ended after {:?}", elapsed);
+        assert!(
+            elapsed < Duration::from_secs(5),
+            "ended after {:?}",
+            elapsed
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Dictionary-based lemmatization of counted words.
+
+use std::collections::HashMap;
+
+use crate::input::{ReadOptions, read_lines};
+
+/// Maps surface forms to their lemma, e.g. `ran` → `run`.
+///
+/// Words without an entry are their own lemma.
+#[derive(Debug, Clone, Default)]
+pub struct Lemmatizer {
+    lemmas: HashMap<String, String>,
+}
+
+impl Lemmatizer {
+    /// Loads a tab-separated `surface<TAB>lemma` file.
+    pub fn load(path: &str) -> Result<Lemmatizer, String> {
+        let lines = read_lines(path, &ReadOptions::default())?;
+        Lemmatizer::from_lines(&lines).map_err(|e| format!("{} in lemma file '{}'", e, path))
+    }
+
+    /// Parses `surface<TAB>lemma` lines.
+    ///
+    /// Both columns are lowercased to match the counted tokens. Blank
+    /// lines and lines starting with `#` are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::lemma::Lemmatizer;
+    ///
+    /// let lemmatizer = Lemmatizer::from_lines(&["Ran\trun".to_string()]).unwrap();
+    /// assert_eq!(lemmatizer.lemma("ran"), "run");
+    /// assert_eq!(lemmatizer.lemma("walked"), "walked");
+    /// ```
+    pub fn from_lines(lines: &[String]) -> Result<Lemmatizer, String> {
+        let mut lemmas = HashMap::new();
+        for (number, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = line
+                .split_once('\t')
+                .map(|(surface, lemma)| (surface.trim(), lemma.trim()))
+                .filter(|(surface, lemma)| !surface.is_empty() && !lemma.is_empty());
+            let Some((surface, lemma)) = entry else {
+                return Err(format!(
+                    "expected 'surface<TAB>lemma' on line {}, got '{}'",
+                    number + 1,
+                    line
+                ));
+            };
+            lemmas.insert(surface.to_lowercase(), lemma.to_lowercase());
+        }
+        Ok(Lemmatizer { lemmas })
+    }
+
+    /// Returns the lemma of a lowercased word, or the word itself.
+    pub fn lemma<'a>(&'a self, word: &'a str) -> &'a str {
+        self.lemmas.get(word).map_or(word, String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_words, regroup, select_top_k};
+
+    /// Test that surface forms aggregate under their lemma
+    #[test]
+    fn test_lemmas_aggregate_counts() {
+        let lexicon = ["# verbs", "ran\trun", "running\trun", "", "failed\tfail"];
+        let lemmatizer = Lemmatizer::from_lines(&lexicon.map(String::from)).unwrap();
+        let logs = vec![
+            "job ran".to_string(),
+            "job running".to_string(),
+            "Run failed, run again".to_string(),
+        ];
+
+        let counts = regroup(count_words(&logs), |word| {
+            Some(lemmatizer.lemma(word).to_string())
+        });
+
+        assert_eq!(
+            select_top_k(counts, 3),
+            vec![
+                ("run".to_string(), 4),
+                ("job".to_string(), 2),
+                ("again".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test that a line without a tab is reported with its number
+    #[test]
+    fn test_malformed_entry() {
+        let error =
+            Lemmatizer::from_lines(&["ran\trun".to_string(), "ran run".to_string()]).unwrap_err();
+
+        assert_eq!(
+            error,
+            "expected 'surface<TAB>lemma' on line 2, got 'ran run'"
+        );
+    }
+}
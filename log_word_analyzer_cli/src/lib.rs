@@ -0,0 +1,1413 @@
+//! Core word-frequency analysis for log files.
+//!
+//! The library exposes the counting functions used by the
+//! `log_word_analyzer_cli` binary so they can be embedded in other tools.
+
+use std::cmp::Ordering;
+use std::ops::ControlFlow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::filter::{DropReason, FilterStats, TokenFilter};
+use crate::tokenize::Tokenizer;
+
+pub mod baseline;
+pub mod blocks;
+pub mod bucket;
+pub mod cli;
+pub mod compare;
+pub mod convergence;
+pub mod cooccur;
+pub mod counts;
+pub mod extract;
+pub mod filter;
+pub mod fuzzy;
+pub mod histogram;
+pub mod hll;
+pub mod input;
+pub mod lemma;
+pub mod line_length;
+pub mod logfmt;
+pub mod named_groups;
+pub mod output;
+pub mod parallel;
+pub mod path_tree;
+pub mod positions;
+pub mod preprocess;
+pub mod repl;
+pub mod runs;
+pub mod severity;
+pub mod shape;
+pub mod state;
+pub mod summary;
+pub mod tfidf;
+pub mod timestamp;
+pub mod tokenize;
+pub mod validate;
+pub mod weight;
+
+mod serialize;
+
+#[cfg(test)]
+pub(crate) mod test_util;
+
+/// Finds the top K most frequently occurring words in a list of log lines.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `k` - The number of top frequent words to return
+///
+/// # Returns
+///
+/// A vector of tuples containing the word and its frequency count,
+/// sorted by frequency (descending) and alphabetically for ties.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_words;
+///
+/// let logs = vec!["Error: disk full".to_string(), "error: network down".to_string()];
+/// let result = top_k_words(&logs, 2);
+/// assert_eq!(result, vec![("error".to_string(), 2), ("disk".to_string(), 1)]);
+/// ```
+pub fn top_k_words(logs: &[String], k: usize) -> Vec<(String, usize)> {
+    select_top_k(count_words(logs), k)
+}
+
+/// Counts how often each word occurs across all log lines.
+///
+/// Words are lowercased and split on non-alphanumeric characters, exactly
+/// as [`top_k_words`] does before ranking.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+///
+/// # Returns
+///
+/// A map from each distinct word to its number of occurrences.
+pub fn count_words(logs: &[String]) -> HashMap<String, usize> {
+    count_lines(logs.iter().map(String::as_str))
+}
+
+/// Finds the top K words in borrowed log lines.
+///
+/// Behaves exactly like [`top_k_words`] but accepts string slices, so
+/// callers holding `&str` data do not need to allocate a `Vec<String>`.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_words_str;
+///
+/// let result = top_k_words_str(&["Error: disk full", "error: network down"], 1);
+/// assert_eq!(result, vec![("error".to_string(), 2)]);
+/// ```
+pub fn top_k_words_str(logs: &[&str], k: usize) -> Vec<(String, usize)> {
+    select_top_k(count_lines(logs.iter().copied()), k)
+}
+
+/// Counts word occurrences using a caller-supplied tokenizer.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `tokenizer` - Splits each line into the tokens to count
+///
+/// # Returns
+///
+/// A map from each distinct token to its number of occurrences.
+pub fn count_words_with(logs: &[String], tokenizer: &dyn Tokenizer) -> HashMap<String, usize> {
+    let mut frequency_map: HashMap<String, usize> = HashMap::new();
+    for line in logs {
+        for token in tokenizer.tokenize(line) {
+            *frequency_map.entry(token).or_insert(0) += 1;
+        }
+    }
+    frequency_map
+}
+
+/// Finds the top K tokens produced by a caller-supplied tokenizer.
+///
+/// Ranking is the same as in [`top_k_words`].
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_words_with;
+/// use log_word_analyzer_cli::tokenize::RegexTokenizer;
+/// use regex::Regex;
+///
+/// let tokenizer = RegexTokenizer::new(Regex::new(r"\bE\d+\b").unwrap());
+/// let logs = vec!["E42 disk full".to_string(), "retry after E42".to_string()];
+/// assert_eq!(top_k_words_with(&logs, 1, &tokenizer), vec![("e42".to_string(), 2)]);
+/// ```
+pub fn top_k_words_with(logs: &[String], k: usize, tokenizer: &dyn Tokenizer) -> Vec<(String, usize)> {
+    select_top_k(count_words_with(logs, tokenizer), k)
+}
+
+/// Counts the words of any sequence of borrowed lines.
+fn count_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> HashMap<String, usize> {
+    // HashMap to store word frequency counts
+    let mut frequency_map: HashMap<String, usize> = HashMap::new();
+    
+    // Process each log line
+    for line in lines {
+        count_line(&mut frequency_map, line);
+    }
+    
+    frequency_map
+}
+
+/// Amounts a [`WordCounter`] can accumulate per word: plain occurrence
+/// counts or weights.
+///
+/// Implemented for `usize`, the default, `u32` and `u64` as well as `f64`
+/// for fractional weights.
+pub trait Count: Copy + Debug + PartialEq {
+    /// The amount one unweighted occurrence adds
+    const ONE: Self;
+
+    /// The amount `occurrences` unweighted occurrences add, saturating
+    /// for integer counts.
+    fn from_occurrences(occurrences: usize) -> Self;
+
+    /// Adds two amounts; integer counts saturate instead of overflowing.
+    fn add(self, other: Self) -> Self;
+
+    /// Orders two amounts, greater meaning more frequent; a total order
+    /// even for floats.
+    fn compare(&self, other: &Self) -> Ordering;
+}
+
+/// Implements [`Count`] for unsigned integer types.
+macro_rules! integer_count {
+    ($($int:ty),*) => {
+        $(
+            impl Count for $int {
+                const ONE: Self = 1;
+
+                fn from_occurrences(occurrences: usize) -> Self {
+                    Self::try_from(occurrences).unwrap_or(Self::MAX)
+                }
+
+                fn add(self, other: Self) -> Self {
+                    self.saturating_add(other)
+                }
+
+                fn compare(&self, other: &Self) -> Ordering {
+                    self.cmp(other)
+                }
+            }
+        )*
+    };
+}
+
+integer_count!(u32, u64, usize);
+
+impl Count for f64 {
+    const ONE: Self = 1.0;
+
+    fn from_occurrences(occurrences: usize) -> Self {
+        occurrences as f64
+    }
+
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+}
+
+/// Accumulates word frequencies line by line, applying a token filter.
+///
+/// The counter also records what the filter dropped, so callers can
+/// explain how the final counts came about. Counts are `usize` unless
+/// another [`Count`] type is chosen with [`WordCounter::with_count_type`],
+/// e.g. `f64` to sum weights.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::WordCounter;
+/// use log_word_analyzer_cli::filter::TokenFilter;
+///
+/// let mut filter = TokenFilter::default();
+/// filter.add_stop_words(["the"]);
+///
+/// let mut counter = WordCounter::new(&filter);
+/// counter.add_line("The disk is full");
+/// let (counts, stats) = counter.into_parts();
+///
+/// assert_eq!(counts.get("disk"), Some(&1));
+/// assert_eq!(counts.get("the"), None);
+/// assert_eq!(stats.tokens_counted, 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WordCounter<'f, C: Count = usize> {
+    filter: &'f TokenFilter,
+    counts: HashMap<String, C>,
+    stats: FilterStats,
+    /// Map size at which the next `prune_threshold` pass runs
+    prune_at: usize,
+    /// Words counted for the current record under `once_per_record`
+    seen_in_record: HashSet<String>,
+}
+
+/// Fewest distinct words a [`WordCounter`] holds before a
+/// `prune_threshold` pass; after a pass the next one waits until the map
+/// has doubled.
+pub const PRUNE_MIN_WORDS: usize = 1024;
+
+impl<'f> WordCounter<'f> {
+    /// Creates an empty counter using the given filter.
+    pub fn new(filter: &'f TokenFilter) -> Self {
+        WordCounter::with_count_type(filter)
+    }
+
+    /// Folds the counts and statistics of another counter into this one.
+    ///
+    /// The unique-word limit also applies to the merge, so the merged map
+    /// never grows beyond it.
+    pub fn merge(&mut self, other: WordCounter<'f>) {
+        self.stats.merge(other.stats);
+        for (word, count) in other.counts {
+            if let Some(existing) = self.counts.get_mut(&word) {
+                *existing = existing.add(count);
+            } else {
+                add_bounded(&mut self.counts, &mut self.stats, self.filter.max_unique, &word, count, count);
+            }
+        }
+        self.prune_if_full();
+    }
+}
+
+impl<'f, C: Count> WordCounter<'f, C> {
+    /// Creates an empty counter accumulating amounts of type `C`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::WordCounter;
+    /// use log_word_analyzer_cli::filter::TokenFilter;
+    ///
+    /// let filter = TokenFilter::default();
+    /// let mut counter: WordCounter<f64> = WordCounter::with_count_type(&filter);
+    /// counter.add_line_weighted("disk full", 0.5);
+    /// counter.add_line_weighted("disk ok", 2.0);
+    /// assert_eq!(counter.into_parts().0["disk"], 2.5);
+    /// ```
+    pub fn with_count_type(filter: &'f TokenFilter) -> Self {
+        WordCounter {
+            filter,
+            counts: HashMap::new(),
+            stats: FilterStats::for_filter(filter),
+            prune_at: PRUNE_MIN_WORDS,
+            seen_in_record: HashSet::new(),
+        }
+    }
+
+    /// Tokenizes one log line and counts every word the filter keeps.
+    ///
+    /// With a `max_tokens_per_line` limit in the filter, the rest of a
+    /// longer line is not tokenized.
+    pub fn add_line(&mut self, line: &str) {
+        self.add_line_weighted(line, C::ONE);
+    }
+
+    /// Like [`WordCounter::add_line`], but every word of the line adds
+    /// `weight` instead of one.
+    pub fn add_line_weighted(&mut self, line: &str, weight: C) {
+        let WordCounter {
+            filter,
+            counts,
+            stats,
+            seen_in_record,
+            ..
+        } = self;
+        seen_in_record.clear();
+        for_each_word_capped(line, filter.max_tokens_per_line, |word| {
+            if filter.admit(word, stats) && first_in_record(filter, seen_in_record, stats, word) {
+                add_bounded(counts, stats, filter.max_unique, word, weight, 1);
+            }
+        });
+        self.prune_if_full();
+    }
+
+    /// Counts the tokens a custom tokenizer finds in one log line.
+    pub fn add_line_with(&mut self, line: &str, tokenizer: &dyn Tokenizer) {
+        let limit = self.filter.max_tokens_per_line.unwrap_or(usize::MAX);
+        self.seen_in_record.clear();
+        for token in tokenizer.tokenize(line).into_iter().take(limit) {
+            if self.filter.admit(&token, &mut self.stats)
+                && first_in_record(self.filter, &mut self.seen_in_record, &mut self.stats, &token)
+            {
+                add_bounded(&mut self.counts, &mut self.stats, self.filter.max_unique, &token, C::ONE, 1);
+            }
+        }
+        self.prune_if_full();
+    }
+
+    /// Counts every line of a slice.
+    pub fn add_lines(&mut self, logs: &[String]) {
+        for line in logs {
+            self.add_line(line);
+        }
+    }
+
+    /// Consumes the counter, returning the frequency map and statistics.
+    pub fn into_parts(self) -> (HashMap<String, C>, FilterStats) {
+        (self.counts, self.stats)
+    }
+
+    /// Drops the words counted fewer than the filter's `prune_threshold`
+    /// times once the map has grown to the next pruning size.
+    fn prune_if_full(&mut self) {
+        let Some(threshold) = self.filter.prune_threshold else {
+            return;
+        };
+        if self.counts.len() < self.prune_at {
+            return;
+        }
+        let threshold = C::from_occurrences(threshold);
+        let before = self.counts.len();
+        self.counts
+            .retain(|_, count| count.compare(&threshold) != Ordering::Less);
+        self.stats.prune_passes += 1;
+        self.stats.words_pruned += before - self.counts.len();
+        self.prune_at = PRUNE_MIN_WORDS.max(self.counts.len() * 2);
+    }
+}
+
+/// Returns false for a word already counted in the current record under
+/// `once_per_record`, moving it from the counted total to the drop
+/// statistics like [`add_bounded`] does.
+fn first_in_record(
+    filter: &TokenFilter,
+    seen: &mut HashSet<String>,
+    stats: &mut FilterStats,
+    word: &str,
+) -> bool {
+    if !filter.once_per_record {
+        return true;
+    }
+    if !seen.contains(word) {
+        seen.insert(word.to_string());
+        return true;
+    }
+    stats.tokens_counted -= 1;
+    *stats.tokens_dropped.entry(DropReason::RepeatedInRecord).or_insert(0) += 1;
+    false
+}
+
+/// Adds `amount` for `occurrences` occurrences of `word`, refusing new
+/// words beyond `limit`.
+///
+/// Refused occurrences are moved from the counted total to the
+/// `--max-unique` drop statistics.
+pub(crate) fn add_bounded<C: Count>(
+    counts: &mut HashMap<String, C>,
+    stats: &mut FilterStats,
+    limit: Option<usize>,
+    word: &str,
+    amount: C,
+    occurrences: usize,
+) {
+    if let Some(existing) = counts.get_mut(word) {
+        *existing = existing.add(amount);
+    } else if limit.is_none_or(|limit| counts.len() < limit) {
+        counts.insert(word.to_string(), amount);
+    } else {
+        stats.tokens_counted -= occurrences;
+        *stats.tokens_dropped.entry(DropReason::UniqueLimit).or_insert(0) += occurrences;
+    }
+}
+
+/// Adds the words of a single log line to a frequency map.
+pub(crate) fn count_line(frequency_map: &mut HashMap<String, usize>, line: &str) {
+    for_each_word(line, |word| {
+        // Increment count for existing word or insert new word with count 1;
+        // looking up first avoids allocating a key for words already seen
+        if let Some(count) = frequency_map.get_mut(word) {
+            *count += 1;
+        } else {
+            frequency_map.insert(word.to_string(), 1);
+        }
+    });
+}
+
+/// Calls `f` with every lowercased word of a log line, in order.
+///
+/// This is the tokenization behind every counting function. Pure-ASCII
+/// lines take a byte-level fast path; other lines are lowercased with
+/// full Unicode rules. Both paths yield the same words for ASCII input.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::for_each_word;
+///
+/// let mut words = Vec::new();
+/// for_each_word("ERROR: disk-full", |word| words.push(word.to_string()));
+/// assert_eq!(words, ["error", "disk", "full"]);
+/// ```
+pub fn for_each_word(line: &str, mut f: impl FnMut(&str)) {
+    let _ = try_for_each_word(line, |word| {
+        f(word);
+        ControlFlow::Continue(())
+    });
+}
+
+/// Like [`for_each_word`], but stops tokenizing after the first `limit`
+/// words, so a pathological line with millions of tokens costs no more
+/// than its first `limit`. `None` takes every word.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::for_each_word_capped;
+///
+/// let mut words = Vec::new();
+/// for_each_word_capped("error disk full", Some(2), |word| words.push(word.to_string()));
+/// assert_eq!(words, ["error", "disk"]);
+/// ```
+pub fn for_each_word_capped(line: &str, limit: Option<usize>, mut f: impl FnMut(&str)) {
+    let Some(mut remaining) = limit else {
+        for_each_word(line, f);
+        return;
+    };
+    let _ = try_for_each_word(line, |word| {
+        if remaining == 0 {
+            return ControlFlow::Break(());
+        }
+        remaining -= 1;
+        f(word);
+        ControlFlow::Continue(())
+    });
+}
+
+/// Calls `f` with every word of a log line until it breaks.
+fn try_for_each_word(line: &str, mut f: impl FnMut(&str) -> ControlFlow<()>) -> ControlFlow<()> {
+    if line.is_ascii() {
+        return for_each_ascii_word(line, f);
+    }
+
+    // Convert to lowercase for case-insensitive comparison
+    let lower_line = line.to_lowercase();
+
+    // Split line into words using non-alphanumeric characters as delimiters
+    for word in lower_line.split(|c: char| !c.is_ascii_alphanumeric()) {
+        // Skip empty strings that may result from splitting
+        if word.is_empty() {
+            continue;
+        }
+        f(word)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// Lookup table of the bytes that belong to a word.
+static WORD_BYTE: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        table[byte] = (byte as u8).is_ascii_alphanumeric();
+        byte += 1;
+    }
+    table
+};
+
+/// Fast path of [`for_each_word`] for lines made only of ASCII bytes.
+///
+/// Lowercasing is a plain byte-wise pass, and words are found in a single
+/// table-driven scan and sliced out by byte index instead of decoding chars.
+fn for_each_ascii_word(line: &str, mut f: impl FnMut(&str) -> ControlFlow<()>) -> ControlFlow<()> {
+    let lower_line = line.to_ascii_lowercase();
+    let mut start = None;
+    for (index, &byte) in lower_line.as_bytes().iter().enumerate() {
+        if WORD_BYTE[byte as usize] {
+            start.get_or_insert(index);
+        } else if let Some(word_start) = start.take() {
+            // Every byte is ASCII, so any index is a char boundary
+            f(&lower_line[word_start..index])?;
+        }
+    }
+    match start {
+        Some(word_start) => f(&lower_line[word_start..]),
+        None => ControlFlow::Continue(()),
+    }
+}
+
+/// How words of equal frequency are ordered, selectable with `--tie-break`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// Alphabetically
+    #[default]
+    Alpha,
+    /// Longest word first, then alphabetically, so more specific terms
+    /// rank above short ones
+    LengthThenAlpha,
+}
+
+impl TieBreak {
+    /// Orders two words of equal frequency.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            TieBreak::Alpha => a.cmp(b),
+            TieBreak::LengthThenAlpha => b
+                .chars()
+                .count()
+                .cmp(&a.chars().count())
+                .then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "alpha" => Ok(TieBreak::Alpha),
+            "length" => Ok(TieBreak::LengthThenAlpha),
+            _ => Err(format!(
+                "unknown tie-break '{}' (expected alpha or length)",
+                name
+            )),
+        }
+    }
+}
+
+/// Ranks a frequency map and keeps the K most frequent words.
+///
+/// # Arguments
+///
+/// * `frequency_map` - Word counts, e.g. as produced by [`count_words`]
+/// * `k` - The number of top frequent words to return; any `k`, up to
+///   `usize::MAX`, is valid and nothing is ever allocated based on it
+///
+/// # Returns
+///
+/// The top K words sorted by frequency (descending) and alphabetically
+/// for ties.
+pub fn select_top_k(frequency_map: HashMap<String, usize>, k: usize) -> Vec<(String, usize)> {
+    select_top_k_by(frequency_map, k, TieBreak::Alpha)
+}
+
+/// Like [`select_top_k`], but orders words of equal frequency by `tie_break`.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::{count_words, select_top_k_by, TieBreak};
+///
+/// let counts = count_words(&["disk timeout".to_string()]);
+/// assert_eq!(
+///     select_top_k_by(counts, 1, TieBreak::LengthThenAlpha),
+///     vec![("timeout".to_string(), 1)]
+/// );
+/// ```
+pub fn select_top_k_by(
+    frequency_map: HashMap<String, usize>,
+    k: usize,
+    tie_break: TieBreak,
+) -> Vec<(String, usize)> {
+    select_top_k_counts(frequency_map, k, tie_break)
+}
+
+/// Like [`select_top_k_by`], for a frequency map of any [`Count`] type.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use log_word_analyzer_cli::{select_top_k_counts, TieBreak};
+///
+/// let scores = HashMap::from([("disk".to_string(), 0.5), ("error".to_string(), 2.25)]);
+/// assert_eq!(
+///     select_top_k_counts(scores, 1, TieBreak::Alpha),
+///     vec![("error".to_string(), 2.25)]
+/// );
+/// ```
+pub fn select_top_k_counts<C: Count>(
+    frequency_map: HashMap<String, C>,
+    k: usize,
+    tie_break: TieBreak,
+) -> Vec<(String, C)> {
+    // Nothing is kept, so there is nothing to sort
+    if k == 0 {
+        return Vec::new();
+    }
+
+    // Convert HashMap to vector of tuples for sorting
+    let mut word_counts: Vec<(String, C)> = frequency_map.into_iter().collect();
+    
+    // Sort by frequency descending, then alphabetically ascending for ties
+    word_counts.sort_by(|a, b| {
+        // Primary sort: frequency descending
+        b.1.compare(&a.1)
+            // Secondary sort: the tie-break for words with same frequency
+            .then_with(|| tie_break.compare(&a.0, &b.0))
+    });
+    
+    // Keep only the top K words
+    word_counts.truncate(k);
+    
+    word_counts
+}
+
+/// Ranked `(word, count)` rows, most relevant first.
+pub type Ranking = Vec<(String, usize)>;
+
+/// Selects both ends of a frequency map: the K most frequent words and
+/// the K least frequent ones.
+///
+/// The top words are ordered as by [`select_top_k_by`], the bottom words
+/// rarest first with `tie_break` among equal counts. With fewer than `2k`
+/// distinct words a word can appear in both lists.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::{count_words, select_top_and_bottom_k, TieBreak};
+///
+/// let counts = count_words(&["error error error disk disk timeout".to_string()]);
+/// let (top, bottom) = select_top_and_bottom_k(counts, 1, TieBreak::Alpha);
+/// assert_eq!(top, vec![("error".to_string(), 3)]);
+/// assert_eq!(bottom, vec![("timeout".to_string(), 1)]);
+/// ```
+pub fn select_top_and_bottom_k(
+    frequency_map: HashMap<String, usize>,
+    k: usize,
+    tie_break: TieBreak,
+) -> (Ranking, Ranking) {
+    let mut word_counts: Vec<(String, usize)> = frequency_map.into_iter().collect();
+
+    // Rarest first for the bottom list
+    word_counts.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| tie_break.compare(&a.0, &b.0)));
+    let bottom = word_counts.iter().take(k).cloned().collect();
+
+    // Then the usual ranking for the top list
+    word_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| tie_break.compare(&a.0, &b.0)));
+    word_counts.truncate(k);
+
+    (word_counts, bottom)
+}
+
+/// Lists the distinct words of the log lines in order of first appearance.
+pub fn discovery_order(logs: &[String]) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut order = Vec::new();
+    for line in logs {
+        for_each_word(line, |word| {
+            if !seen.contains(word) {
+                seen.insert(word.to_string());
+                order.push(word.to_string());
+            }
+        });
+    }
+    order
+}
+
+/// Keeps the first K words of a frequency map in a given word order.
+///
+/// Unlike [`select_top_k`], counts play no part in the selection. Words
+/// of `order` missing from the map (for example because a filter dropped
+/// them) are skipped, and repeated entries are used only once.
+///
+/// # Arguments
+///
+/// * `frequency_map` - Word counts, e.g. as produced by [`count_words`]
+/// * `order` - Words in the order they should be reported
+/// * `k` - The number of words to return
+pub fn select_in_order(
+    mut frequency_map: HashMap<String, usize>,
+    order: impl IntoIterator<Item = String>,
+    k: usize,
+) -> Vec<(String, usize)> {
+    order
+        .into_iter()
+        .filter_map(|word| frequency_map.remove_entry(&word))
+        .take(k)
+        .collect()
+}
+
+/// Finds the first K distinct words in order of appearance, with totals.
+///
+/// This is the unsorted view behind `--sort none`, useful for debugging
+/// tokenization: frequency is ignored entirely.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::first_k_words;
+///
+/// let logs = vec!["disk error".to_string(), "error error timeout".to_string()];
+/// let result = first_k_words(&logs, 2);
+/// assert_eq!(result, vec![("disk".to_string(), 1), ("error".to_string(), 3)]);
+/// ```
+pub fn first_k_words(logs: &[String], k: usize) -> Vec<(String, usize)> {
+    select_in_order(count_words(logs), discovery_order(logs), k)
+}
+
+/// Finds the K longest (or shortest) distinct words with their counts.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `k` - The number of words to return
+/// * `longest` - Rank the longest words first when true, the shortest otherwise
+///
+/// # Returns
+///
+/// `(word, count)` tuples ordered by character length and alphabetically
+/// for words of equal length.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_by_length;
+///
+/// let logs = vec!["disk full: filesystem error".to_string()];
+/// assert_eq!(top_k_by_length(&logs, 1, true), vec![("filesystem".to_string(), 1)]);
+/// assert_eq!(top_k_by_length(&logs, 1, false), vec![("disk".to_string(), 1)]);
+/// ```
+pub fn top_k_by_length(logs: &[String], k: usize, longest: bool) -> Vec<(String, usize)> {
+    let mut words: Vec<(String, usize)> = count_words(logs).into_iter().collect();
+
+    // Sort by length in the requested direction, then alphabetically
+    words.sort_by(|a, b| {
+        let by_length = a.0.chars().count().cmp(&b.0.chars().count());
+        let by_length = if longest { by_length.reverse() } else { by_length };
+        by_length.then_with(|| a.0.cmp(&b.0))
+    });
+
+    words.truncate(k);
+    words
+}
+
+/// Finds the top K words together with a sample line for each: the first
+/// log line the word occurs on, to show it in context.
+///
+/// Ranking is the same as in [`top_k_words`]. Only the index of each
+/// word's first line is kept while counting; the selected lines are
+/// copied at the end.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_with_samples;
+///
+/// let logs = vec!["disk full on /dev/sda".to_string(), "Error: disk gone".to_string()];
+/// let result = top_k_with_samples(&logs, 1);
+/// assert_eq!(result, vec![("disk".to_string(), 2, "disk full on /dev/sda".to_string())]);
+/// ```
+pub fn top_k_with_samples(logs: &[String], k: usize) -> Vec<(String, usize, String)> {
+    let mut frequency_map: HashMap<String, usize> = HashMap::new();
+    let mut first_lines: HashMap<String, usize> = HashMap::new();
+    for (index, line) in logs.iter().enumerate() {
+        for_each_word(line, |word| {
+            if let Some(count) = frequency_map.get_mut(word) {
+                *count += 1;
+            } else {
+                frequency_map.insert(word.to_string(), 1);
+                first_lines.insert(word.to_string(), index);
+            }
+        });
+    }
+
+    select_top_k(frequency_map, k)
+        .into_iter()
+        .map(|(word, count)| {
+            let sample = logs[first_lines[&word]].clone();
+            (word, count, sample)
+        })
+        .collect()
+}
+
+/// Finds the top K words, each with its 1-based rank.
+///
+/// Ranking is the same as in [`top_k_words`], but tied words share a
+/// rank and the next count skips as many ranks as words tied
+/// (competition ranking, `1224`), so a rank is not the same as a row
+/// number.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::top_k_ranked;
+///
+/// let logs = vec!["error disk error timeout".to_string()];
+/// let result = top_k_ranked(&logs, 3);
+/// assert_eq!(
+///     result,
+///     vec![
+///         (1, "error".to_string(), 2),
+///         (2, "disk".to_string(), 1),
+///         (2, "timeout".to_string(), 1),
+///     ]
+/// );
+/// ```
+pub fn top_k_ranked(logs: &[String], k: usize) -> Vec<(usize, String, usize)> {
+    let mut rank = 0;
+    let mut previous = None;
+    top_k_words(logs, k)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (word, count))| {
+            if previous != Some(count) {
+                rank = index + 1;
+                previous = Some(count);
+            }
+            (rank, word, count)
+        })
+        .collect()
+}
+
+/// Finds the K most frequent words that occur in every one of several
+/// files, such as boilerplate shared by the logs of all services.
+///
+/// # Arguments
+///
+/// * `files` - The log lines of each file
+/// * `k` - The number of words to return
+///
+/// # Returns
+///
+/// Words present in every file with their counts summed over all files,
+/// ranked like [`select_top_k`]. No files means no common words.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::common_words;
+///
+/// let files = vec![
+///     vec!["INFO started disk check".to_string()],
+///     vec!["INFO started network".to_string(), "info done".to_string()],
+/// ];
+/// assert_eq!(common_words(&files, 5), vec![("info".to_string(), 3), ("started".to_string(), 2)]);
+/// ```
+pub fn common_words(files: &[Vec<String>], k: usize) -> Vec<(String, usize)> {
+    let mut counts = files.iter().map(|logs| count_words(logs));
+    let Some(mut common) = counts.next() else {
+        return Vec::new();
+    };
+    for file_counts in counts {
+        common.retain(|word, _| file_counts.contains_key(word));
+        for (word, total) in common.iter_mut() {
+            *total += file_counts[word];
+        }
+    }
+    select_top_k(common, k)
+}
+
+/// Re-keys a frequency map, merging the counts of words with the same key.
+///
+/// This is how token-level transforms such as numeric bucketing are
+/// applied after counting: every word is mapped through `key`, words
+/// mapping to `None` are dropped, and counts of equal keys are summed.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::{count_words, regroup};
+///
+/// let counts = count_words(&["Error errors".to_string()]);
+/// let stems = regroup(counts, |word| Some(word.trim_end_matches('s').to_string()));
+/// assert_eq!(stems.get("error"), Some(&2));
+/// ```
+pub fn regroup(
+    frequency_map: HashMap<String, usize>,
+    key: impl Fn(&str) -> Option<String>,
+) -> HashMap<String, usize> {
+    let mut grouped: HashMap<String, usize> = HashMap::new();
+    for (word, count) in frequency_map {
+        if let Some(group) = key(&word) {
+            *grouped.entry(group).or_insert(0) += count;
+        }
+    }
+    grouped
+}
+
+/// Computes the share of all tokens covered by a result list.
+///
+/// # Arguments
+///
+/// * `results` - Ranked word counts, typically a top-K selection
+/// * `total_tokens` - The total number of tokens the counts were taken from
+///
+/// # Returns
+///
+/// The covered percentage in the range `0.0..=100.0`, or `0.0` when there
+/// were no tokens at all.
+pub fn coverage_percent(results: &[(String, usize)], total_tokens: usize) -> f64 {
+    if total_tokens == 0 {
+        return 0.0;
+    }
+    let covered: usize = results.iter().map(|(_, count)| count).sum();
+    covered as f64 * 100.0 / total_tokens as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test basic functionality with case insensitivity
+    #[test]
+    fn test_basic_functionality() {
+        let logs = vec![
+            "Error: Disk full".to_string(),
+            "error: network down".to_string(),
+            "ERROR: disk error".to_string(), 
+        ];
+        
+        let result = top_k_words(&logs, 2);
+        
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], ("error".to_string(), 4)); 
+        assert_eq!(result[1], ("disk".to_string(), 2));
+    }
+
+    /// Test sorting order: frequency descending, then alphabetical
+    #[test]
+    fn test_sorting_order() {
+        let logs = vec![
+            "apple banana apple".to_string(),
+            "banana cherry".to_string(),
+            "apple cherry date".to_string(),
+            "date egg".to_string(),
+        ];
+        
+        let result = top_k_words(&logs, 4);
+        
+        // Expected order: apple(3), banana(2), cherry(2), date(2)
+        assert_eq!(result[0], ("apple".to_string(), 3));
+        assert_eq!(result[1], ("banana".to_string(), 2));
+        assert_eq!(result[2], ("cherry".to_string(), 2));
+        assert_eq!(result[3], ("date".to_string(), 2));
+    }
+
+    /// Test that the length tie-break ranks the longer of two tied words first
+    #[test]
+    fn test_tie_break_length_then_alpha() {
+        let logs = vec!["disk timeout error".to_string(), "disk timeout".to_string()];
+        let counts = count_words(&logs);
+
+        let result = select_top_k_by(counts.clone(), 3, TieBreak::LengthThenAlpha);
+        assert_eq!(
+            result,
+            vec![
+                ("timeout".to_string(), 2),
+                ("disk".to_string(), 2),
+                ("error".to_string(), 1),
+            ]
+        );
+        assert_eq!(select_top_k(counts, 1), vec![("disk".to_string(), 2)]);
+    }
+
+    /// Test with alphanumeric words and special characters
+    #[test]
+    fn test_alphanumeric_words() {
+        let logs = vec![
+            "Error123 test 123".to_string(),  
+            "error123 test test".to_string(), 
+            "test123 456".to_string(),        
+        ];
+        
+        let result = top_k_words(&logs, 3);
+        
+        
+        assert_eq!(result[0], ("test".to_string(), 3));
+        assert_eq!(result[1], ("error123".to_string(), 2));
+        assert_eq!(result[2], ("123".to_string(), 1)); 
+    }
+
+    /// Test empty input
+    #[test]
+    fn test_empty_input() {
+        let logs: Vec<String> = vec![];
+        let result = top_k_words(&logs, 5);
+        assert_eq!(result.len(), 0);
+    }
+
+    /// Test k larger than number of unique words
+    #[test]
+    fn test_k_larger_than_unique_words() {
+        let logs = vec![
+            "word1 word2".to_string(),
+            "word1 word3".to_string(),
+        ];
+        
+        let result = top_k_words(&logs, 10);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], ("word1".to_string(), 2));
+    }
+
+    /// Test that k = usize::MAX returns the whole vocabulary
+    #[test]
+    fn test_k_usize_max() {
+        let logs = vec!["word1 word2".to_string(), "word1 word3".to_string()];
+
+        assert_eq!(top_k_words(&logs, usize::MAX), top_k_words(&logs, 3));
+        assert_eq!(first_k_words(&logs, usize::MAX).len(), 3);
+    }
+
+    /// Test that both ends come from the same frequency map
+    #[test]
+    fn test_select_top_and_bottom_k() {
+        let logs = vec![
+            "error error error error disk disk disk".to_string(),
+            "timeout timeout retry boot".to_string(),
+        ];
+
+        let (top, bottom) = select_top_and_bottom_k(count_words(&logs), 2, TieBreak::Alpha);
+
+        assert_eq!(top, vec![("error".to_string(), 4), ("disk".to_string(), 3)]);
+        assert_eq!(bottom, vec![("boot".to_string(), 1), ("retry".to_string(), 1)]);
+    }
+
+    /// Test that a long line contributes only its first N tokens
+    #[test]
+    fn test_max_tokens_per_line() {
+        let mut long_line = vec!["error"; 3];
+        long_line.extend(["disk"; 1000]);
+        let logs = vec![long_line.join(" "), "disk timeout".to_string()];
+        let filter = TokenFilter {
+            max_tokens_per_line: Some(4),
+            ..TokenFilter::default()
+        };
+
+        let mut counter = WordCounter::new(&filter);
+        counter.add_lines(&logs);
+        let (counts, stats) = counter.into_parts();
+
+        assert_eq!(counts["error"], 3);
+        assert_eq!(counts["disk"], 2);
+        assert_eq!(counts["timeout"], 1);
+        assert_eq!(stats.tokens_counted, 6);
+    }
+
+    /// Test k = 0
+    #[test]
+    fn test_k_zero() {
+        let logs = vec!["test".to_string()];
+        let result = top_k_words(&logs, 0);
+        assert_eq!(result.len(), 0);
+    }
+
+    /// A count that fails the test as soon as it is sorted.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Unsortable(usize);
+
+    impl Count for Unsortable {
+        const ONE: Self = Unsortable(1);
+
+        fn from_occurrences(occurrences: usize) -> Self {
+            Unsortable(occurrences)
+        }
+
+        fn add(self, other: Self) -> Self {
+            Unsortable(self.0 + other.0)
+        }
+
+        fn compare(&self, _: &Self) -> Ordering {
+            panic!("counts were sorted")
+        }
+    }
+
+    /// Test that a k of 0 returns before sorting anything
+    #[test]
+    fn test_k_zero_skips_sorting() {
+        let counts = HashMap::from([
+            ("error".to_string(), Unsortable(2)),
+            ("disk".to_string(), Unsortable(1)),
+        ]);
+
+        assert!(select_top_k_counts(counts, 0, TieBreak::Alpha).is_empty());
+    }
+
+    /// Test with punctuation and special characters
+    #[test]
+    fn test_punctuation_handling() {
+        let logs = vec![
+            "Error, disk; full!".to_string(),
+            "error: network-down".to_string(),
+            "error (disk) full?".to_string(),
+        ];
+        
+        let result = top_k_words(&logs, 3);
+        
+        assert_eq!(result[0], ("error".to_string(), 3));
+        assert_eq!(result[1], ("disk".to_string(), 2));
+        assert_eq!(result[2], ("full".to_string(), 2));
+    }
+
+    /// Test exact matching instead of contains
+    #[test]
+    fn test_exact_matching() {
+        let logs = vec![
+            "test test test".to_string(),
+            "hello world".to_string(),
+        ];
+        
+        let result = top_k_words(&logs, 2);
+        let expected = vec![("test".to_string(), 3), ("hello".to_string(), 1)];
+        assert_eq!(result, expected);
+    }
+
+    /// Test coverage of the full selection relative to all tokens
+    #[test]
+    fn test_coverage_percent() {
+        let logs = vec!["a a b c".to_string()];
+        let frequency_map = count_words(&logs);
+        let total: usize = frequency_map.values().sum();
+        let result = select_top_k(frequency_map, 2);
+
+        assert_eq!(total, 4);
+        assert_eq!(coverage_percent(&result, total), 75.0);
+        assert_eq!(coverage_percent(&[], 0), 0.0);
+    }
+
+    /// Test that the `&str` variant matches the `String` version
+    #[test]
+    fn test_top_k_words_str_matches_string_version() {
+        let borrowed = ["Error: Disk full", "error: network-down", "ERROR (disk) full?"];
+        let owned: Vec<String> = borrowed.iter().map(|line| line.to_string()).collect();
+
+        assert_eq!(top_k_words_str(&borrowed, 10), top_k_words(&owned, 10));
+        assert_eq!(top_k_words_str(&[], 3), vec![]);
+    }
+
+    /// Test that the ASCII fast path splits exactly like the Unicode path
+    #[test]
+    fn test_ascii_fast_path_matches_unicode_path() {
+        let unicode_words = |line: &str| -> Vec<String> {
+            line.to_lowercase()
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let lines = [
+            "ERROR: Disk FULL on /dev/sda1 (code=0x1F)",
+            "--leading and trailing separators--",
+            "Café Müller ÉCHEC connexion",
+            "mixed ascii DISK with İstanbul",
+            "",
+            "   ",
+            "a",
+            "x9Y8z7",
+        ];
+
+        for line in lines {
+            let mut words = Vec::new();
+            for_each_word(line, |word| words.push(word.to_string()));
+            assert_eq!(words, unicode_words(line), "line: {:?}", line);
+        }
+    }
+
+    /// Test that --max-unique bounds the map while frequent words keep counting
+    #[test]
+    fn test_max_unique_bounds_map() {
+        let filter = TokenFilter {
+            max_unique: Some(3),
+            ..TokenFilter::default()
+        };
+        let mut logs = vec!["error disk timeout".to_string()];
+        for i in 0..50 {
+            logs.push(format!("error rare{} disk", i));
+        }
+
+        let mut counter = WordCounter::new(&filter);
+        counter.add_lines(&logs);
+        let (counts, stats) = counter.into_parts();
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["error"], 51);
+        assert_eq!(counts["disk"], 51);
+        assert_eq!(counts["timeout"], 1);
+        assert_eq!(stats.tokens_dropped[&DropReason::UniqueLimit], 50);
+        assert_eq!(stats.tokens_counted, 103);
+    }
+
+    /// Test that tied words share a rank and the next count skips past them
+    #[test]
+    fn test_top_k_ranked_competition_ranking() {
+        let logs = vec![
+            "error error error".to_string(),
+            "disk timeout disk timeout".to_string(),
+            "retry".to_string(),
+        ];
+
+        let result = top_k_ranked(&logs, 4);
+
+        assert_eq!(
+            result,
+            vec![
+                (1, "error".to_string(), 3),
+                (2, "disk".to_string(), 2),
+                (2, "timeout".to_string(), 2),
+                (4, "retry".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test that each sample is the first line containing its word
+    #[test]
+    fn test_top_k_with_samples_first_occurrence() {
+        let logs = vec![
+            "INFO service started".to_string(),
+            "WARN disk at 91%".to_string(),
+            "ERROR disk full".to_string(),
+            "ERROR write failed: disk full".to_string(),
+        ];
+
+        let result = top_k_with_samples(&logs, 3);
+
+        let words: Vec<(&str, usize)> = result
+            .iter()
+            .map(|(word, count, _)| (word.as_str(), *count))
+            .collect();
+        assert_eq!(words, [("disk", 3), ("error", 2), ("full", 2)]);
+        for (word, _, sample) in &result {
+            let first = logs
+                .iter()
+                .find(|line| count_words(&[line.to_string()]).contains_key(word))
+                .unwrap();
+            assert!(sample.to_lowercase().contains(word.as_str()));
+            assert_eq!(sample, first);
+        }
+        assert_eq!(result[0].2, "WARN disk at 91%");
+        assert_eq!(result[1].2, "ERROR disk full");
+    }
+
+    /// Test that a word repeated within one multi-line record counts once
+    #[test]
+    fn test_once_per_record() {
+        let filter = TokenFilter {
+            once_per_record: true,
+            ..TokenFilter::default()
+        };
+        let records = vec![
+            "ERROR disk full\n  retry: error\n  retry: error\n  gave up: ERROR".to_string(),
+            "error timeout".to_string(),
+        ];
+
+        let mut counter = WordCounter::new(&filter);
+        counter.add_lines(&records);
+        let (counts, stats) = counter.into_parts();
+
+        assert_eq!(counts["error"], 2);
+        assert_eq!(counts["retry"], 1);
+        assert_eq!(stats.tokens_dropped[&DropReason::RepeatedInRecord], 4);
+        assert_eq!(count_words(&records)["error"], 5);
+    }
+
+    /// Test that pruning a skewed stream bounds the map but keeps the top K
+    #[test]
+    fn test_prune_threshold_keeps_heavy_hitters() {
+        let logs: Vec<String> = (0..10_000)
+            .map(|i| {
+                let mut line = format!("error id{}", i);
+                if i % 2 == 0 {
+                    line.push_str(" disk");
+                }
+                if i % 5 == 0 {
+                    line.push_str(" timeout");
+                }
+                line
+            })
+            .collect();
+        let filter = TokenFilter {
+            prune_threshold: Some(2),
+            ..TokenFilter::default()
+        };
+
+        let mut counter = WordCounter::new(&filter);
+        counter.add_lines(&logs);
+        let (counts, stats) = counter.into_parts();
+
+        assert!(stats.prune_passes > 0);
+        assert!(counts.len() < PRUNE_MIN_WORDS);
+        assert_eq!(
+            select_top_k(counts.clone(), 3),
+            select_top_k(count_words(&logs), 3)
+        );
+        // Heavy hitters were never below the threshold, so lost nothing
+        assert_eq!(counts["timeout"], 2000);
+        assert!(stats.prune_error_bound(2) >= 1);
+    }
+
+    /// Test that only words of all three files are reported, with summed counts
+    #[test]
+    fn test_common_words_three_files() {
+        let files = vec![
+            vec!["INFO request started".to_string(), "error disk".to_string()],
+            vec!["info request done".to_string(), "info retry".to_string()],
+            vec!["request info received".to_string(), "warning disk".to_string()],
+        ];
+
+        let result = common_words(&files, 10);
+
+        assert_eq!(
+            result,
+            vec![("info".to_string(), 4), ("request".to_string(), 3)]
+        );
+        assert_eq!(common_words(&[], 10), vec![]);
+    }
+
+    /// Test the counting core with u32 counts and f64 weights
+    #[test]
+    fn test_word_counter_count_types() {
+        let filter = TokenFilter::default();
+        let logs = vec!["error disk".to_string(), "error retry".to_string()];
+
+        let mut counter: WordCounter<u32> = WordCounter::with_count_type(&filter);
+        counter.add_lines(&logs);
+        let (counts, stats) = counter.into_parts();
+        assert_eq!(counts.get("error"), Some(&2u32));
+        assert_eq!(stats.tokens_counted, 4);
+
+        let mut counter: WordCounter<f64> = WordCounter::with_count_type(&filter);
+        counter.add_line_weighted("error disk", 0.25);
+        counter.add_line_weighted("disk retry", 1.5);
+        let (scores, _) = counter.into_parts();
+        assert_eq!(
+            select_top_k_counts(scores, 2, TieBreak::Alpha),
+            vec![("disk".to_string(), 1.75), ("retry".to_string(), 1.5)]
+        );
+
+        // Integer counts saturate instead of overflowing
+        assert_eq!(u32::MAX.add(1), u32::MAX);
+    }
+
+    /// Test longest-first and shortest-first ordering by word length
+    #[test]
+    fn test_top_k_by_length() {
+        let logs = vec![
+            "connection refused by upstream".to_string(),
+            "io ok ok by".to_string(),
+        ];
+
+        let longest = top_k_by_length(&logs, 2, true);
+        assert_eq!(
+            longest,
+            vec![("connection".to_string(), 1), ("upstream".to_string(), 1)]
+        );
+
+        let shortest = top_k_by_length(&logs, 3, false);
+        assert_eq!(
+            shortest,
+            vec![("by".to_string(), 2), ("io".to_string(), 1), ("ok".to_string(), 2)]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Distribution of line lengths, for capacity planning.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Running statistics of line lengths in characters.
+///
+/// Lengths are kept as a histogram, so memory grows with the number of
+/// distinct lengths rather than lines, and quantiles are exact.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::line_length::LineLengthStats;
+///
+/// let mut stats = LineLengthStats::default();
+/// stats.add_lines(&["ok".to_string(), "disk full".to_string()]);
+/// assert_eq!((stats.lines(), stats.max()), (2, 9));
+/// assert_eq!(stats.mean(), 5.5);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineLengthStats {
+    /// Number of lines of each length
+    lengths: BTreeMap<usize, usize>,
+    lines: usize,
+    total: usize,
+}
+
+impl LineLengthStats {
+    /// Records the length of one line.
+    pub fn add(&mut self, line: &str) {
+        let length = line.chars().count();
+        *self.lengths.entry(length).or_insert(0) += 1;
+        self.lines += 1;
+        self.total += length;
+    }
+
+    /// Records the lengths of every line of a slice.
+    pub fn add_lines(&mut self, logs: &[String]) {
+        for line in logs {
+            self.add(line);
+        }
+    }
+
+    /// Adds the lengths recorded by another run or file to these.
+    pub fn merge(&mut self, other: LineLengthStats) {
+        for (length, lines) in other.lengths {
+            *self.lengths.entry(length).or_insert(0) += lines;
+        }
+        self.lines += other.lines;
+        self.total += other.total;
+    }
+
+    /// Number of lines recorded.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Average line length; zero without lines.
+    pub fn mean(&self) -> f64 {
+        if self.lines == 0 {
+            return 0.0;
+        }
+        self.total as f64 / self.lines as f64
+    }
+
+    /// Longest line length; zero without lines.
+    pub fn max(&self) -> usize {
+        self.lengths.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Smallest length at or above `percent` % of the lines (nearest rank);
+    /// zero without lines.
+    pub fn percentile(&self, percent: usize) -> usize {
+        let rank = (percent * self.lines).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (length, lines) in &self.lengths {
+            seen += lines;
+            if seen >= rank {
+                return *length;
+            }
+        }
+        0
+    }
+}
+
+impl fmt::Display for LineLengthStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} lines, length mean {:.2}, median {}, p90 {}, p99 {}, max {} characters",
+            self.lines,
+            self.mean(),
+            self.percentile(50),
+            self.percentile(90),
+            self.percentile(99),
+            self.max()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test mean, max and percentiles on known line lengths
+    #[test]
+    fn test_known_line_lengths() {
+        // Lengths 1, 2, ..., 10, with one multi-byte line of 4 characters
+        let mut logs: Vec<String> = (1..=10).map(|length| "x".repeat(length)).collect();
+        logs.push("übel".to_string());
+        let mut stats = LineLengthStats::default();
+
+        stats.add_lines(&logs[..6]);
+        let mut rest = LineLengthStats::default();
+        rest.add_lines(&logs[6..]);
+        stats.merge(rest);
+
+        assert_eq!(stats.lines(), 11);
+        assert_eq!(stats.max(), 10);
+        assert_eq!(stats.mean(), 59.0 / 11.0);
+        assert_eq!(stats.percentile(50), 5);
+        assert_eq!(stats.percentile(90), 9);
+        assert_eq!(stats.percentile(100), 10);
+        assert_eq!(
+            LineLengthStats::default().to_string(),
+            "0 lines, length mean 0.00, median 0, p90 0, p99 0, max 0 characters"
+        );
+    }
+}
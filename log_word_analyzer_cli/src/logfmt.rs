@@ -0,0 +1,251 @@
+//! Counting the values of one key in logfmt lines such as
+//! `level=error msg="disk full" duration=12ms`, or the transitions
+//! between the values of two keys such as `from=idle to=running`.
+
+use std::collections::HashMap;
+
+use crate::filter::{DropReason, FilterStats, TokenFilter};
+use crate::select_top_k;
+
+/// Splits a logfmt line into its `key=value` pairs, in line order.
+///
+/// Quoted values are kept whole with `\"` and `\\` unescaped, so
+/// `msg="disk full"` yields `("msg", "disk full")`. A bare key without
+/// `=` has an empty value.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::logfmt::pairs;
+///
+/// let line = r#"level=error msg="disk \"sda\" full" retry"#;
+/// assert_eq!(
+///     pairs(line),
+///     vec![
+///         ("level".to_string(), "error".to_string()),
+///         ("msg".to_string(), "disk \"sda\" full".to_string()),
+///         ("retry".to_string(), String::new()),
+///     ]
+/// );
+/// ```
+pub fn pairs(line: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '=' && !c.is_whitespace()) {
+            key.push(c);
+        }
+        if key.is_empty() && chars.peek().is_none() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                // A quoted value runs to the next unescaped quote
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        if !key.is_empty() {
+            pairs.push((key, value));
+        }
+    }
+    pairs
+}
+
+/// Returns the value of the first occurrence of `key` in a logfmt line.
+pub fn value(line: &str, key: &str) -> Option<String> {
+    pairs(line)
+        .into_iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value)
+}
+
+/// Counts the lowercased values of `key` that the filter keeps.
+///
+/// Each value counts once per line as a single token, however many
+/// words it contains.
+///
+/// # Returns
+///
+/// The value counts, the filter statistics and the number of lines
+/// skipped for lacking the key.
+pub(crate) fn logfmt_value_counts(
+    logs: &[String],
+    key: &str,
+    filter: &TokenFilter,
+) -> (HashMap<String, usize>, FilterStats, usize) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut stats = FilterStats::for_filter(filter);
+    let mut skipped = 0;
+
+    for line in logs {
+        let Some(value) = value(line, key) else {
+            skipped += 1;
+            continue;
+        };
+        let value = value.to_lowercase();
+        if !filter.admit(&value, &mut stats) {
+            continue;
+        }
+        if let Some(count) = counts.get_mut(&value) {
+            *count += 1;
+        } else if filter.max_unique.is_none_or(|limit| counts.len() < limit) {
+            counts.insert(value, 1);
+        } else {
+            stats.tokens_counted -= 1;
+            *stats
+                .tokens_dropped
+                .entry(DropReason::UniqueLimit)
+                .or_insert(0) += 1;
+        }
+    }
+
+    (counts, stats, skipped)
+}
+
+/// Finds the K most frequent directed edges between the values of
+/// `from_key` and `to_key`, such as the transitions of a state machine.
+///
+/// Edges are rendered as `from -> to` with both values lowercased.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::logfmt::top_k_edges;
+///
+/// let logs: Vec<String> = ["from=idle to=busy", "from=busy to=idle", "from=idle to=busy"]
+///     .map(String::from)
+///     .to_vec();
+/// assert_eq!(
+///     top_k_edges(&logs, "from", "to", 1),
+///     vec![("idle -> busy".to_string(), 2)]
+/// );
+/// ```
+pub fn top_k_edges(
+    logs: &[String],
+    from_key: &str,
+    to_key: &str,
+    k: usize,
+) -> Vec<(String, usize)> {
+    select_top_k(edge_counts(logs, from_key, to_key).0, k)
+}
+
+/// Counts the `from -> to` edges of all lines holding both keys.
+///
+/// # Returns
+///
+/// The edge counts and the number of lines skipped for lacking either
+/// key.
+pub(crate) fn edge_counts(
+    logs: &[String],
+    from_key: &str,
+    to_key: &str,
+) -> (HashMap<String, usize>, usize) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut skipped = 0;
+
+    for line in logs {
+        let pairs = pairs(line);
+        let find = |key: &str| {
+            pairs
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.to_lowercase())
+        };
+        match (find(from_key), find(to_key)) {
+            (Some(from), Some(to)) => {
+                *counts.entry(format!("{} -> {}", from, to)).or_insert(0) += 1
+            }
+            _ => skipped += 1,
+        }
+    }
+
+    (counts, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test counting `level` values across logfmt lines
+    #[test]
+    fn test_level_value_frequencies() {
+        let logs = vec![
+            r#"level=error msg="disk full" duration=12ms"#.to_string(),
+            r#"ts=2024-05-01 level=info msg="level=debug inside a quote""#.to_string(),
+            "level=ERROR msg=retry".to_string(),
+            "msg=\"no level here\"".to_string(),
+            "level=warn".to_string(),
+            "level=info".to_string(),
+            "level=error".to_string(),
+        ];
+
+        let (counts, _, skipped) = logfmt_value_counts(&logs, "level", &TokenFilter::default());
+
+        assert_eq!(skipped, 1);
+        assert_eq!(
+            select_top_k(counts, 5),
+            vec![
+                ("error".to_string(), 3),
+                ("info".to_string(), 2),
+                ("warn".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test that the most frequent transition ranks first
+    #[test]
+    fn test_transition_edges() {
+        let logs: Vec<String> = [
+            "from=idle to=running",
+            "from=running to=failed",
+            "from=failed to=idle",
+            "from=idle to=running job=7",
+            "to=idle from=running",
+            "from=idle to=running",
+            "from=idle",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        let (counts, skipped) = edge_counts(&logs, "from", "to");
+
+        assert_eq!(skipped, 1);
+        assert_eq!(
+            select_top_k(counts, 2),
+            vec![
+                ("idle -> running".to_string(), 3),
+                ("failed -> idle".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test that quoted values are kept whole
+    #[test]
+    fn test_quoted_values() {
+        let logs = vec![
+            r#"msg="disk full" level=error"#.to_string(),
+            r#"level=error msg="disk full""#.to_string(),
+            r#"msg=timeout"#.to_string(),
+        ];
+
+        let (counts, _, _) = logfmt_value_counts(&logs, "msg", &TokenFilter::default());
+
+        assert_eq!(counts["disk full"], 2);
+        assert_eq!(counts["timeout"], 1);
+    }
+}
@@ -0,0 +1,133 @@
+//! Breakdowns of the input by the values of named regex captures, such
+//! as the top words per service and per level from one pattern.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::{Ranking, count_words, select_top_k};
+
+/// The lines of one capture value.
+pub type ValueLines = (String, Vec<String>);
+
+/// The top-K words of the lines of each value of one named group.
+pub type GroupRanking = (String, Vec<(String, Ranking)>);
+
+/// Splits the lines by the value of every named capture group of
+/// `pattern`, in a single pass over the input.
+///
+/// A line matching `pattern` joins one group per named capture that took
+/// part in the match; lines not matching are left out. Values are kept
+/// case-sensitive as captured.
+///
+/// # Returns
+///
+/// One `(name, values)` entry per named group in pattern order, with the
+/// values of each ordered by their number of lines (descending) and
+/// alphabetically for ties.
+///
+/// # Example
+///
+/// ```
+/// use regex::Regex;
+/// use log_word_analyzer_cli::named_groups::lines_by_named_group;
+///
+/// let pattern = Regex::new(r"^(?P<level>\w+) (?P<service>\w+):").unwrap();
+/// let logs: Vec<String> = ["ERROR db: timeout", "INFO db: ok", "ERROR api: refused"]
+///     .map(String::from)
+///     .to_vec();
+/// let groups = lines_by_named_group(&logs, &pattern);
+///
+/// assert_eq!(groups[0].0, "level");
+/// assert_eq!(groups[0].1[0], ("ERROR".to_string(), vec![logs[0].clone(), logs[2].clone()]));
+/// assert_eq!(groups[1].1[0].0, "db");
+/// ```
+pub fn lines_by_named_group(logs: &[String], pattern: &Regex) -> Vec<(String, Vec<ValueLines>)> {
+    let names: Vec<&str> = pattern.capture_names().flatten().collect();
+    let mut groups: Vec<HashMap<String, Vec<String>>> = vec![HashMap::new(); names.len()];
+
+    for line in logs {
+        let Some(captures) = pattern.captures(line) else {
+            continue;
+        };
+        for (name, values) in names.iter().zip(groups.iter_mut()) {
+            if let Some(value) = captures.name(name) {
+                values
+                    .entry(value.as_str().to_string())
+                    .or_default()
+                    .push(line.clone());
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .zip(groups)
+        .map(|(name, values)| {
+            let mut values: Vec<ValueLines> = values.into_iter().collect();
+            values.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+            (name.to_string(), values)
+        })
+        .collect()
+}
+
+/// Finds the top-K words of the lines of every value of every named
+/// capture group of `pattern`, as grouped by [`lines_by_named_group`].
+///
+/// # Returns
+///
+/// `(name, [(value, top_k)])` entries, the top-K of each value sorted by
+/// frequency (descending) and alphabetically for ties.
+pub fn top_k_by_named_group(logs: &[String], pattern: &Regex, k: usize) -> Vec<GroupRanking> {
+    lines_by_named_group(logs, pattern)
+        .into_iter()
+        .map(|(name, values)| {
+            let values = values
+                .into_iter()
+                .map(|(value, lines)| (value, select_top_k(count_words(&lines), k)))
+                .collect();
+            (name, values)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that two named captures give independent breakdowns
+    #[test]
+    fn test_independent_breakdowns() {
+        let pattern = Regex::new(r"^(?P<level>[A-Z]+) \[(?P<service>\w+)\]").unwrap();
+        let logs: Vec<String> = [
+            "ERROR [db] timeout",
+            "ERROR [api] timeout",
+            "INFO [db] connected",
+            "no level here",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        let result = top_k_by_named_group(&logs, &pattern, 1);
+
+        let words = |(value, top): &(String, Ranking)| (value.clone(), top[0].0.clone(), top[0].1);
+        let level: Vec<_> = result[0].1.iter().map(words).collect();
+        let service: Vec<_> = result[1].1.iter().map(words).collect();
+        assert_eq!(result[0].0, "level");
+        assert_eq!(
+            level,
+            vec![
+                ("ERROR".to_string(), "error".to_string(), 2),
+                ("INFO".to_string(), "connected".to_string(), 1),
+            ]
+        );
+        assert_eq!(result[1].0, "service");
+        assert_eq!(
+            service,
+            vec![
+                ("db".to_string(), "db".to_string(), 2),
+                ("api".to_string(), "api".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1893 @@
+//! Rendering of top-K results in the supported output formats.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::compare::CompareRow;
+use crate::timestamp::TimeSpan;
+
+/// Output formats selectable with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Rust debug representation, e.g. `[("error", 3), ("disk", 2)]`
+    #[default]
+    Debug,
+    /// JSON array of `{"word": ..., "count": ...}` objects
+    Json,
+    /// Comma-separated values with a `word,count` header
+    Csv,
+    /// Tab-separated values with a `word<TAB>count` header
+    Tsv,
+    /// GitHub-flavored Markdown table with `Rank`, `Word` and `Count` columns
+    Markdown,
+    /// JSON array of `{"text": ..., "weight": ...}` objects for word-cloud
+    /// libraries, with counts scaled linearly into the weight range
+    WordCloud(WeightRange),
+    /// One `count word` line per result, which `--weight-field 1` reads back
+    Lines,
+    /// Every word on a line of its own, repeated `count` times, so the
+    /// output can be analyzed again as a log
+    Repeated,
+    /// Prometheus text exposition format, one `log_word_count{word="..."}`
+    /// gauge sample per result
+    Prometheus,
+    /// Apache Parquet file with `word`, `count` and `percent` columns,
+    /// written by [`write_parquet`] (requires the `parquet` feature)
+    Parquet,
+}
+
+/// Range word-cloud weights are scaled into, e.g. `10-100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WeightRange {
+    /// Weight of the least frequent reported word
+    pub min: u32,
+    /// Weight of the most frequent reported word
+    pub max: u32,
+}
+
+impl Default for WeightRange {
+    fn default() -> Self {
+        WeightRange { min: 10, max: 100 }
+    }
+}
+
+impl WeightRange {
+    /// Maps `value` from `[low, high]` linearly onto the range, rounding
+    /// to the nearest weight. When all values are equal every word gets
+    /// the maximum weight.
+    pub fn scale(self, value: f64, low: f64, high: f64) -> u32 {
+        if high <= low {
+            return self.max;
+        }
+        let span = f64::from(self.max - self.min);
+        self.min + ((value - low) / (high - low) * span).round() as u32
+    }
+}
+
+impl FromStr for WeightRange {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid weight range '{}' (expected min-max, e.g. 10-100)",
+                spec
+            )
+        };
+        let (min, max) = spec.split_once('-').ok_or_else(invalid)?;
+        let min: u32 = min.trim().parse().map_err(|_| invalid())?;
+        let max: u32 = max.trim().parse().map_err(|_| invalid())?;
+        if min > max {
+            return Err(invalid());
+        }
+        Ok(WeightRange { min, max })
+    }
+}
+
+impl OutputFormat {
+    /// Lowercase name, matching the `--format` value.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Debug => "debug",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::WordCloud(_) => "wordcloud",
+            OutputFormat::Lines => "lines",
+            OutputFormat::Repeated => "repeated",
+            OutputFormat::Prometheus => "prometheus",
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "debug" => Ok(OutputFormat::Debug),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "wordcloud" => Ok(OutputFormat::WordCloud(WeightRange::default())),
+            "lines" => Ok(OutputFormat::Lines),
+            "repeated" => Ok(OutputFormat::Repeated),
+            "prometheus" => Ok(OutputFormat::Prometheus),
+            "parquet" => Ok(OutputFormat::Parquet),
+            _ => Err(format!(
+                "unknown output format '{}' (expected debug, json, csv, tsv, markdown, wordcloud, lines, repeated, prometheus or parquet)",
+                name
+            )),
+        }
+    }
+}
+
+/// Result orderings selectable with `--sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// By count (descending), then alphabetically
+    #[default]
+    Count,
+    /// In the order words first appear in the input, ignoring counts
+    None,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "count" => Ok(SortOrder::Count),
+            "none" | "discovery" => Ok(SortOrder::None),
+            _ => Err(format!(
+                "unknown sort order '{}' (expected count or none)",
+                name
+            )),
+        }
+    }
+}
+
+/// Keys the reported rows can be ordered by with `--sort-by`, once the
+/// top-K has been selected by count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// By count
+    #[default]
+    Count,
+    /// Alphabetically
+    Alpha,
+    /// By word length in characters
+    Length,
+    /// By where the word first appears in the input
+    FirstSeen,
+}
+
+impl SortKey {
+    /// The name `--sort-by` accepts for this key.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Count => "count",
+            SortKey::Alpha => "alpha",
+            SortKey::Length => "length",
+            SortKey::FirstSeen => "firstseen",
+        }
+    }
+
+    /// The direction used when `--sort-dir` is not given: largest first
+    /// for counts and lengths, A to Z and earliest first otherwise.
+    pub fn default_direction(self) -> SortDirection {
+        match self {
+            SortKey::Count | SortKey::Length => SortDirection::Desc,
+            SortKey::Alpha | SortKey::FirstSeen => SortDirection::Asc,
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "count" => Ok(SortKey::Count),
+            "alpha" => Ok(SortKey::Alpha),
+            "length" => Ok(SortKey::Length),
+            "firstseen" => Ok(SortKey::FirstSeen),
+            _ => Err(format!(
+                "unknown sort key '{}' (expected count, alpha, length or firstseen)",
+                name
+            )),
+        }
+    }
+}
+
+/// Directions selectable with `--sort-dir`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    /// Smallest, earliest or A first
+    Asc,
+    /// Largest, latest or Z first
+    Desc,
+}
+
+impl SortDirection {
+    /// The name `--sort-dir` accepts for this direction.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        }
+    }
+}
+
+impl FromStr for SortDirection {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "asc" => Ok(SortDirection::Asc),
+            "desc" => Ok(SortDirection::Desc),
+            _ => Err(format!(
+                "unknown sort direction '{}' (expected asc or desc)",
+                name
+            )),
+        }
+    }
+}
+
+/// Values that can appear in the second column of a result row.
+///
+/// Plain counts are labeled `count`, weighted floating-point scores
+/// `score` (as JSON keys and CSV/TSV headers).
+pub(crate) trait ResultValue: Debug + Clone {
+    /// Name of the value column
+    const KEY: &'static str;
+
+    /// Renders the value as a plain number
+    fn to_number(&self) -> String;
+
+    /// The value as a float, for scaling
+    fn to_f64(&self) -> f64;
+
+    /// Further members of the JSON result object, each preceded by a comma
+    fn json_members(&self) -> String {
+        String::new()
+    }
+
+    /// Writes a ranked list of these values in `format`, as [`write_list`]
+    /// does
+    fn write_ranking<W: Write>(
+        results: &[(String, Self)],
+        writer: &mut W,
+        format: OutputFormat,
+    ) -> io::Result<()> {
+        write_list(results, writer, format)
+    }
+}
+
+impl ResultValue for usize {
+    const KEY: &'static str = "count";
+
+    fn to_number(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    /// Plain counts are streamed row by row by [`stream_results`]
+    fn write_ranking<W: Write>(
+        results: &[(String, Self)],
+        writer: &mut W,
+        format: OutputFormat,
+    ) -> io::Result<()> {
+        stream_results(
+            results.iter().map(|(word, count)| (word.as_str(), *count)),
+            writer,
+            format,
+        )
+    }
+}
+
+impl ResultValue for f64 {
+    const KEY: &'static str = "score";
+
+    fn to_number(&self) -> String {
+        // JSON has no representation for NaN or infinities
+        if self.is_finite() {
+            self.to_string()
+        } else {
+            "null".to_string()
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+}
+
+/// A count together with the first lines its word occurs on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CountWithLines {
+    /// Number of occurrences
+    pub count: usize,
+    /// Line numbers, starting at 1
+    pub lines: Vec<usize>,
+}
+
+impl ResultValue for CountWithLines {
+    const KEY: &'static str = "count";
+
+    fn to_number(&self) -> String {
+        self.count.to_string()
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.count as f64
+    }
+
+    fn json_members(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(usize::to_string).collect();
+        format!(",\"lines\":[{}]", lines.join(","))
+    }
+}
+
+/// A count together with the time span its word occurs over, `None` when
+/// none of its lines carries a timestamp.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct CountWithSpan {
+    /// Number of occurrences
+    pub count: usize,
+    /// First and last timestamps the word was seen at
+    pub span: Option<TimeSpan>,
+}
+
+impl Debug for CountWithSpan {
+    /// Continues the row tuple, as in `("error", 3, "2024-03-01T10:00:00Z",
+    /// "2024-03-01T10:05:00Z")`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(
+                f,
+                "{}, {:?}, {:?}",
+                self.count,
+                span.first.to_string(),
+                span.last.to_string()
+            ),
+            None => write!(f, "{}, None, None", self.count),
+        }
+    }
+}
+
+impl ResultValue for CountWithSpan {
+    const KEY: &'static str = "count";
+
+    fn to_number(&self) -> String {
+        self.count.to_string()
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.count as f64
+    }
+
+    fn json_members(&self) -> String {
+        match self.span {
+            Some(span) => format!(
+                ",\"first\":{},\"last\":{}",
+                json_string(&span.first.to_string()),
+                json_string(&span.last.to_string())
+            ),
+            None => ",\"first\":null,\"last\":null".to_string(),
+        }
+    }
+}
+
+/// The length of a run of identical consecutive lines.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RunLength(pub usize);
+
+impl Debug for RunLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResultValue for RunLength {
+    const KEY: &'static str = "run";
+
+    fn to_number(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.0 as f64
+    }
+}
+
+/// Writes top-K results to any writer in the requested format.
+///
+/// Every format ends with a trailing newline, so the output can be
+/// written to a terminal or file unchanged.
+///
+/// # Arguments
+///
+/// * `results` - Ranked `(word, count)` rows, e.g. from [`crate::top_k_words`]
+/// * `writer` - Destination of the formatted bytes
+/// * `format` - The output format to use
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::output::{write_results, OutputFormat};
+///
+/// let results = vec![("error".to_string(), 3), ("disk".to_string(), 2)];
+/// let mut buffer = Vec::new();
+/// write_results(&results, &mut buffer, OutputFormat::Csv).unwrap();
+/// assert_eq!(String::from_utf8(buffer).unwrap(), "word,count\nerror,3\ndisk,2\n");
+/// ```
+pub fn write_results<W: Write>(
+    results: &[(String, usize)],
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    emit_results(results, &mut FormatSink::new(writer, format))
+}
+
+/// A destination for ranked `(word, count)` rows, so results can go to a
+/// database, a channel or a callback instead of a writer.
+///
+/// Closures taking `(rank, word, count)` are sinks, and [`FormatSink`]
+/// writes the rows in an output format.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::output::emit_results;
+///
+/// let results = vec![("error".to_string(), 3), ("disk".to_string(), 2)];
+/// let mut lines = Vec::new();
+/// emit_results(&results, &mut |rank: usize, word: &str, count: usize| {
+///     lines.push(format!("#{} {} ({})", rank, word, count));
+/// })
+/// .unwrap();
+/// assert_eq!(lines, ["#1 error (3)", "#2 disk (2)"]);
+/// ```
+pub trait OutputSink {
+    /// Receives one row; `rank` counts from 1 and rows come in rank order.
+    fn emit(&mut self, rank: usize, word: &str, count: usize) -> io::Result<()>;
+
+    /// Called once after the last row, to complete buffered or framed
+    /// output. Does nothing by default.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(usize, &str, usize)> OutputSink for F {
+    fn emit(&mut self, rank: usize, word: &str, count: usize) -> io::Result<()> {
+        self(rank, word, count);
+        Ok(())
+    }
+}
+
+/// Writes the rows it receives to a writer in an output format, the same
+/// bytes as [`write_results`].
+///
+/// Each row is written as it arrives, with the header before the first
+/// and any closing bracket written by [`OutputSink::finish`]. Only the word
+/// cloud, which scales the rows by the smallest and largest count, holds
+/// them until then.
+#[derive(Debug)]
+pub struct FormatSink<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    /// Rows written so far
+    written: usize,
+    /// Whether the header is written
+    started: bool,
+    /// Word-cloud rows waiting for their scale
+    cloud_rows: Vec<(String, usize)>,
+}
+
+impl<W: Write> FormatSink<W> {
+    /// Creates a sink writing to `writer` in `format`.
+    pub fn new(writer: W, format: OutputFormat) -> Self {
+        FormatSink {
+            writer,
+            format,
+            written: 0,
+            started: false,
+            cloud_rows: Vec::new(),
+        }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            write_stream_start::<usize, _>(&mut self.writer, self.format)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> OutputSink for FormatSink<W> {
+    fn emit(&mut self, _rank: usize, word: &str, count: usize) -> io::Result<()> {
+        if let OutputFormat::WordCloud(_) = self.format {
+            self.cloud_rows.push((word.to_string(), count));
+            return Ok(());
+        }
+        self.start()?;
+        write_stream_row(self.written, word, &count, &mut self.writer, self.format)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let OutputFormat::WordCloud(_) = self.format {
+            let rows = std::mem::take(&mut self.cloud_rows);
+            return write_list(&rows, &mut self.writer, self.format);
+        }
+        self.start()?;
+        write_stream_end(&mut self.writer, self.format)
+    }
+}
+
+/// Sends ranked `(word, count)` rows to `sink`, ranks counting from 1,
+/// and finishes it.
+pub fn emit_results<S: OutputSink + ?Sized>(
+    results: &[(String, usize)],
+    sink: &mut S,
+) -> io::Result<()> {
+    for (index, (word, count)) in results.iter().enumerate() {
+        sink.emit(index + 1, word, *count)?;
+    }
+    sink.finish()
+}
+
+/// Writes one result list in the given format, followed by a newline.
+pub(crate) fn write_list<T: ResultValue, W: Write>(
+    results: &[(String, T)],
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    if let OutputFormat::WordCloud(range) = format {
+        write_cloud_array(results, range, writer)?;
+        return writeln!(writer);
+    }
+    write_stream::<T, _, _, _>(
+        results.iter().map(|(word, value)| (word, value)),
+        writer,
+        format,
+    )
+}
+
+/// Writes ranked `(word, count)` rows to any writer as they come from an
+/// iterator, without holding the whole result in memory; the words may
+/// be owned or borrowed.
+///
+/// The rows go through a [`FormatSink`], so the bytes written are the
+/// same as [`write_results`] writes for the collected rows. Only the
+/// word-cloud format, which scales every weight by the smallest and
+/// largest count, collects the rows first.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::output::{stream_results, OutputFormat};
+///
+/// let rows = (1..=3).rev().map(|count| (format!("word{}", count), count));
+/// let mut buffer = Vec::new();
+/// stream_results(rows, &mut buffer, OutputFormat::Tsv).unwrap();
+/// assert_eq!(
+///     String::from_utf8(buffer).unwrap(),
+///     "word\tcount\nword3\t3\nword2\t2\nword1\t1\n"
+/// );
+/// ```
+pub fn stream_results<I, S, W>(results: I, writer: &mut W, format: OutputFormat) -> io::Result<()>
+where
+    I: IntoIterator<Item = (S, usize)>,
+    S: AsRef<str>,
+    W: Write,
+{
+    let mut sink = FormatSink::new(writer, format);
+    for (index, (word, count)) in results.into_iter().enumerate() {
+        sink.emit(index + 1, word.as_ref(), count)?;
+    }
+    sink.finish()
+}
+
+/// Writes result rows one at a time in any format but the word cloud,
+/// followed by a newline; the rows may be borrowed or owned.
+fn write_stream<T, S, V, W>(
+    rows: impl IntoIterator<Item = (S, V)>,
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()>
+where
+    T: ResultValue,
+    S: AsRef<str>,
+    V: Borrow<T>,
+    W: Write,
+{
+    write_stream_start::<T, _>(writer, format)?;
+    for (index, (word, value)) in rows.into_iter().enumerate() {
+        write_stream_row(index, word.as_ref(), value.borrow(), writer, format)?;
+    }
+    write_stream_end(writer, format)
+}
+
+/// Writes what comes before the first streamed row: an opening bracket,
+/// the header or the metric type.
+fn write_stream_start<T: ResultValue, W: Write>(
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Debug | OutputFormat::Json => write!(writer, "["),
+        OutputFormat::WordCloud(_) => unreachable!("word clouds are scaled over all rows"),
+        OutputFormat::Parquet => Err(binary_format_error()),
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            writeln!(writer, "word{}{}", delimiter(format), T::KEY)
+        }
+        OutputFormat::Markdown => {
+            writeln!(writer, "| Rank | Word | {} |", capitalized(T::KEY))?;
+            writeln!(writer, "|---:|---|---:|")
+        }
+        OutputFormat::Prometheus => writeln!(writer, "# TYPE log_word_{} gauge", T::KEY),
+        OutputFormat::Lines | OutputFormat::Repeated => Ok(()),
+    }
+}
+
+/// Writes the streamed row at `index`, counting from 0.
+fn write_stream_row<T: ResultValue, W: Write>(
+    index: usize,
+    word: &str,
+    value: &T,
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        // Same as the `{:?}` of the collected rows
+        OutputFormat::Debug => {
+            if index > 0 {
+                write!(writer, ", ")?;
+            }
+            write!(writer, "{:?}", (word, value))
+        }
+        OutputFormat::Json => {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write_json_object(word, value, writer)
+        }
+        OutputFormat::WordCloud(_) => unreachable!("word clouds are scaled over all rows"),
+        OutputFormat::Parquet => Err(binary_format_error()),
+        OutputFormat::Csv | OutputFormat::Tsv => writeln!(
+            writer,
+            "{}{}{}",
+            field(word, format),
+            delimiter(format),
+            value.to_number()
+        ),
+        OutputFormat::Markdown => writeln!(
+            writer,
+            "| {} | {} | {} |",
+            index + 1,
+            field(word, format),
+            value.to_number()
+        ),
+        OutputFormat::Lines => writeln!(writer, "{} {}", value.to_number(), word),
+        OutputFormat::Prometheus => write_sample(word, value, "", writer),
+        OutputFormat::Repeated => {
+            // Scores are rounded to whole copies
+            let copies = value.to_f64().round().max(0.0) as usize;
+            for _ in 0..copies {
+                writeln!(writer, "{}", word)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes what comes after the last streamed row.
+fn write_stream_end<W: Write>(writer: &mut W, format: OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Debug | OutputFormat::Json => writeln!(writer, "]"),
+        _ => Ok(()),
+    }
+}
+
+/// Writes several labeled result lists, e.g. one per input file.
+///
+/// The debug format heads each section with `==> label <==` and Markdown
+/// with a `### label` heading above its table, JSON and word-cloud output nest
+/// each section's results under its label as an object key, and CSV/TSV
+/// prepend a column named `label_column` to a single table, as Prometheus
+/// samples carry it as a label. The lines and
+/// repeated formats drop the labels and concatenate the sections, since
+/// their output is meant to be read back as a log.
+pub(crate) fn write_sections<T: ResultValue, W: Write>(
+    sections: &[(String, Vec<(String, T)>)],
+    label_column: &str,
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Debug | OutputFormat::Markdown => {
+            for (index, (name, results)) in sections.iter().enumerate() {
+                // Separate sections with a blank line
+                if index > 0 {
+                    writeln!(writer)?;
+                }
+                if format == OutputFormat::Markdown {
+                    writeln!(writer, "### {}\n", field(name, format))?;
+                } else {
+                    writeln!(writer, "==> {} <==", name)?;
+                }
+                write_list(results, writer, format)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json | OutputFormat::WordCloud(_) => {
+            write!(writer, "{{")?;
+            for (index, (name, results)) in sections.iter().enumerate() {
+                if index > 0 {
+                    write!(writer, ",")?;
+                }
+                write!(writer, "{}:", json_string(name))?;
+                match format {
+                    OutputFormat::WordCloud(range) => write_cloud_array(results, range, writer)?,
+                    _ => write_json_array(results, writer)?,
+                }
+            }
+            writeln!(writer, "}}")
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let separator = delimiter(format);
+            writeln!(writer, "{1}{0}word{0}{2}", separator, label_column, T::KEY)?;
+            for (name, results) in sections {
+                for (word, value) in results {
+                    writeln!(
+                        writer,
+                        "{1}{0}{2}{0}{3}",
+                        separator,
+                        field(name, format),
+                        field(word, format),
+                        value.to_number()
+                    )?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Lines | OutputFormat::Repeated => {
+            for (_, results) in sections {
+                write_list(results, writer, format)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Prometheus => {
+            writeln!(writer, "# TYPE log_word_{} gauge", T::KEY)?;
+            for (name, results) in sections {
+                write_samples(results, Some((label_column, name)), writer)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Parquet => Err(binary_format_error()),
+    }
+}
+
+/// Result lists labeled two levels deep, as written by
+/// [`write_nested_sections`].
+pub(crate) type Nested<T> = (String, Vec<(String, Vec<(String, T)>)>);
+
+/// Writes result lists grouped two levels deep, e.g. per value of every
+/// named capture group.
+///
+/// JSON nests each outer label's sections as an object under it; every
+/// other format writes the sections as [`write_sections`] does, labeled
+/// `outer=inner` in a column named `label_column`.
+pub(crate) fn write_nested_sections<T: ResultValue, W: Write>(
+    groups: &[Nested<T>],
+    label_column: &str,
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    if format != OutputFormat::Json {
+        let sections: Vec<(String, Vec<(String, T)>)> = groups
+            .iter()
+            .flat_map(|(outer, sections)| {
+                sections
+                    .iter()
+                    .map(move |(inner, results)| (format!("{}={}", outer, inner), results.clone()))
+            })
+            .collect();
+        return write_sections(&sections, label_column, writer, format);
+    }
+    write!(writer, "{{")?;
+    for (index, (outer, sections)) in groups.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "{}:{{", json_string(outer))?;
+        for (index, (inner, results)) in sections.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{}:", json_string(inner))?;
+            write_json_array(results, writer)?;
+        }
+        write!(writer, "}}")?;
+    }
+    writeln!(writer, "}}")
+}
+
+/// Writes rows of two numbers, such as per-block statistics.
+///
+/// `columns` names the two values; they become JSON keys and the CSV/TSV
+/// header, while the debug format prints the plain tuples. Rows hold no
+/// words, so the word-cloud format writes them as JSON and the lines and
+/// repeated formats as space-separated pairs. Prometheus samples of a
+/// `log_<second>` gauge carry the first value as a label.
+pub(crate) fn write_rows<W: Write>(
+    rows: &[(usize, usize)],
+    columns: [&str; 2],
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let [first, second] = columns;
+    match format {
+        OutputFormat::Debug => writeln!(writer, "{:?}", rows),
+        OutputFormat::Json | OutputFormat::WordCloud(_) => {
+            let objects: Vec<String> = rows
+                .iter()
+                .map(|(a, b)| format!("{{\"{}\":{},\"{}\":{}}}", first, a, second, b))
+                .collect();
+            writeln!(writer, "[{}]", objects.join(","))
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let separator = delimiter(format);
+            writeln!(writer, "{}{}{}", first, separator, second)?;
+            for (a, b) in rows {
+                writeln!(writer, "{}{}{}", a, separator, b)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Markdown => {
+            writeln!(
+                writer,
+                "| {} | {} |",
+                capitalized(first),
+                capitalized(second)
+            )?;
+            writeln!(writer, "|---:|---:|")?;
+            for (a, b) in rows {
+                writeln!(writer, "| {} | {} |", a, b)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Lines | OutputFormat::Repeated => {
+            for (a, b) in rows {
+                writeln!(writer, "{} {}", a, b)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Prometheus => {
+            writeln!(writer, "# TYPE log_{} gauge", second)?;
+            for (a, b) in rows {
+                writeln!(writer, "log_{}{{{}=\"{}\"}} {}", second, first, a, b)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Parquet => Err(binary_format_error()),
+    }
+}
+
+/// Writes a comparison of two rankings, one row per word.
+///
+/// The debug format prints a table aligned in columns headed by the two
+/// input `names`, with cells reading `#rank count` or `-` where a word is
+/// missing from one side; Markdown prints the same as a table. JSON
+/// objects hold `first` and `second` members of `rank` and `count`
+/// (`null` when missing), and CSV/TSV have `first_rank`, `first_count`,
+/// `second_rank` and `second_count` columns left empty when missing.
+/// Formats without a layout of their own use the debug table.
+pub(crate) fn write_comparison<W: Write>(
+    rows: &[CompareRow],
+    names: [&str; 2],
+    writer: &mut W,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let cell = |side: Option<(usize, usize)>| match side {
+        Some((rank, count)) => format!("#{} {}", rank, count),
+        None => "-".to_string(),
+    };
+    match format {
+        OutputFormat::Json => {
+            let side = |side: Option<(usize, usize)>| match side {
+                Some((rank, count)) => format!("{{\"rank\":{},\"count\":{}}}", rank, count),
+                None => "null".to_string(),
+            };
+            let objects: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    format!(
+                        "{{\"word\":{},\"first\":{},\"second\":{}}}",
+                        json_string(&row.word),
+                        side(row.first),
+                        side(row.second)
+                    )
+                })
+                .collect();
+            writeln!(writer, "[{}]", objects.join(","))
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let separator = delimiter(format);
+            let side = |side: Option<(usize, usize)>| match side {
+                Some((rank, count)) => format!("{}{}{}", rank, separator, count),
+                None => separator.to_string(),
+            };
+            writeln!(
+                writer,
+                "word{0}first_rank{0}first_count{0}second_rank{0}second_count",
+                separator
+            )?;
+            for row in rows {
+                writeln!(
+                    writer,
+                    "{1}{0}{2}{0}{3}",
+                    separator,
+                    field(&row.word, format),
+                    side(row.first),
+                    side(row.second)
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Markdown => {
+            writeln!(
+                writer,
+                "| Word | {} | {} |",
+                field(names[0], format),
+                field(names[1], format)
+            )?;
+            writeln!(writer, "|---|---:|---:|")?;
+            for row in rows {
+                writeln!(
+                    writer,
+                    "| {} | {} | {} |",
+                    field(&row.word, format),
+                    cell(row.first),
+                    cell(row.second)
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Parquet => Err(binary_format_error()),
+        OutputFormat::Debug
+        | OutputFormat::WordCloud(_)
+        | OutputFormat::Lines
+        | OutputFormat::Repeated
+        | OutputFormat::Prometheus => {
+            let table: Vec<[String; 3]> = rows
+                .iter()
+                .map(|row| [row.word.clone(), cell(row.first), cell(row.second)])
+                .collect();
+            let header = [
+                "word".to_string(),
+                names[0].to_string(),
+                names[1].to_string(),
+            ];
+            let width = |column: usize| {
+                table
+                    .iter()
+                    .chain([&header])
+                    .map(|cells| cells[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            };
+            let (word_width, first_width) = (width(0), width(1));
+            for cells in [&header].into_iter().chain(&table) {
+                let line = format!(
+                    "{:<word_width$}  {:<first_width$}  {}",
+                    cells[0], cells[1], cells[2]
+                );
+                writeln!(writer, "{}", line.trim_end())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Groups ranked results by their first character, index style.
+///
+/// Groups are labeled with the uppercased initial letter and ordered
+/// alphabetically; words starting with anything other than a letter go
+/// into a `#` group listed first. Within a group the input (frequency)
+/// order is preserved.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::output::group_by_initial;
+///
+/// let results = vec![("error".to_string(), 3), ("404".to_string(), 2), ("disk".to_string(), 2)];
+/// let groups = group_by_initial(&results);
+/// assert_eq!(groups[0], ("#".to_string(), vec![("404".to_string(), 2)]));
+/// assert_eq!(groups[1].0, "D");
+/// assert_eq!(groups[2].0, "E");
+/// ```
+pub fn group_by_initial<T: Clone>(results: &[(String, T)]) -> Vec<(String, Vec<(String, T)>)> {
+    let mut groups: BTreeMap<String, Vec<(String, T)>> = BTreeMap::new();
+    for (word, value) in results {
+        let label = match word.chars().next() {
+            Some(initial) if initial.is_alphabetic() => initial.to_uppercase().collect(),
+            _ => "#".to_string(),
+        };
+        groups
+            .entry(label)
+            .or_default()
+            .push((word.clone(), value.clone()));
+    }
+    groups.into_iter().collect()
+}
+
+/// Partitions ranked results into frequency tiers such as `>= 100`,
+/// `>= 10` and `>= 1`.
+///
+/// Each word goes into the highest tier whose threshold its value
+/// reaches; `thresholds` must be sorted from the highest down. Words
+/// below every threshold form a last `< n` tier. Empty tiers are left
+/// out and the input (frequency) order is kept within a tier.
+pub(crate) fn group_by_tier<T: ResultValue>(
+    results: &[(String, T)],
+    thresholds: &[usize],
+) -> Vec<(String, Vec<(String, T)>)> {
+    let mut tiers: Vec<(String, Vec<(String, T)>)> = thresholds
+        .iter()
+        .map(|threshold| (format!(">= {}", threshold), Vec::new()))
+        .collect();
+    let lowest = thresholds.last().copied().unwrap_or(0);
+    tiers.push((format!("< {}", lowest), Vec::new()));
+
+    for (word, value) in results {
+        let tier = thresholds
+            .iter()
+            .position(|&threshold| value.to_f64() >= threshold as f64)
+            .unwrap_or(thresholds.len());
+        tiers[tier].1.push((word.clone(), value.clone()));
+    }
+    tiers.retain(|(_, words)| !words.is_empty());
+    tiers
+}
+
+/// Column separator of a delimited format.
+fn delimiter(format: OutputFormat) -> char {
+    if format == OutputFormat::Tsv {
+        '\t'
+    } else {
+        ','
+    }
+}
+
+/// Uppercases the first letter of a column name for table headers.
+fn capitalized(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Escapes a text field for a delimited or table format.
+///
+/// CSV fields are quoted per RFC 4180 when they contain a comma, quote or
+/// line break. TSV cannot quote, so tabs and line breaks are escaped as
+/// `\t`, `\n` and `\r` instead. Markdown table cells escape `|` as `\|`
+/// and turn line breaks into spaces, which would otherwise end the row.
+fn field(value: &str, format: OutputFormat) -> String {
+    if format == OutputFormat::Markdown {
+        return value.replace('|', "\\|").replace(['\n', '\r'], " ");
+    }
+    if format == OutputFormat::Tsv {
+        return value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+    }
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes one Prometheus sample per result, labeled by its word and by
+/// `extra`, a further label name and value, when given.
+fn write_samples<T: ResultValue, W: Write>(
+    results: &[(String, T)],
+    extra: Option<(&str, &str)>,
+    writer: &mut W,
+) -> io::Result<()> {
+    let extra = extra
+        .map(|(name, value)| format!("{}=\"{}\",", name, label_value(value)))
+        .unwrap_or_default();
+    for (word, value) in results {
+        write_sample(word, value, &extra, writer)?;
+    }
+    Ok(())
+}
+
+/// Writes one Prometheus sample; `extra` holds further labels, each
+/// followed by a comma.
+fn write_sample<T: ResultValue, W: Write>(
+    word: &str,
+    value: &T,
+    extra: &str,
+    writer: &mut W,
+) -> io::Result<()> {
+    let number = value.to_f64();
+    let number = if number.is_nan() {
+        "NaN".to_string()
+    } else if number.is_infinite() {
+        if number > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_number()
+    };
+    writeln!(
+        writer,
+        "log_word_{}{{{}word=\"{}\"}} {}",
+        T::KEY,
+        extra,
+        label_value(word),
+        number
+    )
+}
+
+/// Version of the `--json-envelope` output, raised whenever its structure
+/// changes incompatibly.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Wraps JSON output in a versioned `{"schema_version":1,"results":...}`
+/// object, so consumers can detect format changes.
+///
+/// Everything written through the envelope becomes the `results` member;
+/// [`JsonEnvelope::finish`] closes the object. A trailing newline of the
+/// wrapped output is moved behind the closing brace.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::output::{write_results, JsonEnvelope, OutputFormat};
+///
+/// let mut buffer = Vec::new();
+/// let mut envelope = JsonEnvelope::new(&mut buffer).unwrap();
+/// write_results(&[("error".to_string(), 2)], &mut envelope, OutputFormat::Json).unwrap();
+/// envelope.finish().unwrap();
+/// assert_eq!(
+///     String::from_utf8(buffer).unwrap(),
+///     "{\"schema_version\":1,\"results\":[{\"word\":\"error\",\"count\":2}]}\n"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct JsonEnvelope<W: Write> {
+    inner: W,
+    /// A newline held back in case it ends the wrapped output
+    pending_newline: bool,
+}
+
+impl<W: Write> JsonEnvelope<W> {
+    /// Opens the envelope object on `inner`.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        write!(
+            inner,
+            "{{\"schema_version\":{},\"results\":",
+            JSON_SCHEMA_VERSION
+        )?;
+        Ok(JsonEnvelope {
+            inner,
+            pending_newline: false,
+        })
+    }
+
+    /// Closes the envelope object and ends the line.
+    pub fn finish(mut self) -> io::Result<()> {
+        writeln!(self.inner, "}}")?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for JsonEnvelope<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending_newline {
+            self.inner.write_all(b"\n")?;
+        }
+        let (body, newline) = match buf.split_last() {
+            Some((b'\n', body)) => (body, true),
+            _ => (buf, false),
+        };
+        self.inner.write_all(body)?;
+        self.pending_newline = newline;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes top-K results as an Apache Parquet file, for loading into
+/// analytics tools.
+///
+/// The file has a required `word` column (UTF-8 string) and `count`
+/// column (64-bit integer) and, when the `total` number of counted tokens
+/// is given, a `percent` column (double) holding each word's share of it.
+///
+/// Requires the `parquet` feature.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(
+    results: &[(String, usize)],
+    total: Option<usize>,
+    writer: W,
+) -> io::Result<()> {
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    let schema = match total {
+        Some(_) => {
+            "message top_k { required binary word (UTF8); required int64 count; required double percent; }"
+        }
+        None => "message top_k { required binary word (UTF8); required int64 count; }",
+    };
+    let schema = parse_message_type(schema).map_err(io::Error::other)?;
+    let properties = WriterProperties::builder().build();
+    let mut file = SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))
+        .map_err(io::Error::other)?;
+
+    let words: Vec<ByteArray> = results
+        .iter()
+        .map(|(word, _)| ByteArray::from(word.as_str()))
+        .collect();
+    let counts: Vec<i64> = results
+        .iter()
+        .map(|(_, count)| i64::try_from(*count).unwrap_or(i64::MAX))
+        .collect();
+
+    let mut row_group = file.next_row_group().map_err(io::Error::other)?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column().map_err(io::Error::other)? {
+        // Columns come in schema order
+        let written = match index {
+            0 => column
+                .typed::<ByteArrayType>()
+                .write_batch(&words, None, None),
+            1 => column.typed::<Int64Type>().write_batch(&counts, None, None),
+            _ => {
+                let total = total.unwrap_or(0);
+                let percents: Vec<f64> = results
+                    .iter()
+                    .map(|(_, count)| percent_of(*count, total))
+                    .collect();
+                column
+                    .typed::<DoubleType>()
+                    .write_batch(&percents, None, None)
+            }
+        };
+        written.map_err(io::Error::other)?;
+        column.close().map_err(io::Error::other)?;
+        index += 1;
+    }
+    row_group.close().map_err(io::Error::other)?;
+    file.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Percentage of `total` that `count` makes up, 0 for an empty total.
+#[cfg(feature = "parquet")]
+fn percent_of(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+/// The error of the text writers for [`OutputFormat::Parquet`], which
+/// only [`write_parquet`] writes, for a single list of counts.
+fn binary_format_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "parquet output is only written for a single top-K list of counts",
+    )
+}
+
+/// Escapes a Prometheus label value: backslash, double quote and line
+/// feed become `\\`, `\"` and `\n`.
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Writes results as a compact JSON array of word/value objects.
+fn write_json_array<T: ResultValue, W: Write>(
+    results: &[(String, T)],
+    writer: &mut W,
+) -> io::Result<()> {
+    write!(writer, "[")?;
+    for (index, (word, value)) in results.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write_json_object(word, value, writer)?;
+    }
+    write!(writer, "]")
+}
+
+/// Writes one result as a JSON word/value object.
+fn write_json_object<T: ResultValue, W: Write>(
+    word: &str,
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "{{\"word\":{},\"{}\":{}{}}}",
+        json_string(word),
+        T::KEY,
+        value.to_number(),
+        value.json_members()
+    )
+}
+
+/// Writes results as a JSON array of word-cloud `{"text","weight"}`
+/// objects, without a trailing newline.
+///
+/// The most frequent result gets the maximum weight of `range` and the
+/// least frequent the minimum.
+fn write_cloud_array<T: ResultValue, W: Write>(
+    results: &[(String, T)],
+    range: WeightRange,
+    writer: &mut W,
+) -> io::Result<()> {
+    let values = results.iter().map(|(_, value)| value.to_f64());
+    let low = values.clone().fold(f64::INFINITY, f64::min);
+    let high = values.fold(f64::NEG_INFINITY, f64::max);
+
+    write!(writer, "[")?;
+    for (index, (word, value)) in results.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"text\":{},\"weight\":{}}}",
+            json_string(word),
+            range.scale(value.to_f64(), low, high)
+        )?;
+    }
+    write!(writer, "]")
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects every row it receives, and whether it was finished.
+    #[derive(Default)]
+    struct VecSink {
+        rows: Vec<(usize, String, usize)>,
+        finished: bool,
+    }
+
+    impl OutputSink for VecSink {
+        fn emit(&mut self, rank: usize, word: &str, count: usize) -> io::Result<()> {
+            self.rows.push((rank, word.to_string(), count));
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    /// Test that a custom sink receives every row in rank order
+    #[test]
+    fn test_custom_sink_captures_rows() {
+        let results = crate::top_k_words(
+            &[
+                "error disk error".to_string(),
+                "retry error disk".to_string(),
+            ],
+            3,
+        );
+
+        let mut sink = VecSink::default();
+        emit_results(&results, &mut sink).unwrap();
+
+        assert_eq!(
+            sink.rows,
+            vec![
+                (1, "error".to_string(), 3),
+                (2, "disk".to_string(), 2),
+                (3, "retry".to_string(), 1),
+            ]
+        );
+        assert!(sink.finished);
+
+        // The format writers are sinks too
+        let mut buffer = Vec::new();
+        emit_results(
+            &results,
+            &mut FormatSink::new(&mut buffer, OutputFormat::Lines),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "3 error\n2 disk\n1 retry\n"
+        );
+    }
+
+    /// Test that a format sink writes each row as it arrives, holding only
+    /// word-cloud rows until it is finished
+    #[test]
+    fn test_format_sink_streams_rows() {
+        let mut buffer = Vec::new();
+        let mut sink = FormatSink::new(&mut buffer, OutputFormat::Csv);
+        sink.emit(1, "error", 3).unwrap();
+        assert!(sink.cloud_rows.is_empty());
+        sink.emit(2, "disk", 2).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "word,count\nerror,3\ndisk,2\n"
+        );
+
+        let mut buffer = Vec::new();
+        let mut sink = FormatSink::new(&mut buffer, OutputFormat::Json);
+        sink.emit(1, "error", 3).unwrap();
+        assert_eq!(sink.written, 1);
+        sink.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "[{\"word\":\"error\",\"count\":3}]\n"
+        );
+
+        let mut sink = FormatSink::new(Vec::new(), OutputFormat::WordCloud(WeightRange::default()));
+        sink.emit(1, "error", 3).unwrap();
+        assert!(sink.writer.is_empty());
+        sink.finish().unwrap();
+        assert!(!sink.writer.is_empty());
+
+        // No rows still frame the output
+        let mut buffer = Vec::new();
+        emit_results(&[], &mut FormatSink::new(&mut buffer, OutputFormat::Debug)).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "[]\n");
+    }
+
+    /// Test JSON escaping of quotes, backslashes and control characters
+    #[test]
+    fn test_json_string_escaping() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(json_string("tab\there\u{1}"), "\"tab\\there\\u0001\"");
+    }
+
+    /// Test that a written Parquet file reads back with its schema and rows
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_round_trip() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let path = crate::test_util::temp_file("top.parquet", "");
+        let results = vec![("error".to_string(), 3), ("disk".to_string(), 1)];
+        write_parquet(&results, Some(4), std::fs::File::create(&path).unwrap()).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr();
+        let columns: Vec<&str> = schema
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        assert_eq!(columns, ["word", "count", "percent"]);
+
+        let rows: Vec<(String, i64, f64)> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (
+                    row.get_string(0).unwrap().clone(),
+                    row.get_long(1).unwrap(),
+                    row.get_double(2).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("error".to_string(), 3, 75.0),
+                ("disk".to_string(), 1, 25.0)
+            ]
+        );
+    }
+
+    /// Test the streamed bytes of every format against fixed output, for
+    /// borrowed words that need escaping
+    #[test]
+    fn test_stream_results_formats() {
+        let rows = [("disk", 2), ("a,\"b|", 1)];
+        let render = |format| {
+            let mut buffer = Vec::new();
+            stream_results(rows, &mut buffer, format).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            render(OutputFormat::Debug),
+            "[(\"disk\", 2), (\"a,\\\"b|\", 1)]\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Json),
+            "[{\"word\":\"disk\",\"count\":2},{\"word\":\"a,\\\"b|\",\"count\":1}]\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Csv),
+            "word,count\ndisk,2\n\"a,\"\"b|\",1\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Tsv),
+            "word\tcount\ndisk\t2\na,\"b|\t1\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Markdown),
+            "| Rank | Word | Count |\n|---:|---|---:|\n| 1 | disk | 2 |\n| 2 | a,\"b\\| | 1 |\n"
+        );
+        assert_eq!(
+            render(OutputFormat::WordCloud(WeightRange::default())),
+            "[{\"text\":\"disk\",\"weight\":100},{\"text\":\"a,\\\"b|\",\"weight\":10}]\n"
+        );
+        assert_eq!(render(OutputFormat::Lines), "2 disk\n1 a,\"b|\n");
+        assert_eq!(
+            render(OutputFormat::Prometheus),
+            "# TYPE log_word_count gauge\nlog_word_count{word=\"disk\"} 2\nlog_word_count{word=\"a,\\\"b|\"} 1\n"
+        );
+        assert_eq!(render(OutputFormat::Repeated), "disk\ndisk\na,\"b|\n");
+        // Parquet is binary and written by write_parquet only
+        assert!(stream_results(rows, &mut Vec::new(), OutputFormat::Parquet).is_err());
+    }
+
+    /// Test streaming a large result against output built row by row, for
+    /// words that need escaping
+    #[test]
+    fn test_stream_large_results() {
+        let results: Vec<(String, usize)> = (0..50_000)
+            .map(|index| (format!("w{}\"|,{}", index % 7, index), 50_000 - index))
+            .collect();
+        let render = |format| {
+            let mut buffer = Vec::new();
+            stream_results(results.iter().cloned(), &mut buffer, format).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+        let rows = |header: &str, row: &dyn Fn(&str, usize) -> String| {
+            let body: String = results
+                .iter()
+                .map(|(word, count)| row(word, *count))
+                .collect();
+            format!("{}{}", header, body)
+        };
+
+        // The debug format used to be the `{:?}` of the whole list
+        assert_eq!(render(OutputFormat::Debug), format!("{:?}\n", results));
+        let objects: Vec<String> = results
+            .iter()
+            .map(|(word, count)| format!("{{\"word\":{:?},\"count\":{}}}", word, count))
+            .collect();
+        assert_eq!(
+            render(OutputFormat::Json),
+            format!("[{}]\n", objects.join(","))
+        );
+        assert_eq!(
+            render(OutputFormat::Csv),
+            rows("word,count\n", &|word, count| {
+                format!("\"{}\",{}\n", word.replace('"', "\"\""), count)
+            })
+        );
+        assert_eq!(
+            render(OutputFormat::Tsv),
+            rows("word\tcount\n", &|word, count| format!(
+                "{}\t{}\n",
+                word, count
+            ))
+        );
+        assert_eq!(
+            render(OutputFormat::Lines),
+            rows("", &|word, count| format!("{} {}\n", count, word))
+        );
+        assert_eq!(
+            render(OutputFormat::Prometheus),
+            rows("# TYPE log_word_count gauge\n", &|word, count| {
+                format!(
+                    "log_word_count{{word=\"{}\"}} {}\n",
+                    word.replace('"', "\\\""),
+                    count
+                )
+            })
+        );
+    }
+
+    /// Test that weighted scores are written under the `score` key
+    #[test]
+    fn test_json_scores() {
+        let mut buffer = Vec::new();
+        write_list(
+            &[("kernel".to_string(), 5.0), ("retry".to_string(), 2.5)],
+            &mut buffer,
+            OutputFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "[{\"word\":\"kernel\",\"score\":5},{\"word\":\"retry\",\"score\":2.5}]\n"
+        );
+    }
+
+    /// Test write_results into a byte buffer for every format
+    #[test]
+    fn test_write_results_formats() {
+        let results = vec![("error".to_string(), 3), ("disk".to_string(), 2)];
+        let render = |format| {
+            let mut buffer = Vec::new();
+            write_results(&results, &mut buffer, format).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            render(OutputFormat::Debug),
+            "[(\"error\", 3), (\"disk\", 2)]\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Json),
+            "[{\"word\":\"error\",\"count\":3},{\"word\":\"disk\",\"count\":2}]\n"
+        );
+        assert_eq!(render(OutputFormat::Csv), "word,count\nerror,3\ndisk,2\n");
+        assert_eq!(
+            render(OutputFormat::Tsv),
+            "word\tcount\nerror\t3\ndisk\t2\n"
+        );
+    }
+
+    /// Test that the top word gets the maximum weight and the rarest the minimum
+    #[test]
+    fn test_wordcloud_scaling() {
+        let results = vec![
+            ("error".to_string(), 50),
+            ("disk".to_string(), 30),
+            ("retry".to_string(), 10),
+        ];
+        let render = |range| {
+            let mut buffer = Vec::new();
+            write_results(&results, &mut buffer, OutputFormat::WordCloud(range)).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            render(WeightRange::default()),
+            "[{\"text\":\"error\",\"weight\":100},\
+             {\"text\":\"disk\",\"weight\":55},\
+             {\"text\":\"retry\",\"weight\":10}]\n"
+        );
+        assert_eq!(
+            render("1-5".parse().unwrap()),
+            "[{\"text\":\"error\",\"weight\":5},\
+             {\"text\":\"disk\",\"weight\":3},\
+             {\"text\":\"retry\",\"weight\":1}]\n"
+        );
+        assert!("100-10".parse::<WeightRange>().is_err());
+    }
+
+    /// Test that repeated output holds each word `count` times
+    #[test]
+    fn test_repeated_and_lines() {
+        let results = vec![("error".to_string(), 3), ("disk".to_string(), 1)];
+        let render = |format| {
+            let mut buffer = Vec::new();
+            write_results(&results, &mut buffer, format).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let repeated = render(OutputFormat::Repeated);
+        assert_eq!(repeated.lines().filter(|line| *line == "error").count(), 3);
+        assert_eq!(repeated, "error\nerror\nerror\ndisk\n");
+        assert_eq!(render(OutputFormat::Lines), "3 error\n1 disk\n");
+    }
+
+    /// Test the aligned comparison table and its CSV layout
+    #[test]
+    fn test_write_comparison() {
+        let rows = crate::compare::compare_rankings(
+            &[("error".to_string(), 12), ("disk".to_string(), 3)],
+            &[("timeout".to_string(), 4), ("error".to_string(), 2)],
+        );
+        let render = |format| {
+            let mut buffer = Vec::new();
+            write_comparison(&rows, ["old.log", "new.log"], &mut buffer, format).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            render(OutputFormat::Debug),
+            "word     old.log  new.log\n\
+             error    #1 12    #2 2\n\
+             disk     #2 3     -\n\
+             timeout  -        #1 4\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Csv),
+            "word,first_rank,first_count,second_rank,second_count\n\
+             error,1,12,2,2\ndisk,2,3,,\ntimeout,,,1,4\n"
+        );
+    }
+
+    /// Test Prometheus samples and the escaping of label values
+    #[test]
+    fn test_prometheus_format() {
+        let results = vec![
+            ("error".to_string(), 42),
+            ("say \"hi\"\\now\n".to_string(), 1),
+        ];
+        let mut buffer = Vec::new();
+
+        write_results(&results, &mut buffer, OutputFormat::Prometheus).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "# TYPE log_word_count gauge\n\
+             log_word_count{word=\"error\"} 42\n\
+             log_word_count{word=\"say \\\"hi\\\"\\\\now\\n\"} 1\n"
+        );
+    }
+
+    /// Test the Markdown table layout and escaping of pipes in words
+    #[test]
+    fn test_markdown_table() {
+        let results = vec![("error".to_string(), 3), ("a|b".to_string(), 1)];
+        let mut buffer = Vec::new();
+
+        write_results(&results, &mut buffer, OutputFormat::Markdown).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "| Rank | Word | Count |\n\
+             |---:|---|---:|\n\
+             | 1 | error | 3 |\n\
+             | 2 | a\\|b | 1 |\n"
+        );
+    }
+
+    /// Test escaping of separators inside delimited fields
+    #[test]
+    fn test_delimited_field_escaping() {
+        assert_eq!(field("a,b", OutputFormat::Csv), "\"a,b\"");
+        assert_eq!(field("say \"hi\"", OutputFormat::Csv), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("plain", OutputFormat::Csv), "plain");
+        assert_eq!(field("a\tb", OutputFormat::Tsv), "a\\tb");
+    }
+
+    /// Test that sectioned JSON output is wrapped whole in the envelope
+    #[test]
+    fn test_json_envelope_wraps_sections() {
+        let sections = vec![("a.log".to_string(), vec![("error".to_string(), 2)])];
+        let mut buffer = Vec::new();
+
+        let mut envelope = JsonEnvelope::new(&mut buffer).unwrap();
+        write_sections(&sections, "file", &mut envelope, OutputFormat::Json).unwrap();
+        envelope.finish().unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["schema_version"], JSON_SCHEMA_VERSION);
+        assert!(value["results"].is_array() || value["results"].is_object());
+        assert!(text.ends_with("}\n") && !text.ends_with("\n}\n"));
+    }
+
+    /// Test that words land in the highest tier they reach
+    #[test]
+    fn test_group_by_tier() {
+        let results = vec![
+            ("error".to_string(), 250),
+            ("disk".to_string(), 100),
+            ("timeout".to_string(), 99),
+            ("retry".to_string(), 10),
+            ("dns".to_string(), 3),
+        ];
+
+        let tiers = group_by_tier(&results, &[100, 10, 5]);
+
+        assert_eq!(
+            tiers,
+            vec![
+                (
+                    ">= 100".to_string(),
+                    vec![("error".to_string(), 250), ("disk".to_string(), 100)]
+                ),
+                (
+                    ">= 10".to_string(),
+                    vec![("timeout".to_string(), 99), ("retry".to_string(), 10)]
+                ),
+                ("< 5".to_string(), vec![("dns".to_string(), 3)]),
+            ]
+        );
+    }
+
+    /// Test that words land in the group of their initial letter
+    #[test]
+    fn test_group_by_initial() {
+        let results = vec![
+            ("error".to_string(), 5),
+            ("disk".to_string(), 4),
+            ("Eof".to_string(), 3),
+            ("_tmp".to_string(), 2),
+            ("dns".to_string(), 1),
+        ];
+
+        let groups = group_by_initial(&results);
+
+        assert_eq!(
+            groups,
+            vec![
+                ("#".to_string(), vec![("_tmp".to_string(), 2)]),
+                (
+                    "D".to_string(),
+                    vec![("disk".to_string(), 4), ("dns".to_string(), 1)]
+                ),
+                (
+                    "E".to_string(),
+                    vec![("error".to_string(), 5), ("Eof".to_string(), 3)]
+                ),
+            ]
+        );
+    }
+}
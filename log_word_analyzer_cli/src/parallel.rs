@@ -0,0 +1,312 @@
+//! Multi-threaded counting built on rayon.
+//!
+//! The parallel functions produce exactly the same results as their
+//! sequential counterparts, independent of the number of threads: lines are
+//! split into fixed-size chunks, each chunk is counted into its own map, and
+//! the maps are merged in chunk order before the final ranking.
+//!
+//! Functions reporting line numbers count each chunk's lines from the
+//! chunk's base offset in the input, so they number lines exactly as a
+//! sequential scan does.
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::filter::{FilterStats, TokenFilter};
+use crate::positions::positions_by_key;
+use crate::{WordCounter, count_line, for_each_word, select_top_k};
+
+/// Number of lines counted together by one rayon task.
+const CHUNK_LINES: usize = 4096;
+
+/// Counts word occurrences across all log lines using the rayon thread pool.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+///
+/// # Returns
+///
+/// The same frequency map [`crate::count_words`] would produce.
+pub fn count_words_parallel(logs: &[String]) -> HashMap<String, usize> {
+    logs.par_chunks(CHUNK_LINES)
+        .map(|chunk| {
+            // Count one chunk into a map owned by the current task
+            let mut frequency_map = HashMap::new();
+            for line in chunk {
+                count_line(&mut frequency_map, line);
+            }
+            frequency_map
+        })
+        // Indexed reduction combines neighbouring chunks in input order
+        .reduce(HashMap::new, merge_counts)
+}
+
+/// Counts word occurrences in parallel while applying a token filter.
+///
+/// # Returns
+///
+/// The same frequency map and filter statistics a sequential
+/// [`WordCounter`] would produce over all lines. A filter whose result
+/// depends on line order, see [`TokenFilter::is_order_dependent`], is
+/// applied on the current thread instead, since chunks filling their own
+/// vocabulary would keep other words than one pass over the input.
+pub fn count_words_parallel_filtered(
+    logs: &[String],
+    filter: &TokenFilter,
+) -> (HashMap<String, usize>, FilterStats) {
+    if filter.is_order_dependent() {
+        let mut counter = WordCounter::new(filter);
+        counter.add_lines(logs);
+        return counter.into_parts();
+    }
+    logs.par_chunks(CHUNK_LINES)
+        .map(|chunk| {
+            let mut counter = WordCounter::new(filter);
+            counter.add_lines(chunk);
+            counter
+        })
+        .reduce(
+            || WordCounter::new(filter),
+            |mut left, right| {
+                left.merge(right);
+                left
+            },
+        )
+        .into_parts()
+}
+
+/// Finds the top K words like [`crate::top_k_words`], counting in parallel.
+///
+/// Ranking uses the same total order (frequency descending, then word
+/// ascending), so the output is identical for any thread count.
+pub fn top_k_words_parallel(logs: &[String], k: usize) -> Vec<(String, usize)> {
+    select_top_k(count_words_parallel(logs), k)
+}
+
+/// Finds the lines on which each of `words` occurs like
+/// [`crate::positions::word_positions`], scanning chunks in parallel.
+///
+/// # Returns
+///
+/// The same line numbers, from 1, and the same earliest `limit` lines
+/// per word as the sequential scan.
+pub fn word_positions_parallel(
+    logs: &[String],
+    words: &[String],
+    limit: usize,
+) -> HashMap<String, Vec<usize>> {
+    positions_by_key_parallel(logs, words, limit, |word| Some(word.to_string()))
+}
+
+/// Like [`word_positions_parallel`], but a token occurs as the word `key`
+/// maps it to, as in [`positions_by_key`].
+pub(crate) fn positions_by_key_parallel(
+    logs: &[String],
+    words: &[String],
+    limit: usize,
+    key: impl Fn(&str) -> Option<String> + Sync,
+) -> HashMap<String, Vec<usize>> {
+    let mut positions = logs
+        .par_chunks(CHUNK_LINES)
+        .enumerate()
+        .map(|(chunk_index, chunk)| {
+            // Shift the chunk's own line numbers by the lines before it
+            let base = chunk_index * CHUNK_LINES;
+            let mut positions = positions_by_key(chunk, words, limit, &key);
+            for lines in positions.values_mut() {
+                lines.iter_mut().for_each(|number| *number += base);
+            }
+            positions
+        })
+        .reduce(HashMap::new, |mut left, right| {
+            // The left map holds the earlier lines, so it fills up first
+            for (word, lines) in right {
+                let kept = left.entry(word).or_default();
+                let room = limit.saturating_sub(kept.len());
+                kept.extend(lines.into_iter().take(room));
+            }
+            left
+        });
+    for word in words {
+        positions.entry(word.clone()).or_default();
+    }
+    positions
+}
+
+/// Finds the top K words with a sample line for each like
+/// [`crate::top_k_with_samples`], counting in parallel.
+///
+/// Each sample is the first line the word occurs on in the whole input,
+/// as in the sequential function, whichever chunk is counted first.
+pub fn top_k_with_samples_parallel(logs: &[String], k: usize) -> Vec<(String, usize, String)> {
+    let (frequency_map, first_lines) = logs
+        .par_chunks(CHUNK_LINES)
+        .enumerate()
+        .map(|(chunk_index, chunk)| {
+            let base = chunk_index * CHUNK_LINES;
+            let mut frequency_map: HashMap<String, usize> = HashMap::new();
+            let mut first_lines: HashMap<String, usize> = HashMap::new();
+            for (index, line) in chunk.iter().enumerate() {
+                for_each_word(line, |word| {
+                    if let Some(count) = frequency_map.get_mut(word) {
+                        *count += 1;
+                    } else {
+                        frequency_map.insert(word.to_string(), 1);
+                        first_lines.insert(word.to_string(), base + index);
+                    }
+                });
+            }
+            (frequency_map, first_lines)
+        })
+        .reduce(
+            || (HashMap::new(), HashMap::new()),
+            |(left_counts, mut left_lines), (right_counts, right_lines)| {
+                // A word already seen on the left first occurred there
+                for (word, index) in right_lines {
+                    left_lines.entry(word).or_insert(index);
+                }
+                (merge_counts(left_counts, right_counts), left_lines)
+            },
+        );
+
+    select_top_k(frequency_map, k)
+        .into_iter()
+        .map(|(word, count)| {
+            let sample = logs[first_lines[&word]].clone();
+            (word, count, sample)
+        })
+        .collect()
+}
+
+/// Merges two partial frequency maps, folding the smaller into the larger.
+fn merge_counts(
+    mut left: HashMap<String, usize>,
+    mut right: HashMap<String, usize>,
+) -> HashMap<String, usize> {
+    if left.len() < right.len() {
+        std::mem::swap(&mut left, &mut right);
+    }
+    for (word, count) in right {
+        *left.entry(word).or_insert(0) += count;
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positions::word_positions;
+    use crate::{top_k_with_samples, top_k_words};
+
+    /// Builds a log large enough to span many chunks with plenty of ties
+    fn sample_logs() -> Vec<String> {
+        (0..20_000)
+            .map(|i| format!("Error code{} disk{} host{} retry", i % 97, i % 13, i % 7))
+            .collect()
+    }
+
+    /// Test that parallel counting matches the sequential result exactly
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let logs = sample_logs();
+        assert_eq!(top_k_words_parallel(&logs, 50), top_k_words(&logs, 50));
+    }
+
+    /// Test byte-identical output for 1, 2, 4 and 8 threads
+    #[test]
+    fn test_parallel_deterministic_across_thread_counts() {
+        let logs = sample_logs();
+        let expected = format!("{:?}", top_k_words(&logs, 200));
+
+        for threads in [1, 2, 4, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let output = pool.install(|| format!("{:?}", top_k_words_parallel(&logs, 200)));
+            assert_eq!(output, expected, "output differs with {} threads", threads);
+        }
+    }
+
+    /// Test that filtered parallel counting matches the sequential counter
+    #[test]
+    fn test_parallel_filtered_matches_sequential() {
+        let logs = sample_logs();
+        let mut filter = TokenFilter::default();
+        filter.add_stop_words(["retry", "error"]);
+
+        let mut counter = WordCounter::new(&filter);
+        counter.add_lines(&logs);
+
+        assert_eq!(
+            count_words_parallel_filtered(&logs, &filter),
+            counter.into_parts()
+        );
+    }
+
+    /// Test that a unique-word limit or pruning keeps the words a single
+    /// pass keeps when the input spans several chunks
+    #[test]
+    fn test_parallel_order_dependent_filters_match_sequential() {
+        let mut logs = vec!["a".to_string(); CHUNK_LINES];
+        logs.push("b a".to_string());
+        logs.extend(sample_logs());
+        let filters = [
+            TokenFilter {
+                max_unique: Some(1),
+                ..TokenFilter::default()
+            },
+            TokenFilter {
+                prune_threshold: Some(3),
+                ..TokenFilter::default()
+            },
+        ];
+
+        for filter in &filters {
+            let mut counter = WordCounter::new(filter);
+            counter.add_lines(&logs);
+            let expected = counter.into_parts();
+            assert_eq!(count_words_parallel_filtered(&logs, filter), expected);
+        }
+        let (counts, _) = count_words_parallel_filtered(&logs, &filters[0]);
+        assert_eq!(counts["a"], CHUNK_LINES + 1);
+    }
+
+    /// Test that positions and first lines found in parallel chunks match
+    /// the sequential line numbers exactly, for any thread count
+    #[test]
+    fn test_parallel_line_numbers_match_sequential() {
+        let mut logs = sample_logs();
+        // A word only occurring late, in the last chunk
+        logs.push("latecomer".to_string());
+        let words: Vec<String> = ["code5", "disk12", "retry", "latecomer", "absent"]
+            .map(String::from)
+            .to_vec();
+
+        for limit in [3, 100_000] {
+            let expected = word_positions(&logs, &words, limit);
+            for threads in [1, 2, 8] {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap();
+                let positions = pool.install(|| word_positions_parallel(&logs, &words, limit));
+                assert_eq!(
+                    positions, expected,
+                    "positions differ with {} threads",
+                    threads
+                );
+            }
+        }
+        assert_eq!(
+            word_positions_parallel(&logs, &words, 100_000)["latecomer"],
+            vec![20_001]
+        );
+        assert_eq!(
+            top_k_with_samples_parallel(&logs, 200),
+            top_k_with_samples(&logs, 200)
+        );
+    }
+}
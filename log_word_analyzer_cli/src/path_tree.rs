@@ -0,0 +1,236 @@
+//! Prefix trees of slash-separated paths with aggregate counts, showing
+//! which subtrees of an API or a file system are hottest.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::output::json_string;
+use crate::tokenize::{Tokenizer, WhitespaceTokenizer};
+
+/// One node of a path tree: a path segment with the number of paths
+/// passing through it.
+///
+/// A node counts every path ending at or below it, so its count is the
+/// sum of its children's counts plus the paths ending exactly there.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::path_tree::PathTree;
+///
+/// let mut tree = PathTree::new();
+/// tree.insert("/api/v1/users");
+/// tree.insert("/api/v1/orders");
+/// tree.insert("/health");
+///
+/// assert_eq!(tree.count, 3);
+/// assert_eq!(tree.children["api"].count, 2);
+/// assert_eq!(tree.children["api"].children["v1"].children["users"].count, 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathTree {
+    /// Number of paths ending at or below this node
+    pub count: usize,
+    /// Child nodes by path segment
+    pub children: BTreeMap<String, PathTree>,
+}
+
+impl PathTree {
+    /// Creates an empty tree.
+    pub fn new() -> Self {
+        PathTree::default()
+    }
+
+    /// Adds one occurrence of `path`, counting it at every node from the
+    /// root down to its last segment.
+    ///
+    /// Empty segments, as in `/api//v1/`, are skipped.
+    pub fn insert(&mut self, path: &str) {
+        self.count += 1;
+        let mut node = self;
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            node = node.children.entry(segment.to_string()).or_default();
+            node.count += 1;
+        }
+    }
+
+    /// The children of this node by count (descending) and alphabetically
+    /// for ties.
+    pub fn ranked_children(&self) -> Vec<(&str, &PathTree)> {
+        let mut children: Vec<(&str, &PathTree)> = self
+            .children
+            .iter()
+            .map(|(segment, child)| (segment.as_str(), child))
+            .collect();
+        children.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+        children
+    }
+
+    /// Writes the tree one node per line as `path count`, indenting each
+    /// level by two spaces and showing at most `limit` children per node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::path_tree::PathTree;
+    ///
+    /// let mut tree = PathTree::new();
+    /// tree.insert("/api/users");
+    /// tree.insert("/api/orders");
+    /// tree.insert("/api/users");
+    ///
+    /// let mut out = Vec::new();
+    /// tree.write_indented(10, &mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "/ 3\n  /api 3\n    /api/users 2\n    /api/orders 1\n"
+    /// );
+    /// ```
+    pub fn write_indented<W: Write>(&self, limit: usize, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "/ {}", self.count)?;
+        self.write_children("", 1, limit, writer)
+    }
+
+    fn write_children<W: Write>(
+        &self,
+        prefix: &str,
+        depth: usize,
+        limit: usize,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        for (segment, child) in self.ranked_children().into_iter().take(limit) {
+            let path = format!("{}/{}", prefix, segment);
+            writeln!(
+                writer,
+                "{:indent$}{} {}",
+                "",
+                path,
+                child.count,
+                indent = depth * 2
+            )?;
+            child.write_children(&path, depth + 1, limit, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the tree as one nested JSON object of `path`, `count` and
+    /// `children`, showing at most `limit` children per node.
+    pub fn write_json<W: Write>(&self, limit: usize, writer: &mut W) -> io::Result<()> {
+        self.write_json_node("/", limit, writer)?;
+        writeln!(writer)
+    }
+
+    fn write_json_node<W: Write>(
+        &self,
+        path: &str,
+        limit: usize,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "{{\"path\":{},\"count\":{},\"children\":[",
+            json_string(path),
+            self.count
+        )?;
+        let prefix = path.trim_end_matches('/');
+        for (index, (segment, child)) in self.ranked_children().into_iter().take(limit).enumerate()
+        {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            child.write_json_node(&format!("{}/{}", prefix, segment), limit, writer)?;
+        }
+        write!(writer, "]}}")
+    }
+}
+
+/// Extracts the path of a whitespace token that looks like one: it starts
+/// with a slash and has at least one segment. A query string or fragment
+/// is cut off, so `/users?id=7` is the path `/users`.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::path_tree::as_path;
+///
+/// assert_eq!(as_path("/api/users?id=7"), Some("/api/users"));
+/// assert_eq!(as_path("/"), None);
+/// assert_eq!(as_path("api/users"), None);
+/// ```
+pub fn as_path(token: &str) -> Option<&str> {
+    let path = token.split(['?', '#']).next().unwrap_or(token);
+    (path.starts_with('/') && path.split('/').any(|segment| !segment.is_empty())).then_some(path)
+}
+
+/// Builds the path tree of every path-like token across all log lines.
+///
+/// Lines are split on whitespace by [`WhitespaceTokenizer`], so paths are
+/// lowercased and lose trailing punctuation.
+pub fn path_tree(logs: &[String]) -> PathTree {
+    let mut tree = PathTree::new();
+    for line in logs {
+        for token in WhitespaceTokenizer.tokenize(line) {
+            if let Some(path) = as_path(&token) {
+                tree.insert(path);
+            }
+        }
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a parent's count equals the sum of its children when no
+    /// path ends at the parent itself
+    #[test]
+    fn test_parent_count_is_sum_of_children() {
+        let logs = vec![
+            "GET /api/v1/users 200".to_string(),
+            "GET /api/v1/users/7 200".to_string(),
+            "POST /api/v1/orders 201".to_string(),
+            "GET /api/v2/users?page=2 200".to_string(),
+            "GET /health 200".to_string(),
+        ];
+
+        let tree = path_tree(&logs);
+
+        let api = &tree.children["api"];
+        assert_eq!(tree.count, 5);
+        assert_eq!(api.count, 4);
+        assert_eq!(
+            api.count,
+            api.children
+                .values()
+                .map(|child| child.count)
+                .sum::<usize>()
+        );
+        let v1 = &api.children["v1"];
+        assert_eq!(v1.count, 3);
+        assert_eq!(
+            v1.count,
+            v1.children.values().map(|child| child.count).sum::<usize>()
+        );
+        // "/api/v1/users" ends at the node, "/api/v1/users/7" below it
+        assert_eq!(v1.children["users"].count, 2);
+        assert_eq!(v1.children["users"].children["7"].count, 1);
+    }
+
+    /// Test that the JSON nests children by count and honours the limit
+    #[test]
+    fn test_write_json() {
+        let mut tree = PathTree::new();
+        for path in ["/a/x", "/a/y", "/a/y", "/b"] {
+            tree.insert(path);
+        }
+
+        let mut out = Vec::new();
+        tree.write_json(1, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"path\":\"/\",\"count\":4,\"children\":[{\"path\":\"/a\",\"count\":3,\"children\":[{\"path\":\"/a/y\",\"count\":2,\"children\":[]}]}]}\n"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Line numbers at which selected words occur, for drilling down from a
+//! report to the lines behind it.
+
+use std::collections::HashMap;
+
+use crate::for_each_word;
+
+/// Finds the lines on which each of `words` occurs.
+///
+/// Lines are numbered from 1 and a line is listed once however often the
+/// word occurs on it. At most `limit` lines are kept per word, the
+/// earliest ones.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::positions::word_positions;
+///
+/// let logs: Vec<String> = ["error disk", "ok", "error error"].map(String::from).to_vec();
+/// let positions = word_positions(&logs, &["error".to_string()], 10);
+/// assert_eq!(positions["error"], vec![1, 3]);
+/// ```
+pub fn word_positions(
+    logs: &[String],
+    words: &[String],
+    limit: usize,
+) -> HashMap<String, Vec<usize>> {
+    positions_by_key(logs, words, limit, |word| Some(word.to_string()))
+}
+
+/// Like [`word_positions`], but a token occurs as the word `key` maps it
+/// to, such as its lemma; tokens mapped to `None` are skipped.
+pub(crate) fn positions_by_key(
+    logs: &[String],
+    words: &[String],
+    limit: usize,
+    key: impl Fn(&str) -> Option<String>,
+) -> HashMap<String, Vec<usize>> {
+    let mut positions: HashMap<String, Vec<usize>> = words
+        .iter()
+        .map(|word| (word.clone(), Vec::new()))
+        .collect();
+
+    for (index, line) in logs.iter().enumerate() {
+        let number = index + 1;
+        for_each_word(line, |token| {
+            let Some(lines) = key(token).and_then(|word| positions.get_mut(&word)) else {
+                return;
+            };
+            if lines.len() < limit && lines.last() != Some(&number) {
+                lines.push(number);
+            }
+        });
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test correct, deduplicated and capped line numbers
+    #[test]
+    fn test_positions_capped() {
+        let logs: Vec<String> = (1..=8)
+            .map(|number| match number % 3 {
+                0 => "error error disk".to_string(),
+                1 => "disk ok".to_string(),
+                _ => "retry".to_string(),
+            })
+            .collect();
+        let words = [
+            "error".to_string(),
+            "disk".to_string(),
+            "absent".to_string(),
+        ];
+
+        let positions = word_positions(&logs, &words, 3);
+
+        assert_eq!(positions["error"], vec![3, 6]);
+        assert_eq!(positions["disk"], vec![1, 3, 4]);
+        assert_eq!(positions["absent"], Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-line text transforms applied before tokenizing.
+
+use std::borrow::Cow;
+
+use regex::Regex;
+use serde::Serialize;
+
+/// Configurable pipeline of transforms run on every line before counting.
+///
+/// Transforms apply in this fixed order:
+///
+/// 1. `trim` removes leading and trailing whitespace
+/// 2. `strip_prefix` removes a regex match anchored at the start of the line
+/// 3. `redact` replaces every match of each pattern with [`REDACTED`]
+/// 4. `hex` replaces every match with [`HEX`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Preprocessor {
+    /// Trim surrounding whitespace from each line
+    pub trim: bool,
+    /// Remove text matching this pattern when it starts at the first character
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub strip_prefix: Option<Regex>,
+    /// Patterns of sensitive text, applied in order
+    #[serde(serialize_with = "crate::serialize::regexes")]
+    pub redact: Vec<Regex>,
+    /// Pattern of hexadecimal tokens such as addresses and hashes, see
+    /// [`Preprocessor::hex_pattern`]
+    #[serde(serialize_with = "crate::serialize::option_regex")]
+    pub hex: Option<Regex>,
+}
+
+/// Placeholder substituted for redacted text; it is counted as the word
+/// `redacted`.
+pub const REDACTED: &str = "<redacted>";
+
+/// Placeholder substituted for hexadecimal tokens; it is counted as the
+/// word `hex`.
+pub const HEX: &str = "<hex>";
+
+/// Shortest bare hexadecimal token `--normalize-hex` replaces by default.
+pub const DEFAULT_HEX_MIN_LEN: usize = 8;
+
+impl Preprocessor {
+    /// Returns true when no transform is configured.
+    pub fn is_empty(&self) -> bool {
+        !self.trim && self.strip_prefix.is_none() && self.redact.is_empty() && self.hex.is_none()
+    }
+
+    /// Builds the pattern of hexadecimal tokens: `0x`-prefixed numbers of
+    /// any length and whole words of at least `min_len` hex digits, such
+    /// as `7f3a9c2e` or a SHA-1 hash.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::preprocess::Preprocessor;
+    ///
+    /// let hex = Preprocessor::hex_pattern(8);
+    /// assert!(hex.is_match("fault at 0x1f"));
+    /// assert!(hex.is_match("commit 9fceb02d0ae598e95dc970b74767f19372d61af8"));
+    /// assert!(!hex.is_match("cafe 1234567"));
+    /// ```
+    pub fn hex_pattern(min_len: usize) -> Regex {
+        Regex::new(&format!(
+            r"\b(?:0[xX][0-9a-fA-F]+|[0-9a-fA-F]{{{},}})\b",
+            min_len
+        ))
+        .expect("the hex pattern is valid")
+    }
+
+    /// Runs the configured transforms on a single line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::preprocess::Preprocessor;
+    /// use regex::Regex;
+    ///
+    /// let preprocessor = Preprocessor {
+    ///     trim: true,
+    ///     strip_prefix: Some(Regex::new(r"\d{2}:\d{2}:\d{2}\s*").unwrap()),
+    ///     ..Preprocessor::default()
+    /// };
+    /// assert_eq!(preprocessor.apply("  12:00:01 disk full "), "disk full");
+    /// ```
+    pub fn apply(&self, line: &str) -> String {
+        let mut text = line;
+        if self.trim {
+            text = text.trim();
+        }
+        if let Some(prefix) = &self.strip_prefix {
+            // Only a match at the very start of the line counts as a prefix
+            if let Some(found) = prefix.find(text).filter(|found| found.start() == 0) {
+                text = &text[found.end()..];
+            }
+        }
+        let mut line = text.to_string();
+        for pattern in &self.redact {
+            if let Cow::Owned(redacted) = pattern.replace_all(&line, REDACTED) {
+                line = redacted;
+            }
+        }
+        if let Some(hex) = &self.hex
+            && let Cow::Owned(normalized) = hex.replace_all(&line, HEX)
+        {
+            line = normalized;
+        }
+        line
+    }
+
+    /// Applies the transforms to every line of an input.
+    pub fn apply_all(&self, logs: Vec<String>) -> Vec<String> {
+        logs.iter().map(|line| self.apply(line)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::top_k_words;
+
+    /// Test that a stripped timestamp prefix is not counted
+    #[test]
+    fn test_strip_timestamp_prefix() {
+        let preprocessor = Preprocessor {
+            strip_prefix: Some(Regex::new(r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}Z ").unwrap()),
+            ..Preprocessor::default()
+        };
+        let logs = vec![
+            "2024-05-01T10:00:00Z error disk full".to_string(),
+            "2024-05-01T10:00:05Z error disk full".to_string(),
+            "error 2024".to_string(),
+        ];
+
+        let result = top_k_words(&preprocessor.apply_all(logs), 10);
+
+        assert_eq!(
+            result,
+            vec![
+                ("error".to_string(), 3),
+                ("disk".to_string(), 2),
+                ("full".to_string(), 2),
+                ("2024".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Test that trimming runs before the anchored prefix match
+    #[test]
+    fn test_trim_before_strip_prefix() {
+        let preprocessor = Preprocessor {
+            trim: true,
+            strip_prefix: Some(Regex::new(r"\[\w+\]").unwrap()),
+            ..Preprocessor::default()
+        };
+
+        assert_eq!(preprocessor.apply("   [app] started  "), " started");
+        assert_eq!(preprocessor.apply("started [app]"), "started [app]");
+    }
+
+    /// Test that every email address is counted under the placeholder
+    #[test]
+    fn test_redact_emails() {
+        let preprocessor = Preprocessor {
+            redact: vec![
+                Regex::new(r"[\w.+-]+@[\w-]+(\.[\w-]+)+").unwrap(),
+                Regex::new(r"\b\d{1,3}(\.\d{1,3}){3}\b").unwrap(),
+            ],
+            ..Preprocessor::default()
+        };
+        let logs = vec![
+            "login failed for alice@example.com from 10.0.0.7".to_string(),
+            "login failed for bob.smith+ops@corp.example.org".to_string(),
+            "no account data".to_string(),
+        ];
+
+        let redacted = preprocessor.apply_all(logs);
+        let result = top_k_words(&redacted, 10);
+
+        assert_eq!(redacted[0], "login failed for <redacted> from <redacted>");
+        assert_eq!(
+            &result[..3],
+            &[
+                ("redacted".to_string(), 3),
+                ("failed".to_string(), 2),
+                ("for".to_string(), 2),
+            ]
+        );
+        assert!(
+            result
+                .iter()
+                .all(|(word, _)| !["alice", "bob", "example", "corp"].contains(&word.as_str()))
+        );
+    }
+
+    /// Test that distinct hex addresses collapse into one placeholder word
+    #[test]
+    fn test_normalize_hex_addresses() {
+        let preprocessor = Preprocessor {
+            hex: Some(Preprocessor::hex_pattern(DEFAULT_HEX_MIN_LEN)),
+            ..Preprocessor::default()
+        };
+        let logs = vec![
+            "segfault at 0x7ffd5e8a1c20 in worker".to_string(),
+            "segfault at 0x7ffd5e8a3f08 in worker".to_string(),
+            "object deadbeef00112233 freed, id 1234".to_string(),
+        ];
+
+        let normalized = preprocessor.apply_all(logs);
+        let result = top_k_words(&normalized, 3);
+
+        assert_eq!(normalized[0], "segfault at <hex> in worker");
+        // Short numbers are left alone
+        assert_eq!(normalized[2], "object <hex> freed, id 1234");
+        assert_eq!(
+            result,
+            vec![
+                ("hex".to_string(), 3),
+                ("at".to_string(), 2),
+                ("in".to_string(), 2),
+            ]
+        );
+    }
+}
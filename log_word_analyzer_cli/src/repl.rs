@@ -0,0 +1,204 @@
+//! Interactive exploration of word counts that were computed once.
+//!
+//! `--interactive` reads and counts the inputs a single time, then hands
+//! the frequency map to a [`Repl`] which answers commands from stdin
+//! without touching the files again.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+
+use crate::output::{OutputFormat, write_list};
+use crate::select_top_k;
+
+/// Number of rows `top` prints when no count is given.
+const DEFAULT_TOP: usize = 10;
+
+/// Command summary printed by `help`.
+const HELP: &str = "\
+Commands:
+  top [n]          Show the n most frequent words (default 10)
+  count <word>     Show how often one word occurs
+  filter <substr>  Only show words containing substr (filters add up)
+  exclude <word>   Hide a word from the results
+  reset            Drop all filters and exclusions
+  help             Show this summary
+  quit             Leave (end of input works too)
+";
+
+/// Command loop over a cached frequency map.
+///
+/// Filters only change the view; the underlying counts stay untouched,
+/// so `reset` restores the full vocabulary.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use log_word_analyzer_cli::output::OutputFormat;
+/// use log_word_analyzer_cli::repl::Repl;
+///
+/// let counts = HashMap::from([("error".to_string(), 3), ("info".to_string(), 5)]);
+/// let mut repl = Repl::new(counts, OutputFormat::Debug);
+/// let (mut out, mut err) = (Vec::new(), Vec::new());
+/// repl.run("exclude info\ntop 1\n".as_bytes(), &mut out, &mut err).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "[(\"error\", 3)]\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Repl {
+    counts: HashMap<String, usize>,
+    format: OutputFormat,
+    /// Substrings every shown word must contain
+    includes: Vec<String>,
+    /// Words that are never shown
+    excludes: HashSet<String>,
+}
+
+impl Repl {
+    /// Creates a session over the given counts, printing in `format`.
+    pub fn new(counts: HashMap<String, usize>, format: OutputFormat) -> Self {
+        Repl {
+            counts,
+            format,
+            includes: Vec::new(),
+            excludes: HashSet::new(),
+        }
+    }
+
+    /// Runs commands line by line until `quit` or the end of the input.
+    ///
+    /// Query results go to `out`; confirmations and command errors go to
+    /// `err`, so a scripted session's stdout holds only results.
+    pub fn run<W: Write, E: Write>(
+        &mut self,
+        input: impl BufRead,
+        out: &mut W,
+        err: &mut E,
+    ) -> io::Result<()> {
+        writeln!(
+            err,
+            "ready: {} distinct words; type 'help' for commands",
+            self.counts.len()
+        )?;
+        for line in input.lines() {
+            if !self.execute(&line?, out, err)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes one command line.
+    ///
+    /// # Returns
+    ///
+    /// False when the session should end.
+    fn execute<W: Write, E: Write>(
+        &mut self,
+        line: &str,
+        out: &mut W,
+        err: &mut E,
+    ) -> io::Result<bool> {
+        let mut parts = line.split_whitespace();
+        let Some(command) = parts.next() else {
+            return Ok(true);
+        };
+        let argument = parts.next();
+        match (command, argument) {
+            ("quit" | "exit", None) => return Ok(false),
+            ("help", None) => write!(err, "{}", HELP)?,
+            ("top", None) => self.write_top(DEFAULT_TOP, out)?,
+            ("top", Some(n)) => match n.parse() {
+                Ok(n) => self.write_top(n, out)?,
+                Err(_) => writeln!(err, "error: top needs a number, got '{}'", n)?,
+            },
+            ("count", Some(word)) => {
+                let word = word.to_lowercase();
+                let count = self.counts.get(&word).copied().unwrap_or(0);
+                write_list(&[(word, count)], out, self.format)?;
+            }
+            ("filter", Some(substr)) => {
+                self.includes.push(substr.to_lowercase());
+                writeln!(
+                    err,
+                    "filter: words containing {}",
+                    self.includes.join(" and ")
+                )?;
+            }
+            ("exclude", Some(word)) => {
+                self.excludes.insert(word.to_lowercase());
+                writeln!(err, "exclude: {} words hidden", self.excludes.len())?;
+            }
+            ("reset", None) => {
+                self.includes.clear();
+                self.excludes.clear();
+                writeln!(err, "reset: showing all words")?;
+            }
+            _ => writeln!(
+                err,
+                "error: unknown command '{}'; type 'help' for commands",
+                line.trim()
+            )?,
+        }
+        Ok(true)
+    }
+
+    /// Writes the top `n` words passing the current filters.
+    fn write_top<W: Write>(&self, n: usize, out: &mut W) -> io::Result<()> {
+        let visible: HashMap<String, usize> = self
+            .counts
+            .iter()
+            .filter(|(word, _)| self.shows(word))
+            .map(|(word, count)| (word.clone(), *count))
+            .collect();
+        write_list(&select_top_k(visible, n), out, self.format)
+    }
+
+    /// Returns true when a word passes every filter and exclusion.
+    fn shows(&self, word: &str) -> bool {
+        !self.excludes.contains(word) && self.includes.iter().all(|substr| word.contains(substr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_words;
+
+    /// Test a scripted session of queries over one cached count
+    #[test]
+    fn test_scripted_session() {
+        let logs = vec![
+            "INFO start INFO ready".to_string(),
+            "ERROR disk error".to_string(),
+            "WARN stderr noise".to_string(),
+            "INFO errno 5".to_string(),
+        ];
+        let mut repl = Repl::new(count_words(&logs), OutputFormat::Debug);
+        let script = "top 2\n\
+                      filter err\n\
+                      top\n\
+                      exclude errno\n\
+                      top 5\n\
+                      count INFO\n\
+                      bogus\n\
+                      reset\n\
+                      top 1\n\
+                      quit\n\
+                      top 1\n";
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+
+        repl.run(script.as_bytes(), &mut out, &mut err).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[(\"info\", 3), (\"error\", 2)]\n\
+             [(\"error\", 2), (\"errno\", 1), (\"stderr\", 1)]\n\
+             [(\"error\", 2), (\"stderr\", 1)]\n\
+             [(\"info\", 3)]\n\
+             [(\"info\", 3)]\n"
+        );
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.starts_with("ready: 10 distinct words"));
+        assert!(err.contains("error: unknown command 'bogus'"));
+    }
+}
@@ -0,0 +1,81 @@
+//! Runs of identical consecutive lines, as produced by flapping services
+//! and retry loops.
+
+use std::collections::HashMap;
+
+use crate::select_top_k;
+
+/// Splits the input into maximal runs of identical consecutive lines.
+///
+/// Lines compare exactly, so any difference such as a timestamp ends a
+/// run; strip such parts first when they vary.
+///
+/// # Returns
+///
+/// `(line, run_length)` pairs in input order.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::runs::consecutive_runs;
+///
+/// let logs: Vec<String> = ["retry", "retry", "ok", "retry"].map(String::from).to_vec();
+/// assert_eq!(
+///     consecutive_runs(&logs),
+///     vec![("retry", 2), ("ok", 1), ("retry", 1)]
+/// );
+/// ```
+pub fn consecutive_runs(logs: &[String]) -> Vec<(&str, usize)> {
+    let mut runs: Vec<(&str, usize)> = Vec::new();
+    for line in logs {
+        match runs.last_mut() {
+            Some((previous, length)) if *previous == line => *length += 1,
+            _ => runs.push((line, 1)),
+        }
+    }
+    runs
+}
+
+/// Finds the K lines with the longest runs of consecutive repetition.
+///
+/// Each line is reported once, with its longest run. Blank lines end runs
+/// but are never reported.
+///
+/// # Returns
+///
+/// `(line, run_length)` pairs sorted by run length (descending) and
+/// alphabetically for ties.
+pub fn top_k_runs(logs: &[String], k: usize) -> Vec<(String, usize)> {
+    let mut longest: HashMap<String, usize> = HashMap::new();
+    for (line, length) in consecutive_runs(logs) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = longest.entry(line.to_string()).or_insert(0);
+        *entry = (*entry).max(length);
+    }
+    select_top_k(longest, k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that five consecutive repeats report a run length of 5
+    #[test]
+    fn test_top_k_runs() {
+        let mut logs: Vec<String> = vec!["connection reset, retrying".to_string(); 5];
+        logs.extend(["ok", "", "", "", "disk full", "disk full", "ok", "ok"].map(String::from));
+        // More occurrences overall, but never consecutive
+        logs.extend(["tick", "tock", "tick", "tock", "tick", "tock"].map(String::from));
+
+        assert_eq!(
+            top_k_runs(&logs, 3),
+            vec![
+                ("connection reset, retrying".to_string(), 5),
+                ("disk full".to_string(), 2),
+                ("ok".to_string(), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! `serialize_with` helpers for settings without a serde form of their
+//! own, as written by `--print-config`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Serialize, Serializer};
+
+/// Writes an optional regex as its pattern.
+pub(crate) fn option_regex<S: Serializer>(
+    regex: &Option<Regex>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    regex.as_ref().map(Regex::as_str).serialize(serializer)
+}
+
+/// Writes a regex as its pattern.
+pub(crate) fn regex<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(regex.as_str())
+}
+
+/// Writes regexes as the list of their patterns.
+pub(crate) fn regexes<S: Serializer>(regexes: &[Regex], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(regexes.iter().map(Regex::as_str))
+}
+
+/// Writes a set in ascending order, so the output is reproducible.
+pub(crate) fn sorted<T: Serialize + Ord, S: Serializer>(
+    set: &HashSet<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut items: Vec<&T> = set.iter().collect();
+    items.sort();
+    serializer.collect_seq(items)
+}
+
+/// Writes a map in ascending key order, so the output is reproducible.
+pub(crate) fn sorted_map<K: Serialize + Ord, V: Serialize, S: Serializer>(
+    map: &HashMap<K, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// Writes an optional duration as fractional seconds.
+pub(crate) fn option_secs<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| duration.as_secs_f64())
+        .serialize(serializer)
+}
@@ -0,0 +1,196 @@
+//! Log level detection and severity-weighted word frequencies.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::filter::{FilterStats, TokenFilter};
+use crate::{TieBreak, WordCounter, for_each_word, select_top_k_counts};
+
+/// Severity level of a log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    /// Parses a level keyword, accepting the usual aliases case-insensitively.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" | "notice" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" | "err" | "fatal" | "critical" | "crit" => Ok(LogLevel::Error),
+            _ => Err(format!("unknown log level '{}'", name)),
+        }
+    }
+}
+
+/// Detects the level of a log line from the first level keyword it contains.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::severity::{detect_level, LogLevel};
+///
+/// assert_eq!(detect_level("2024-01-01 WARNING: memory low"), Some(LogLevel::Warn));
+/// assert_eq!(detect_level("disk full"), None);
+/// ```
+pub fn detect_level(line: &str) -> Option<LogLevel> {
+    let mut level = None;
+    for_each_word(line, |word| {
+        if level.is_none() {
+            level = word.parse().ok();
+        }
+    });
+    level
+}
+
+/// Multipliers applied to the words of a line according to its level.
+///
+/// Lines without a detectable level, and levels without an explicit
+/// weight, use the default weight of `1.0`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SeverityWeights {
+    #[serde(serialize_with = "crate::serialize::sorted_map")]
+    weights: HashMap<LogLevel, f64>,
+}
+
+impl SeverityWeights {
+    /// Creates weights from explicit `(level, multiplier)` pairs.
+    pub fn new(weights: impl IntoIterator<Item = (LogLevel, f64)>) -> Self {
+        SeverityWeights {
+            weights: weights.into_iter().collect(),
+        }
+    }
+
+    /// Returns the multiplier for a line of the given level.
+    pub fn weight(&self, level: Option<LogLevel>) -> f64 {
+        level
+            .and_then(|level| self.weights.get(&level).copied())
+            .unwrap_or(1.0)
+    }
+}
+
+impl FromStr for SeverityWeights {
+    type Err = String;
+
+    /// Parses a comma-separated list such as `ERROR=5,WARN=2,INFO=1`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut weights = HashMap::new();
+        for entry in spec.split(',').filter(|entry| !entry.is_empty()) {
+            let (level, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected LEVEL=WEIGHT, got '{}'", entry))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight '{}' for level '{}'", weight, level))?;
+            weights.insert(level.trim().parse()?, weight);
+        }
+        Ok(SeverityWeights { weights })
+    }
+}
+
+/// Finds the top K words by severity-weighted frequency.
+///
+/// Every occurrence of a word adds the weight of its line's level instead
+/// of `1`, so rare words in severe lines can outrank common ones in
+/// routine lines.
+///
+/// # Arguments
+///
+/// * `logs` - A slice of strings containing log lines to process
+/// * `k` - The number of top words to return
+/// * `weights` - Multipliers per detected log level
+///
+/// # Returns
+///
+/// A vector of `(word, score)` tuples sorted by score (descending) and
+/// alphabetically for ties.
+pub fn top_k_weighted_by_severity(
+    logs: &[String],
+    k: usize,
+    weights: &SeverityWeights,
+) -> Vec<(String, f64)> {
+    let (score_map, _) = weighted_scores(logs, weights, &TokenFilter::default());
+    select_top_k_scored(score_map, k)
+}
+
+/// Sums the severity weights of every word the filter keeps.
+pub(crate) fn weighted_scores(
+    logs: &[String],
+    weights: &SeverityWeights,
+    filter: &TokenFilter,
+) -> (HashMap<String, f64>, FilterStats) {
+    let mut counter: WordCounter<f64> = WordCounter::with_count_type(filter);
+    for line in logs {
+        counter.add_line_weighted(line, weights.weight(detect_level(line)));
+    }
+    counter.into_parts()
+}
+
+/// Ranks a map of floating-point scores and keeps the K highest.
+pub(crate) fn select_top_k_scored(score_map: HashMap<String, f64>, k: usize) -> Vec<(String, f64)> {
+    select_top_k_counts(score_map, k, TieBreak::Alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test level detection including aliases and lines without a level
+    #[test]
+    fn test_detect_level() {
+        assert_eq!(detect_level("Error: Disk full"), Some(LogLevel::Error));
+        assert_eq!(detect_level("[warning] memory low"), Some(LogLevel::Warn));
+        assert_eq!(detect_level("INFO retry after error"), Some(LogLevel::Info));
+        assert_eq!(detect_level("network down"), None);
+    }
+
+    /// Test parsing of the weight specification
+    #[test]
+    fn test_parse_weights() {
+        let weights: SeverityWeights = "ERROR=5,warn=2".parse().unwrap();
+
+        assert_eq!(weights.weight(Some(LogLevel::Error)), 5.0);
+        assert_eq!(weights.weight(Some(LogLevel::Warn)), 2.0);
+        assert_eq!(weights.weight(Some(LogLevel::Info)), 1.0);
+        assert_eq!(weights.weight(None), 1.0);
+        assert!("ERROR".parse::<SeverityWeights>().is_err());
+        assert!("LOUD=3".parse::<SeverityWeights>().is_err());
+    }
+
+    /// Test that one ERROR occurrence outranks two INFO occurrences at 5x/1x
+    #[test]
+    fn test_error_word_outranks_info_words() {
+        let logs = vec![
+            "ERROR kernel".to_string(),
+            "INFO retry".to_string(),
+            "INFO retry".to_string(),
+        ];
+        let weights: SeverityWeights = "ERROR=5,INFO=1".parse().unwrap();
+
+        let result = top_k_weighted_by_severity(&logs, 4, &weights);
+
+        assert_eq!(
+            result,
+            vec![
+                ("error".to_string(), 5.0),
+                ("kernel".to_string(), 5.0),
+                ("info".to_string(), 2.0),
+                ("retry".to_string(), 2.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Word shapes: tokens reduced to their letter/digit pattern, so that
+//! `error404` and `abcde123` both count as `aaaaa000`.
+
+use std::collections::HashMap;
+
+use crate::filter::{FilterStats, TokenFilter};
+use crate::{add_bounded, for_each_word_capped};
+
+/// Returns the shape of a word: letters become `a` and digits `0`.
+///
+/// With `keep_case` uppercase letters become `A` instead. Any other
+/// character is kept as it is.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::shape::word_shape;
+///
+/// assert_eq!(word_shape("error404", false), "aaaaa000");
+/// assert_eq!(word_shape("12ab", false), "00aa");
+/// assert_eq!(word_shape("Http2", true), "Aaaa0");
+/// ```
+pub fn word_shape(word: &str, keep_case: bool) -> String {
+    word.chars()
+        .map(|c| match c {
+            '0'..='9' => '0',
+            'A'..='Z' if keep_case => 'A',
+            c if c.is_alphabetic() => 'a',
+            c => c,
+        })
+        .collect()
+}
+
+/// Counts the shapes of all words whose lowercased form the filter keeps.
+///
+/// Without `keep_case` the words are the usual lowercased tokens of
+/// [`crate::for_each_word`]. With it, the same ASCII letter/digit runs are taken
+/// from the original line so their case survives.
+pub(crate) fn shape_counts(
+    logs: &[String],
+    keep_case: bool,
+    filter: &TokenFilter,
+) -> (HashMap<String, usize>, FilterStats) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut stats = FilterStats::for_filter(filter);
+    let mut add = |word: &str, lowercase: &str| {
+        if filter.admit(lowercase, &mut stats) {
+            let shape = word_shape(word, keep_case);
+            add_bounded(&mut counts, &mut stats, filter.max_unique, &shape, 1, 1);
+        }
+    };
+
+    for line in logs {
+        if keep_case {
+            line.split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .take(filter.max_tokens_per_line.unwrap_or(usize::MAX))
+                .for_each(|word| add(word, &word.to_ascii_lowercase()));
+        } else {
+            for_each_word_capped(line, filter.max_tokens_per_line, |word| add(word, word));
+        }
+    }
+
+    (counts, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::select_top_k;
+
+    /// Test that tokens of one shape aggregate and other shapes stay apart
+    #[test]
+    fn test_shapes_aggregate() {
+        let logs = vec![
+            "error404 fatal500 12ab".to_string(),
+            "Disk 99zz ERROR".to_string(),
+        ];
+        let filter = TokenFilter::default();
+
+        let (counts, _) = shape_counts(&logs, false, &filter);
+        assert_eq!(
+            select_top_k(counts, 5),
+            vec![
+                ("00aa".to_string(), 2),
+                ("aaaaa000".to_string(), 2),
+                ("aaaa".to_string(), 1),
+                ("aaaaa".to_string(), 1),
+            ]
+        );
+
+        let (counts, _) = shape_counts(&logs, true, &filter);
+        assert_eq!(counts["AAAAA"], 1);
+        assert_eq!(counts["Aaaa"], 1);
+        assert_eq!(counts["aaaaa000"], 2);
+    }
+}
@@ -0,0 +1,77 @@
+//! On-disk running totals for incremental analysis across runs.
+//!
+//! The state file is a JSON object mapping each word to its cumulative
+//! count, e.g. `{"disk":4,"error":7}`, with keys in sorted order so
+//! successive versions diff cleanly.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+
+/// Loads the cumulative counts stored at `path`.
+///
+/// A missing or empty file is an empty state, so the first run needs no
+/// setup.
+pub fn load_counts(path: &str) -> Result<HashMap<String, usize>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("Unable to read state file '{}': {}", path, e)),
+    };
+    if text.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+    serde_json::from_str(&text).map_err(|e| format!("invalid state file '{}': {}", path, e))
+}
+
+/// Saves cumulative counts to `path`.
+///
+/// The counts are written to a temporary file next to `path` first and
+/// then renamed over it, so an interrupted run leaves the old state intact.
+pub fn save_counts(path: &str, counts: &HashMap<String, usize>) -> Result<(), String> {
+    let sorted: BTreeMap<&String, &usize> = counts.iter().collect();
+    let json = serde_json::to_string(&sorted).map_err(|e| e.to_string())?;
+    let temporary = format!("{}.tmp", path);
+    fs::write(&temporary, json + "\n")
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|e| format!("Unable to write state file '{}': {}", path, e))
+}
+
+/// Adds new counts to the stored totals at `path` and saves the result.
+///
+/// # Returns
+///
+/// The updated cumulative counts.
+pub fn accumulate(
+    path: &str,
+    new_counts: HashMap<String, usize>,
+) -> Result<HashMap<String, usize>, String> {
+    let mut totals = load_counts(path)?;
+    for (word, count) in new_counts {
+        *totals.entry(word).or_insert(0) += count;
+    }
+    save_counts(path, &totals)?;
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_words;
+    use crate::test_util::temp_file;
+
+    /// Test that successive runs add up and the file stays sorted JSON
+    #[test]
+    fn test_accumulate_across_runs() {
+        let path = temp_file("state_unit.json", "");
+
+        accumulate(&path, count_words(&["error disk".to_string()])).unwrap();
+        let totals = accumulate(&path, count_words(&["error timeout".to_string()])).unwrap();
+
+        assert_eq!(totals["error"], 2);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"disk\":1,\"error\":2,\"timeout\":1}\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Summary statistics of the word-frequency distribution.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::count_words;
+
+/// Distribution of per-word counts: how evenly occurrences spread over
+/// the vocabulary.
+///
+/// For empty input every field is zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FreqStats {
+    /// Number of distinct words
+    pub distinct_words: usize,
+    /// Smallest count of any word
+    pub min: usize,
+    /// Largest count of any word
+    pub max: usize,
+    /// Average count per word
+    pub mean: f64,
+    /// Middle count; the average of the two middle counts for an even
+    /// number of words
+    pub median: f64,
+    /// 90th percentile count (nearest-rank)
+    pub p90: usize,
+    /// 99th percentile count (nearest-rank)
+    pub p99: usize,
+}
+
+impl FreqStats {
+    /// Computes the statistics of an existing frequency map.
+    pub fn from_counts(frequency_map: &HashMap<String, usize>) -> FreqStats {
+        let mut counts: Vec<usize> = frequency_map.values().copied().collect();
+        if counts.is_empty() {
+            return FreqStats::default();
+        }
+        counts.sort_unstable();
+
+        let n = counts.len();
+        let total: usize = counts.iter().sum();
+        let median = if n % 2 == 1 {
+            counts[n / 2] as f64
+        } else {
+            (counts[n / 2 - 1] + counts[n / 2]) as f64 / 2.0
+        };
+        FreqStats {
+            distinct_words: n,
+            min: counts[0],
+            max: counts[n - 1],
+            mean: total as f64 / n as f64,
+            median,
+            p90: nearest_rank(&counts, 90),
+            p99: nearest_rank(&counts, 99),
+        }
+    }
+}
+
+impl fmt::Display for FreqStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} distinct words, count min {}, max {}, mean {:.2}, median {}, p90 {}, p99 {}",
+            self.distinct_words, self.min, self.max, self.mean, self.median, self.p90, self.p99
+        )
+    }
+}
+
+/// Returns the smallest count at or above `percent` % of the sorted counts.
+fn nearest_rank(sorted: &[usize], percent: usize) -> usize {
+    let rank = (percent * sorted.len()).div_ceil(100);
+    sorted[rank.max(1) - 1]
+}
+
+/// Summarizes the distribution of word counts across all log lines.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::summary::frequency_stats;
+///
+/// let logs = vec!["error error error disk timeout".to_string()];
+/// let stats = frequency_stats(&logs);
+/// assert_eq!((stats.distinct_words, stats.max), (3, 3));
+/// assert_eq!(stats.median, 1.0);
+/// ```
+pub fn frequency_stats(logs: &[String]) -> FreqStats {
+    FreqStats::from_counts(&count_words(logs))
+}
+
+/// Computes the Shannon entropy, in bits, of the token distribution of a
+/// frequency map: `-Σ p log2 p` over the share `p` of every word.
+///
+/// A log dominated by one word scores near zero, and `n` equally frequent
+/// words score `log2 n`, the maximum. An empty map scores zero.
+pub fn entropy(frequency_map: &HashMap<String, usize>) -> f64 {
+    let total: usize = frequency_map.values().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    frequency_map
+        .values()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Computes the Shannon entropy of the words across all log lines.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::summary::word_entropy;
+///
+/// assert_eq!(word_entropy(&["error disk timeout retry".to_string()]), 2.0);
+/// assert_eq!(word_entropy(&[]), 0.0);
+/// ```
+pub fn word_entropy(logs: &[String]) -> f64 {
+    entropy(&count_words(logs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test median and percentiles on a known distribution
+    #[test]
+    fn test_frequency_stats_known_distribution() {
+        // Ten words occurring 1, 2, ..., 10 times
+        let logs: Vec<String> = (1..=10)
+            .map(|count| vec![format!("w{}", count); count].join(" "))
+            .collect();
+
+        let stats = frequency_stats(&logs);
+
+        assert_eq!(stats.distinct_words, 10);
+        assert_eq!((stats.min, stats.max), (1, 10));
+        assert_eq!(stats.mean, 5.5);
+        assert_eq!(stats.median, 5.5);
+        assert_eq!(stats.p90, 9);
+        assert_eq!(stats.p99, 10);
+    }
+
+    /// Test that empty input yields zeroed statistics
+    #[test]
+    fn test_frequency_stats_empty() {
+        assert_eq!(frequency_stats(&[]), FreqStats::default());
+    }
+
+    /// Test that a skewed distribution has lower entropy than a uniform one
+    #[test]
+    fn test_entropy_skewed_vs_uniform() {
+        let skewed = vec!["error error error error error error error disk".to_string()];
+        let uniform = vec!["error disk timeout retry".to_string()];
+
+        let low = word_entropy(&skewed);
+        let high = word_entropy(&uniform);
+
+        assert!(low < high, "{} should be below {}", low, high);
+        assert!((low - 0.5436).abs() < 1e-4, "got {}", low);
+        assert_eq!(high, 2.0);
+        assert_eq!(word_entropy(&["error error".to_string()]), 0.0);
+    }
+}
@@ -0,0 +1,13 @@
+//! Helpers shared by the unit tests of several modules.
+
+/// Writes `contents` to a uniquely named file in the temp directory.
+pub(crate) fn temp_file(name: &str, contents: &str) -> String {
+    temp_bytes(name, contents.as_bytes())
+}
+
+/// Writes raw bytes to a uniquely named file in the temp directory.
+pub(crate) fn temp_bytes(name: &str, contents: &[u8]) -> String {
+    let path = std::env::temp_dir().join(format!("lwa_{}_{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
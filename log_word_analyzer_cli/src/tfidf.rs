@@ -0,0 +1,98 @@
+//! TF-IDF scoring that treats each input file as one document.
+
+use std::collections::HashMap;
+
+use crate::count_words;
+use crate::severity::select_top_k_scored;
+
+/// Computes TF-IDF scores for every word of every document.
+///
+/// The term frequency is a word's count divided by the document's token
+/// total, and the inverse document frequency is `ln(N / df)` where `N` is
+/// the number of documents and `df` the number of documents containing the
+/// word. Words present in every document therefore score `0.0`.
+///
+/// # Arguments
+///
+/// * `documents` - Word counts per document, e.g. from [`count_words`]
+///
+/// # Returns
+///
+/// One score map per document, in the same order as the input.
+pub fn tfidf_scores(documents: &[HashMap<String, usize>]) -> Vec<HashMap<String, f64>> {
+    // Count in how many documents each word occurs
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for counts in documents {
+        for word in counts.keys() {
+            *document_frequency.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    let document_count = documents.len() as f64;
+    documents
+        .iter()
+        .map(|counts| {
+            let total_tokens: usize = counts.values().sum();
+            counts
+                .iter()
+                .map(|(word, &count)| {
+                    let tf = count as f64 / total_tokens as f64;
+                    let idf = (document_count / document_frequency[word.as_str()] as f64).ln();
+                    (word.clone(), tf * idf)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Finds the top K most distinctive words of each document by TF-IDF.
+///
+/// # Arguments
+///
+/// * `documents` - The log lines of each document (typically one per file)
+/// * `k` - The number of words to return per document
+///
+/// # Returns
+///
+/// For each document, `(word, score)` tuples sorted by score (descending)
+/// and alphabetically for ties.
+pub fn top_k_tfidf(documents: &[Vec<String>], k: usize) -> Vec<Vec<(String, f64)>> {
+    let counts: Vec<HashMap<String, usize>> =
+        documents.iter().map(|logs| count_words(logs)).collect();
+    tfidf_scores(&counts)
+        .into_iter()
+        .map(|scores| select_top_k_scored(scores, k))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    /// Test that a word unique to one file outscores a word shared by both
+    #[test]
+    fn test_unique_word_scores_higher_than_shared_word() {
+        let documents = vec![
+            lines(&["error timeout", "error timeout", "error"]),
+            lines(&["error disk", "error"]),
+        ];
+
+        let result = top_k_tfidf(&documents, 2);
+
+        // "timeout" only occurs in the first file, "error" in both
+        assert_eq!(result[0][0].0, "timeout");
+        assert!((result[0][0].1 - 0.4 * 2f64.ln()).abs() < 1e-12);
+        assert_eq!(result[0][1], ("error".to_string(), 0.0));
+        assert_eq!(result[1][0].0, "disk");
+    }
+
+    /// Test that empty input yields no documents
+    #[test]
+    fn test_no_documents() {
+        assert!(top_k_tfidf(&[], 3).is_empty());
+    }
+}
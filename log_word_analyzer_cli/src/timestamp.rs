@@ -0,0 +1,393 @@
+//! Timestamp parsing for timestamped log lines, time-range filtering and
+//! the time span over which words occur.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::{Serialize, Serializer};
+
+use crate::for_each_word_capped;
+
+/// Matches an RFC3339 date-time, also accepting a space separator and a
+/// missing offset as commonly written by loggers.
+static DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(\d{4})-(\d{2})-(\d{2})[Tt ](\d{2}):(\d{2}):(\d{2})(?:\.(\d{1,9}))?(?:([Zz])|([+-])(\d{2}):(\d{2}))?",
+    )
+    .expect("date-time pattern is valid")
+});
+
+/// A point in time as seconds and nanoseconds since the Unix epoch (UTC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    /// Whole seconds since 1970-01-01T00:00:00Z
+    pub seconds: i64,
+    /// Sub-second part in nanoseconds
+    pub nanos: u32,
+}
+
+impl Timestamp {
+    /// Finds and parses the first date-time appearing in a log line.
+    ///
+    /// Besides strict RFC3339, a space between date and time is accepted,
+    /// and a date-time without offset is read as UTC.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::timestamp::Timestamp;
+    ///
+    /// let at = Timestamp::find("[2024-03-01 10:15:00] ERROR disk full").unwrap();
+    /// assert_eq!(at, "2024-03-01T10:15:00Z".parse().unwrap());
+    /// assert_eq!(Timestamp::find("ERROR disk full"), None);
+    /// ```
+    pub fn find(line: &str) -> Option<Timestamp> {
+        DATE_TIME
+            .captures_iter(line)
+            .find_map(|caps| Self::from_captures(&caps))
+    }
+
+    /// Converts regex captures to a timestamp, rejecting impossible dates.
+    fn from_captures(caps: &Captures) -> Option<Timestamp> {
+        let number = |i: usize| caps.get(i).map(|m| m.as_str().parse::<i64>().ok())?;
+        let (year, month, day) = (number(1)?, number(2)?, number(3)?);
+        let (hour, minute, second) = (number(4)?, number(5)?, number(6)?);
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+        let nanos = match caps.get(7) {
+            Some(fraction) => {
+                let digits = fraction.as_str();
+                digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32)
+            }
+            None => 0,
+        };
+        let offset = match caps.get(9) {
+            Some(sign) => {
+                let (offset_hour, offset_minute) = (number(10)?, number(11)?);
+                if offset_hour > 23 || offset_minute > 59 {
+                    return None;
+                }
+                let offset = offset_hour * 3600 + offset_minute * 60;
+                if sign.as_str() == "-" {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            None => 0,
+        };
+        let days = days_from_civil(year, month, day);
+        let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+        Some(Timestamp { seconds, nanos })
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    /// Parses a complete RFC3339 timestamp such as `2024-03-01T10:15:00Z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DATE_TIME
+            .captures(s)
+            .filter(|caps| caps.get(0).is_some_and(|m| m.len() == s.len()))
+            .and_then(|caps| Self::from_captures(&caps))
+            .ok_or_else(|| format!("invalid RFC3339 timestamp '{}'", s))
+    }
+}
+
+impl Serialize for Timestamp {
+    /// Writes the timestamp in its RFC3339 [`Display`](fmt::Display) form.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats the timestamp as RFC3339 in UTC, such as
+    /// `2024-03-01T10:15:00Z`, with a fraction only when it is nonzero.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.seconds.div_euclid(86_400));
+        let time = self.seconds.rem_euclid(86_400);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )?;
+        if self.nanos > 0 {
+            let fraction = format!("{:09}", self.nanos);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+/// Returns true for Gregorian leap years.
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Returns the number of days in a month of the given year.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Counts days from 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since 1970-01-01 back to a civil date, the inverse of
+/// [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Keeps only lines whose timestamp lies within an inclusive time range.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TimeRange {
+    /// Lines stamped before this instant are dropped
+    pub since: Option<Timestamp>,
+    /// Lines stamped after this instant are dropped
+    pub until: Option<Timestamp>,
+}
+
+impl TimeRange {
+    /// Returns true when neither bound is configured.
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Returns true when the timestamp satisfies both configured bounds.
+    pub fn contains(&self, at: Timestamp) -> bool {
+        self.since.is_none_or(|since| at >= since) && self.until.is_none_or(|until| at <= until)
+    }
+
+    /// Keeps the lines stamped within `[since, until]`.
+    ///
+    /// Lines without a parseable timestamp are dropped, since they cannot
+    /// be placed inside the range.
+    pub fn select(&self, logs: Vec<String>) -> Vec<String> {
+        logs.into_iter().filter(|line| self.admit(line)).collect()
+    }
+
+    /// Returns true when the line carries a timestamp within the range.
+    pub fn admit(&self, line: &str) -> bool {
+        Timestamp::find(line).is_some_and(|at| self.contains(at))
+    }
+}
+
+/// The earliest and latest timestamps of the lines a word occurs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSpan {
+    /// Timestamp of the earliest line
+    pub first: Timestamp,
+    /// Timestamp of the latest line
+    pub last: Timestamp,
+}
+
+impl TimeSpan {
+    /// Widens the span to include `at`.
+    fn extend(&mut self, at: Timestamp) {
+        self.first = self.first.min(at);
+        self.last = self.last.max(at);
+    }
+}
+
+/// Finds the time span over which each of `words` occurs, telling
+/// persistent words from transient ones.
+///
+/// Each line is dated by its first timestamp, usually the leading one.
+/// Lines need not be in time order, and lines without a parseable
+/// timestamp do not affect any span; words seen only on such lines have
+/// no entry.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::timestamp::word_time_spans;
+///
+/// let logs: Vec<String> = [
+///     "2024-03-01T10:00:00Z error disk",
+///     "2024-03-01T10:05:00Z error network",
+/// ]
+/// .map(String::from)
+/// .to_vec();
+/// let spans = word_time_spans(&logs, &["error".to_string(), "disk".to_string()]);
+///
+/// assert_eq!(spans["error"].first.to_string(), "2024-03-01T10:00:00Z");
+/// assert_eq!(spans["error"].last.to_string(), "2024-03-01T10:05:00Z");
+/// assert_eq!(spans["disk"].first, spans["disk"].last);
+/// ```
+pub fn word_time_spans(logs: &[String], words: &[String]) -> HashMap<String, TimeSpan> {
+    spans_by_key(logs, words, None, |word| Some(word.to_string()))
+}
+
+/// Like [`word_time_spans`], but a token occurs as the word `key` maps it
+/// to, such as its lemma, and only the first `max_tokens` tokens of a line
+/// are looked at.
+pub(crate) fn spans_by_key(
+    logs: &[String],
+    words: &[String],
+    max_tokens: Option<usize>,
+    key: impl Fn(&str) -> Option<String>,
+) -> HashMap<String, TimeSpan> {
+    let mut spans: HashMap<String, Option<TimeSpan>> =
+        words.iter().map(|word| (word.clone(), None)).collect();
+
+    for line in logs {
+        let Some(at) = Timestamp::find(line) else {
+            continue;
+        };
+        for_each_word_capped(line, max_tokens, |token| {
+            let Some(span) = key(token).and_then(|word| spans.get_mut(&word)) else {
+                return;
+            };
+            match span {
+                Some(span) => span.extend(at),
+                None => {
+                    *span = Some(TimeSpan {
+                        first: at,
+                        last: at,
+                    })
+                }
+            }
+        });
+    }
+    spans
+        .into_iter()
+        .filter_map(|(word, span)| Some((word, span?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> Timestamp {
+        s.parse().unwrap()
+    }
+
+    /// Test conversion to Unix time, including fractions and offsets
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(ts("1970-01-01T00:00:00Z").seconds, 0);
+        assert_eq!(ts("2000-03-01T00:00:00Z").seconds, 951_868_800);
+        assert_eq!(ts("2024-02-29T12:00:00+02:00"), ts("2024-02-29T10:00:00Z"));
+        assert_eq!(ts("1969-12-31T23:59:59Z").seconds, -1);
+        assert_eq!(ts("2024-01-01T00:00:00.25Z").nanos, 250_000_000);
+        assert!("2023-02-29T00:00:00Z".parse::<Timestamp>().is_err());
+        assert!("2024-01-01T24:00:00Z".parse::<Timestamp>().is_err());
+        assert!(
+            "2024-01-01T00:00:00Z trailing"
+                .parse::<Timestamp>()
+                .is_err()
+        );
+    }
+
+    /// Test formatting back to RFC3339 in UTC
+    #[test]
+    fn test_display_rfc3339() {
+        for text in [
+            "1970-01-01T00:00:00Z",
+            "2024-02-29T23:59:59Z",
+            "1969-07-20T20:17:40Z",
+            "2000-03-01T00:00:00.25Z",
+        ] {
+            assert_eq!(ts(text).to_string(), text);
+        }
+        assert_eq!(
+            ts("2024-03-01T01:00:00+02:00").to_string(),
+            "2024-02-29T23:00:00Z"
+        );
+    }
+
+    /// Test that spans cover every stamped occurrence and skip unstamped lines
+    #[test]
+    fn test_word_time_spans() {
+        let logs: Vec<String> = [
+            "2024-01-01T10:05:00Z error disk",
+            "2024-01-01T10:00:00Z error retry",
+            "no timestamp error",
+            "2024-01-01T10:30:00Z error",
+            "unstamped orphan",
+        ]
+        .map(String::from)
+        .to_vec();
+        let words = ["error", "disk", "orphan"].map(String::from);
+
+        let spans = word_time_spans(&logs, &words);
+
+        assert_eq!(
+            spans["error"],
+            TimeSpan {
+                first: ts("2024-01-01T10:00:00Z"),
+                last: ts("2024-01-01T10:30:00Z"),
+            }
+        );
+        assert_eq!(spans["disk"].first, ts("2024-01-01T10:05:00Z"));
+        assert_eq!(spans["disk"].last, ts("2024-01-01T10:05:00Z"));
+        assert!(!spans.contains_key("orphan"));
+    }
+
+    /// Test that the time range is inclusive and drops unstamped lines
+    #[test]
+    fn test_time_range_select() {
+        let range = TimeRange {
+            since: Some(ts("2024-01-01T10:00:00Z")),
+            until: Some(ts("2024-01-01T11:00:00Z")),
+        };
+        let logs = vec![
+            "2024-01-01T09:59:59Z early".to_string(),
+            "2024-01-01T10:00:00Z start".to_string(),
+            "no timestamp".to_string(),
+            "2024-01-01T12:30:00+02:00 inside".to_string(),
+            "2024-01-01T11:00:01Z late".to_string(),
+        ];
+        assert_eq!(
+            range.select(logs),
+            vec![
+                "2024-01-01T10:00:00Z start",
+                "2024-01-01T12:30:00+02:00 inside"
+            ]
+        );
+    }
+}
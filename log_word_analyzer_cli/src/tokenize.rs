@@ -0,0 +1,451 @@
+//! Pluggable tokenization for project-specific log formats.
+//!
+//! The built-in counting functions split lines with [`DefaultTokenizer`].
+//! Implement [`Tokenizer`] and pass it to
+//! [`count_words_with`](crate::count_words_with) to count SQL statements,
+//! access-log fields or anything else without changing the crate, or wrap
+//! a custom line normalization in [`NormalizingTokenizer`].
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::for_each_word;
+
+/// Splits a log line into the words that should be counted.
+pub trait Tokenizer {
+    /// Returns the tokens of one line, in order; repeated tokens are
+    /// counted once per occurrence.
+    fn tokenize(&self, line: &str) -> Vec<String>;
+}
+
+/// The crate's standard tokenization: lowercase, then split on every
+/// character that is not an ASCII letter or digit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTokenizer;
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        for_each_word(line, |word| tokens.push(word.to_string()));
+        tokens
+    }
+}
+
+/// Splits on whitespace only, keeping punctuation inside tokens such as
+/// `file.txt` or `/api/v1`.
+///
+/// Trailing punctuation is stripped from each token, as are leading
+/// quotes and opening brackets, so `"/api/v1",` becomes `/api/v1`.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{Tokenizer, WhitespaceTokenizer};
+///
+/// let tokens = WhitespaceTokenizer.tokenize("GET /api/v1, (file.TXT) ...");
+/// assert_eq!(tokens, ["get", "/api/v1", "file.txt"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        line.split_whitespace()
+            .map(|field| {
+                field
+                    .trim_start_matches(['"', '\'', '`', '(', '[', '{', '<'])
+                    .trim_end_matches(|c: char| c.is_ascii_punctuation())
+            })
+            .filter(|token| !token.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+}
+
+/// Splits on exactly one separator character, such as a tab or comma in
+/// fixed-format logs, counting every lowercased field as a whole token.
+///
+/// Fields keep their inner spaces and punctuation. With trimming, the
+/// whitespace around each field is removed; empty fields are skipped.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{FieldTokenizer, Tokenizer};
+///
+/// let tokenizer = FieldTokenizer::new(',').trimmed(true);
+/// assert_eq!(tokenizer.tokenize("GET, /api/v1 ,,Disk Full"), ["get", "/api/v1", "disk full"]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FieldTokenizer {
+    separator: char,
+    trim: bool,
+}
+
+impl FieldTokenizer {
+    /// Creates a tokenizer splitting on `separator`, without trimming.
+    pub fn new(separator: char) -> Self {
+        FieldTokenizer {
+            separator,
+            trim: false,
+        }
+    }
+
+    /// Sets whether the whitespace around each field is removed.
+    pub fn trimmed(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+}
+
+impl Tokenizer for FieldTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        line.split(self.separator)
+            .map(|field| if self.trim { field.trim() } else { field })
+            .filter(|field| !field.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+}
+
+/// The standard split on non-alphanumeric characters, except that an
+/// apostrophe between two letters stays in the word, so contractions
+/// such as `don't` and `it's` count as one token.
+///
+/// The typographic apostrophe `’` counts as `'`, so both spellings of a
+/// contraction count together. Quotes at the start or end of a word are
+/// dropped like any other punctuation.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{ApostropheTokenizer, Tokenizer};
+///
+/// let tokens = ApostropheTokenizer.tokenize("Don’t retry: 'it's' the disk's 90's");
+/// assert_eq!(tokens, ["don't", "retry", "it's", "the", "disk's", "90", "s"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApostropheTokenizer;
+
+impl Tokenizer for ApostropheTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        let chars: Vec<char> = line
+            .to_lowercase()
+            .chars()
+            .map(|c| if c == '\u{2019}' { '\'' } else { c })
+            .collect();
+        let is_letter = |index: Option<usize>| {
+            index
+                .and_then(|index| chars.get(index))
+                .is_some_and(char::is_ascii_alphabetic)
+        };
+
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        for (index, &c) in chars.iter().enumerate() {
+            let inner_apostrophe =
+                c == '\'' && is_letter(index.checked_sub(1)) && is_letter(Some(index + 1));
+            if c.is_ascii_alphanumeric() || inner_apostrophe {
+                word.push(c);
+            } else if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+        if !word.is_empty() {
+            tokens.push(word);
+        }
+        tokens
+    }
+}
+
+/// Matches a number directly followed by a common duration, size, rate
+/// or frequency unit, as in `12ms`, `3.5gb` or `100mbps`.
+static NUMBER_WITH_UNIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b\d+(?:\.\d+)?(?:ns|us|µs|ms|sec|min|hr|[kmgtp]ib|[kmgtp]b|[kmg]?bps|[kmg]?hz|[smhdbkgt])\b",
+    )
+    .expect("unit pattern is valid")
+});
+
+/// The standard split on non-alphanumeric characters, except that a
+/// number followed by a unit, such as `12ms`, `3.5GB` or `200MB`, stays
+/// one token instead of splitting into `12` and `ms` or `3` and `5gb`.
+///
+/// Units are lowercased like every word, so `3.5GB` and `3.5gb` count
+/// together. A number glued to anything but a known unit, such as `404x`
+/// or `1.5x`, is tokenized as usual.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{Tokenizer, UnitTokenizer};
+///
+/// let tokens = UnitTokenizer.tokenize("took 12ms, heap 3.5GB (v2 404x)");
+/// assert_eq!(tokens, ["took", "12ms", "heap", "3.5gb", "v2", "404x"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitTokenizer;
+
+impl Tokenizer for UnitTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        let line = line.to_lowercase();
+        let mut tokens = Vec::new();
+        let mut rest = 0;
+        for unit in NUMBER_WITH_UNIT.find_iter(&line) {
+            for_each_word(&line[rest..unit.start()], |word| {
+                tokens.push(word.to_string())
+            });
+            tokens.push(unit.as_str().to_string());
+            rest = unit.end();
+        }
+        for_each_word(&line[rest..], |word| tokens.push(word.to_string()));
+        tokens
+    }
+}
+
+/// The standard split on non-alphanumeric characters, preceded by a
+/// caller-supplied normalization instead of the built-in lowercasing.
+///
+/// The normalizer sees each whole line before it is split, so it can
+/// fold case, strip diacritics (for example with the `deunicode` crate)
+/// or transliterate. Only ASCII letters and digits form words, as with
+/// [`DefaultTokenizer`], and tokens are counted exactly as normalized.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{NormalizingTokenizer, Tokenizer};
+///
+/// // Keep case, so ERROR and error count apart
+/// let tokenizer = NormalizingTokenizer::new(|line: &str| line.to_string());
+/// assert_eq!(tokenizer.tokenize("ERROR: error"), ["ERROR", "error"]);
+/// ```
+pub struct NormalizingTokenizer<F> {
+    normalize: F,
+}
+
+impl<F: Fn(&str) -> String> NormalizingTokenizer<F> {
+    /// Creates a tokenizer that runs `normalize` on every line first.
+    pub fn new(normalize: F) -> Self {
+        NormalizingTokenizer { normalize }
+    }
+}
+
+impl<F: Fn(&str) -> String> Tokenizer for NormalizingTokenizer<F> {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        (self.normalize)(line)
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Produces one lowercased token per match of a regular expression.
+///
+/// # Example
+///
+/// ```
+/// use log_word_analyzer_cli::tokenize::{RegexTokenizer, Tokenizer};
+/// use regex::Regex;
+///
+/// // Keep dotted identifiers such as `db.users` in one piece
+/// let tokenizer = RegexTokenizer::new(Regex::new(r"[\w.]+").unwrap());
+/// assert_eq!(tokenizer.tokenize("SELECT * FROM db.users"), ["select", "from", "db.users"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexTokenizer {
+    pattern: Regex,
+}
+
+impl RegexTokenizer {
+    /// Creates a tokenizer emitting every non-overlapping match of `pattern`.
+    pub fn new(pattern: Regex) -> Self {
+        RegexTokenizer { pattern }
+    }
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        self.pattern
+            .find_iter(line)
+            .filter(|found| !found.is_empty())
+            .map(|found| found.as_str().to_lowercase())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_words, count_words_with, top_k_words_with};
+
+    /// Counts HTTP status codes of access-log lines and ignores everything else
+    struct StatusTokenizer;
+
+    impl Tokenizer for StatusTokenizer {
+        fn tokenize(&self, line: &str) -> Vec<String> {
+            line.split_whitespace()
+                .filter(|field| field.len() == 3 && field.bytes().all(|b| b.is_ascii_digit()))
+                .map(|field| format!("status_{}", field))
+                .collect()
+        }
+    }
+
+    /// Test that the default tokenizer matches the built-in counting
+    #[test]
+    fn test_default_tokenizer_matches_count_words() {
+        let logs = vec![
+            "Error: disk full".to_string(),
+            "error--network_down 42".to_string(),
+        ];
+
+        assert_eq!(
+            count_words_with(&logs, &DefaultTokenizer),
+            count_words(&logs)
+        );
+    }
+
+    /// Test counting through a custom trait implementation
+    #[test]
+    fn test_custom_tokenizer() {
+        let logs = vec![
+            "GET /index.html 200 5120".to_string(),
+            "GET /missing 404 0".to_string(),
+            "POST /login 200 1280".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 2, &StatusTokenizer);
+
+        assert_eq!(
+            result,
+            vec![("status_200".to_string(), 2), ("status_404".to_string(), 1)]
+        );
+    }
+
+    /// Test that only outer punctuation is stripped from whitespace tokens
+    #[test]
+    fn test_whitespace_tokenizer() {
+        assert_eq!(
+            WhitespaceTokenizer.tokenize("request /api/v1, failed: 'config.yaml' -"),
+            ["request", "/api/v1", "failed", "config.yaml"]
+        );
+    }
+
+    /// Test that contractions stay whole while surrounding quotes go
+    #[test]
+    fn test_apostrophe_tokenizer_keeps_contractions() {
+        let logs = vec![
+            "Don't panic, it's only 'disk' trouble".to_string(),
+            "it’s fine, don't".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 3, &ApostropheTokenizer);
+
+        assert_eq!(
+            result,
+            vec![
+                ("don't".to_string(), 2),
+                ("it's".to_string(), 2),
+                ("disk".to_string(), 1),
+            ]
+        );
+        // Without letters on both sides an apostrophe still splits
+        assert_eq!(ApostropheTokenizer.tokenize("'quoted' o'"), ["quoted", "o"]);
+    }
+
+    /// Test that numbers with units stay whole without leaving a bare unit
+    #[test]
+    fn test_unit_tokenizer_keeps_units() {
+        let logs = vec![
+            "request took 12ms".to_string(),
+            "request took 200ms, retry took 12MS".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 10, &UnitTokenizer);
+
+        assert_eq!(
+            result,
+            vec![
+                ("took".to_string(), 3),
+                ("12ms".to_string(), 2),
+                ("request".to_string(), 2),
+                ("200ms".to_string(), 1),
+                ("retry".to_string(), 1),
+            ]
+        );
+        // A decimal point inside the number does not split it
+        assert_eq!(UnitTokenizer.tokenize("3.5gb/200MB"), ["3.5gb", "200mb"]);
+    }
+
+    /// Test counting whole CSV field values, with and without trimming
+    #[test]
+    fn test_field_tokenizer_counts_csv_fields() {
+        let logs = vec![
+            "GET,/api/v1,200,disk full".to_string(),
+            "GET, /api/v1,500,".to_string(),
+            "POST,/login,200,disk full".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 3, &FieldTokenizer::new(',').trimmed(true));
+        assert_eq!(
+            result,
+            vec![
+                ("/api/v1".to_string(), 2),
+                ("200".to_string(), 2),
+                ("disk full".to_string(), 2),
+            ]
+        );
+
+        // Untrimmed, the leading space makes a distinct field
+        let untrimmed = count_words_with(&logs, &FieldTokenizer::new(','));
+        assert_eq!(untrimmed.get(" /api/v1"), Some(&1));
+        assert_eq!(untrimmed.get("/api/v1"), Some(&1));
+    }
+
+    /// Test that a diacritic-stripping normalizer merges "café" and "cafe"
+    #[test]
+    fn test_normalizing_tokenizer_strips_diacritics() {
+        let strip = |line: &str| {
+            line.to_lowercase()
+                .chars()
+                .map(|c| match c {
+                    'à' | 'á' | 'â' | 'ä' => 'a',
+                    'è' | 'é' | 'ê' | 'ë' => 'e',
+                    'ì' | 'í' | 'î' | 'ï' => 'i',
+                    'ò' | 'ó' | 'ô' | 'ö' => 'o',
+                    'ù' | 'ú' | 'û' | 'ü' => 'u',
+                    c => c,
+                })
+                .collect::<String>()
+        };
+        let logs = vec![
+            "Café opened".to_string(),
+            "cafe closed, café reopened".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 1, &NormalizingTokenizer::new(strip));
+
+        assert_eq!(result, vec![("cafe".to_string(), 3)]);
+        // The built-in tokenization splits at the accent instead
+        assert_eq!(count_words(&logs).get("caf"), Some(&2));
+    }
+
+    /// Test that the regex tokenizer lowercases and counts every match
+    #[test]
+    fn test_regex_tokenizer() {
+        let tokenizer = RegexTokenizer::new(Regex::new(r"\w+@\w+").unwrap());
+        let logs = vec![
+            "login Alice@host ok".to_string(),
+            "alice@host bob@host".to_string(),
+        ];
+
+        let result = top_k_words_with(&logs, 5, &tokenizer);
+
+        assert_eq!(
+            result,
+            vec![("alice@host".to_string(), 2), ("bob@host".to_string(), 1)]
+        );
+    }
+}
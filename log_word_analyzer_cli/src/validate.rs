@@ -0,0 +1,59 @@
+//! Checking that log lines follow an expected format, as a log-format
+//! linter would.
+
+use regex::Regex;
+
+/// Finds the lines that do not match `format`.
+///
+/// The pattern may match anywhere in a line; anchor it with `^` and `$`
+/// to demand that whole lines conform.
+///
+/// # Returns
+///
+/// The numbers of the nonconforming lines, starting at 1.
+///
+/// # Example
+///
+/// ```
+/// use regex::Regex;
+/// use log_word_analyzer_cli::validate::nonconforming_lines;
+///
+/// let format = Regex::new(r"^\d{4}-\d{2}-\d{2} (INFO|WARN|ERROR) ").unwrap();
+/// let logs: Vec<String> = [
+///     "2024-03-01 INFO started",
+///     "started without a date",
+///     "2024-03-01 ERROR disk full",
+/// ]
+/// .map(String::from)
+/// .to_vec();
+/// assert_eq!(nonconforming_lines(&logs, &format), vec![2]);
+/// ```
+pub fn nonconforming_lines(logs: &[String], format: &Regex) -> Vec<usize> {
+    logs.iter()
+        .enumerate()
+        .filter(|(_, line)| !format.is_match(line))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that every nonconforming line is reported, including blank ones
+    #[test]
+    fn test_nonconforming_lines() {
+        let format = Regex::new(r"^\[(INFO|WARN|ERROR)\] \S").unwrap();
+        let logs: Vec<String> = [
+            "[INFO] service up",
+            "[DEBUG] not an allowed level",
+            "",
+            "[ERROR] disk full",
+            "  [WARN] indented",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(nonconforming_lines(&logs, &format), vec![2, 3, 5]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Word counts weighted by a numeric field of each line, such as the
+//! bytes transferred in an access log.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::filter::{FilterStats, TokenFilter};
+use crate::{WordCounter, select_top_k};
+
+/// Selects the whitespace-separated field holding each line's weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WeightField {
+    /// Position of the field, starting at 1
+    pub index: usize,
+    /// Give lines without a valid weight a weight of 1 instead of skipping them
+    pub default_one: bool,
+}
+
+impl WeightField {
+    /// Creates a selector for the 1-based field `index` that skips lines
+    /// without a valid weight.
+    pub fn new(index: usize) -> Self {
+        WeightField {
+            index,
+            default_one: false,
+        }
+    }
+
+    /// Splits a line into its weight and the text whose words are counted.
+    ///
+    /// The weight field itself is left out of the text. A missing field or
+    /// one that is not a non-negative integer yields `None`, or weight 1
+    /// with the whole line as text when `default_one` is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use log_word_analyzer_cli::weight::WeightField;
+    ///
+    /// let field = WeightField::new(3);
+    /// assert_eq!(field.split("GET /index.html 512"), Some((512, "GET /index.html".to_string())));
+    /// assert_eq!(field.split("GET /index.html -"), None);
+    /// ```
+    pub fn split(&self, line: &str) -> Option<(usize, String)> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let weight = self
+            .index
+            .checked_sub(1)
+            .and_then(|position| fields.get(position))
+            .and_then(|field| field.parse::<usize>().ok());
+        match weight {
+            Some(weight) => {
+                let mut text = fields;
+                text.remove(self.index - 1);
+                Some((weight, text.join(" ")))
+            }
+            None if self.default_one => Some((1, line.to_string())),
+            None => None,
+        }
+    }
+}
+
+/// Finds the top K words by summed line weight.
+///
+/// Every occurrence of a word adds its line's weight instead of `1`, so
+/// the words of a few heavy lines can outrank frequent words of light
+/// ones. Lines without a valid weight are handled as described in
+/// [`WeightField::split`].
+///
+/// # Returns
+///
+/// A vector of `(word, weight)` tuples sorted by weight (descending) and
+/// alphabetically for ties.
+pub fn top_k_weighted_by_field(
+    logs: &[String],
+    k: usize,
+    field: WeightField,
+) -> Vec<(String, usize)> {
+    let (counts, _, _) = field_weighted_counts(logs, field, &TokenFilter::default());
+    select_top_k(counts, k)
+}
+
+/// Sums line weights for every word the filter keeps.
+///
+/// # Returns
+///
+/// The weighted counts, the filter statistics and the number of lines
+/// skipped for lacking a valid weight.
+pub(crate) fn field_weighted_counts(
+    logs: &[String],
+    field: WeightField,
+    filter: &TokenFilter,
+) -> (HashMap<String, usize>, FilterStats, usize) {
+    let mut counter = WordCounter::new(filter);
+    let mut skipped = 0;
+
+    for line in logs {
+        let Some((weight, text)) = field.split(line) else {
+            skipped += 1;
+            continue;
+        };
+        counter.add_line_weighted(&text, weight);
+    }
+
+    let (counts, stats) = counter.into_parts();
+    (counts, stats, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that one heavy line outranks more frequent light words
+    #[test]
+    fn test_heavy_line_outranks_frequent_words() {
+        let logs = vec![
+            "GET /health 1".to_string(),
+            "GET /health 1".to_string(),
+            "GET /health 1".to_string(),
+            "GET /download 100".to_string(),
+        ];
+
+        let result = top_k_weighted_by_field(&logs, 3, WeightField::new(3));
+
+        assert_eq!(
+            result,
+            vec![
+                ("get".to_string(), 103),
+                ("download".to_string(), 100),
+                ("health".to_string(), 3),
+            ]
+        );
+    }
+
+    /// Test skipping versus defaulting lines without a valid weight
+    #[test]
+    fn test_unparseable_weight() {
+        let logs = vec![
+            "GET /a 10".to_string(),
+            "GET /b -".to_string(),
+            "GET".to_string(),
+        ];
+        let filter = TokenFilter::default();
+
+        let (counts, _, skipped) = field_weighted_counts(&logs, WeightField::new(3), &filter);
+        assert_eq!((counts["get"], counts.get("b"), skipped), (10, None, 2));
+
+        let fallback = WeightField {
+            index: 3,
+            default_one: true,
+        };
+        let (counts, _, skipped) = field_weighted_counts(&logs, fallback, &filter);
+        assert_eq!((counts["get"], counts["b"], skipped), (12, 1, 0));
+    }
+}